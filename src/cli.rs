@@ -0,0 +1,269 @@
+// Copyright Elasticsearch B.V. and/or licensed to Elasticsearch B.V. under one
+// or more contributor license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Headless sub-commands that operate on the database without opening a window.
+
+use crate::storage::{ExecutableMeta, FileId, SymbStatus, Table, DB};
+use crate::symbolizer::{export_symbfile, import_kernel_symbols, IngestSource, IngestTask};
+use anyhow::{bail, Result};
+use std::path::PathBuf;
+
+const SYMBOLS_USAGE: &str = "\
+Usage: devfiler [--db-dir <dir>] symbols <command>
+
+Commands:
+  add <paths...>                  Ingest symbols from executables and their debug files,
+                                  or from symbfiles named <file-id>.symbfile[.zst]
+  import <file-id> <symbfile>     Ingest symbols from a symbfile for the given file ID
+  export <file-id> <symbfile>     Write symbols to a symbfile, compressed if ending in .zst
+  list                            List executables and their symbolization status
+  remove <file-ids...>            Remove ingested symbols for the given file IDs
+  kernel <release> <paths...>     Import kernel symbols for a kernel release from kallsyms
+                                  dumps, vmlinux images or kernel modules (.ko)
+  kernels                         List kernel images seen in profiles and imported symbols";
+
+/// Run the `symbols` sub-command with the given arguments.
+pub fn symbols(args: &[String]) -> Result<()> {
+    match args.split_first() {
+        Some((cmd, paths)) if cmd == "add" && !paths.is_empty() => add(paths),
+        Some((cmd, rest)) if cmd == "list" && rest.is_empty() => {
+            list();
+            Ok(())
+        }
+        Some((cmd, ids)) if cmd == "remove" && !ids.is_empty() => remove(ids),
+        Some((cmd, [release, paths @ ..])) if cmd == "kernel" && !paths.is_empty() => {
+            kernel(release, paths)
+        }
+        Some((cmd, rest)) if cmd == "kernels" && rest.is_empty() => {
+            kernels();
+            Ok(())
+        }
+        Some((cmd, [id, path])) if cmd == "import" => import(id, path),
+        Some((cmd, [id, path])) if cmd == "export" => export(id, path),
+        _ => bail!("{SYMBOLS_USAGE}"),
+    }
+}
+
+fn add(paths: &[String]) -> Result<()> {
+    let mut num_failed = 0;
+
+    // Executables are paired with separate debug files passed along.
+    let sources = IngestSource::classify(paths.iter().map(PathBuf::from).collect());
+    let num_sources = sources.len();
+
+    for source in sources {
+        let path = source.path().display().to_string();
+        let debug = match source {
+            IngestSource::Executable(ref files) => files
+                .debug
+                .as_ref()
+                .map(|x| format!(" (debug file: {})", x.display()))
+                .unwrap_or_default(),
+            IngestSource::Symbfile { .. } => String::new(),
+        };
+
+        match IngestTask::spawn(source).join() {
+            Ok(file_id) => {
+                let status = DB.executables.get(file_id).map(|x| x.read().symb_status);
+                println!(
+                    "{}  {}  {path}{debug}",
+                    file_id.format_hex(),
+                    status.map_or_else(String::new, status_text),
+                );
+            }
+            Err(e) => {
+                eprintln!("{path}: {e:#}");
+                num_failed += 1;
+            }
+        }
+    }
+
+    if num_failed > 0 {
+        bail!("failed to ingest {num_failed} of {num_sources} files");
+    }
+
+    Ok(())
+}
+
+fn import(id: &str, path: &str) -> Result<()> {
+    let Some(file_id) = parse_file_id(id) else {
+        bail!("invalid file ID: {id}");
+    };
+
+    let source = IngestSource::Symbfile {
+        path: path.into(),
+        file_id: Some(file_id),
+    };
+    IngestTask::spawn(source).join()?;
+
+    let status = DB.executables.get(file_id).map(|x| x.read().symb_status);
+    println!(
+        "{}  {}  {path}",
+        file_id.format_hex(),
+        status.map_or_else(String::new, status_text),
+    );
+
+    Ok(())
+}
+
+fn kernel(release: &str, paths: &[String]) -> Result<()> {
+    let rt = tokio::runtime::Handle::current();
+    let mut num_failed = 0;
+
+    for path in paths {
+        let (release2, path2) = (release.to_owned(), PathBuf::from(path));
+        let task = tokio::task::spawn_blocking(move || import_kernel_symbols(&release2, &path2));
+
+        match rt.block_on(task).expect("kernel symbol import panicked") {
+            Ok(modules) => {
+                for (module, num_symbols) in modules {
+                    println!("{release}  {module:<24}  {num_symbols} symbols  {path}");
+                }
+            }
+            Err(e) => {
+                eprintln!("{path}: {e:#}");
+                num_failed += 1;
+            }
+        }
+    }
+
+    if num_failed > 0 {
+        bail!("failed to import {num_failed} of {} files", paths.len());
+    }
+
+    Ok(())
+}
+
+fn kernels() {
+    println!("Kernel images seen in profiles:");
+    for (file_id, image) in DB.kernel_images.iter() {
+        let image = image.read();
+        let release = image.release.as_deref();
+        let symbols = match DB.kernel_symbols.lookup(release, &image.module) {
+            Some(_) => "symbols imported",
+            None => "no symbols",
+        };
+        println!(
+            "  {}  {:<24}  {:<32}  {symbols}",
+            file_id.format_hex(),
+            image.module,
+            release.unwrap_or("<unknown release>"),
+        );
+    }
+
+    println!("Imported kernel symbols:");
+    for (_, symbols) in DB.kernel_symbols.iter() {
+        let symbols = symbols.read();
+        println!(
+            "  {:<32}  {:<24}  {} symbols  {}",
+            symbols.release, symbols.module, symbols.num_symbols, symbols.source,
+        );
+    }
+}
+
+fn export(id: &str, path: &str) -> Result<()> {
+    let Some(file_id) = parse_file_id(id) else {
+        bail!("invalid file ID: {id}");
+    };
+
+    let num_symbols = export_symbfile(file_id, path.as_ref())?;
+    println!("{}  {num_symbols} symbols  {path}", file_id.format_hex());
+
+    Ok(())
+}
+
+fn list() {
+    let mut executables: Vec<_> = DB
+        .executables
+        .iter()
+        .map(|(file_id, meta)| (file_id, meta.read()))
+        .collect();
+
+    executables.sort_by(|(_, a), (_, b)| a.file_name.cmp(&b.file_name));
+
+    for (file_id, meta) in executables {
+        println!(
+            "{}  {:<24}  {:<40}  {}",
+            file_id.format_hex(),
+            status_text(meta.symb_status),
+            meta.build_id.as_deref().unwrap_or("-"),
+            meta.file_name.as_deref().unwrap_or("<unknown>"),
+        );
+    }
+}
+
+fn remove(ids: &[String]) -> Result<()> {
+    let mut num_failed = 0;
+
+    for id in ids {
+        let Some((file_id, meta)) = find_executable(id) else {
+            eprintln!("{id}: no such executable");
+            num_failed += 1;
+            continue;
+        };
+
+        if !DB.symbols.remove(file_id)? {
+            eprintln!("{id}: no symbols present");
+        }
+
+        if meta.first_seen.is_some() {
+            // Executable was seen in profiles: keep the record, but allow
+            // other symbol sources to pick it up again.
+            let mut meta = meta;
+            meta.symb_status = SymbStatus::NotAttempted;
+            meta.symb_attempts = 0;
+            meta.symb_error = None;
+            meta.symb_source = None;
+            DB.executables.insert(file_id, meta);
+        } else {
+            // Record was only created by ingesting symbols.
+            DB.executables.remove(file_id);
+        }
+
+        println!("{}  removed", file_id.format_hex());
+    }
+
+    if num_failed > 0 {
+        bail!("{num_failed} of {} file IDs not found", ids.len());
+    }
+
+    Ok(())
+}
+
+/// Find an executable by file ID in either hex or ES notation.
+fn find_executable(id: &str) -> Option<(FileId, ExecutableMeta)> {
+    DB.executables
+        .iter()
+        .find(|(file_id, _)| {
+            file_id.format_hex().eq_ignore_ascii_case(id) || file_id.format_es() == id
+        })
+        .map(|(file_id, meta)| (file_id, meta.read()))
+}
+
+fn parse_file_id(id: &str) -> Option<FileId> {
+    FileId::try_parse_hex(id).or_else(|| FileId::try_parse_es(id))
+}
+
+fn status_text(status: SymbStatus) -> String {
+    match status {
+        SymbStatus::NotAttempted => "not attempted".into(),
+        SymbStatus::TempError { .. } => "temporary error".into(),
+        SymbStatus::NotPresentGlobally => "not present".into(),
+        SymbStatus::Ignored => "ignored".into(),
+        SymbStatus::Complete { num_symbols } => format!("{num_symbols} symbols"),
+    }
+}
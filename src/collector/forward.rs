@@ -0,0 +1,358 @@
+// Copyright Elasticsearch B.V. and/or licensed to Elasticsearch B.V. under one
+// or more contributor license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Forwarding of received requests to an upstream OTLP collector.
+
+use super::otlp::{ExportProfilesServiceRequest, ProfilesServiceClient};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::path::PathBuf;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tonic::codec::CompressionEncoding;
+use tonic::metadata::{AsciiMetadataKey, AsciiMetadataValue};
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Endpoint};
+use tonic::Code;
+
+/// Delay before the first retry of a failed request. Doubled on every retry.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(250);
+
+/// Upper bound for the delay between two retries.
+const MAX_BACKOFF: Duration = Duration::from_secs(10);
+
+/// Configuration for forwarding requests to an upstream collector.
+#[derive(Debug, Clone)]
+pub struct ForwardConfig {
+    /// Upstream OTLP gRPC endpoint, e.g. `https://otlp.example.com:4317`.
+    pub endpoint: String,
+
+    /// Additional gRPC meta-data sent along with every request.
+    pub headers: Vec<(String, String)>,
+
+    /// PEM file with the CA certificate used to verify the upstream.
+    ///
+    /// The system's root certificates are used if this is `None`.
+    pub ca_cert: Option<PathBuf>,
+
+    /// Overrides the domain name used for TLS certificate verification.
+    pub tls_domain: Option<String>,
+
+    /// Number of requests to buffer while the upstream is slow or unreachable.
+    pub buffer_size: usize,
+
+    /// Number of retries before a request is discarded.
+    pub max_retries: u32,
+}
+
+impl ForwardConfig {
+    pub fn new(endpoint: String) -> Self {
+        ForwardConfig {
+            endpoint,
+            headers: Vec::new(),
+            ca_cert: None,
+            tls_domain: None,
+            buffer_size: 256,
+            max_retries: 5,
+        }
+    }
+}
+
+/// Forwarding statistics.
+#[derive(Debug)]
+pub struct ForwardStats {
+    pub endpoint: String,
+    pub forwarded: AtomicU64,
+    pub failed: AtomicU64,
+    pub dropped: AtomicU64,
+    pub last_error: Mutex<Option<(DateTime<Utc>, String)>>,
+}
+
+impl ForwardStats {
+    /// Number of requests that never made it to the upstream.
+    pub fn num_lost(&self) -> u64 {
+        self.failed.load(Relaxed) + self.dropped.load(Relaxed)
+    }
+
+    fn record_error(&self, message: String) {
+        tracing::warn!("Forwarding to {} failed: {message}", self.endpoint);
+        *self.last_error.lock().unwrap() = Some((Utc::now(), message));
+    }
+}
+
+/// Delivers received requests to an upstream collector in the background.
+#[derive(Debug)]
+pub struct Forwarder {
+    tx: mpsc::Sender<ExportProfilesServiceRequest>,
+    stats: Arc<ForwardStats>,
+}
+
+impl Forwarder {
+    /// Spawn the task delivering requests to the upstream.
+    ///
+    /// The connection is established lazily, so an upstream that isn't
+    /// reachable yet doesn't prevent devfiler from starting.
+    pub fn spawn(cfg: ForwardConfig) -> Result<Self> {
+        let mut endpoint = Endpoint::from_shared(cfg.endpoint.clone())
+            .context("invalid upstream endpoint")?
+            .connect_timeout(Duration::from_secs(10))
+            .timeout(Duration::from_secs(30));
+
+        if endpoint.uri().scheme_str() == Some("https") || cfg.ca_cert.is_some() {
+            let mut tls = ClientTlsConfig::new();
+            if let Some(ref path) = cfg.ca_cert {
+                let pem = std::fs::read(path)
+                    .with_context(|| format!("failed to read {}", path.display()))?;
+                tls = tls.ca_certificate(Certificate::from_pem(pem));
+            }
+            if let Some(ref domain) = cfg.tls_domain {
+                tls = tls.domain_name(domain.clone());
+            }
+            endpoint = endpoint.tls_config(tls).context("invalid TLS config")?;
+        }
+
+        let headers = cfg
+            .headers
+            .iter()
+            .map(|(key, value)| -> Result<_> {
+                let key = AsciiMetadataKey::from_bytes(key.as_bytes())
+                    .with_context(|| format!("invalid header name: {key:?}"))?;
+                let value = AsciiMetadataValue::try_from(value.as_str())
+                    .with_context(|| format!("invalid value for header {key}"))?;
+                Ok((key, value))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let client = ProfilesServiceClient::new(endpoint.connect_lazy())
+            .send_compressed(CompressionEncoding::Gzip);
+
+        let stats = Arc::new(ForwardStats {
+            endpoint: cfg.endpoint,
+            forwarded: 0.into(),
+            failed: 0.into(),
+            dropped: 0.into(),
+            last_error: Mutex::new(None),
+        });
+
+        let (tx, rx) = mpsc::channel(cfg.buffer_size.max(1));
+        tokio::spawn(forward_task(
+            rx,
+            client,
+            headers,
+            cfg.max_retries,
+            Arc::clone(&stats),
+        ));
+
+        tracing::info!("Forwarding requests to {}", stats.endpoint);
+
+        Ok(Forwarder { tx, stats })
+    }
+
+    /// Queue a request for delivery to the upstream.
+    ///
+    /// Never blocks: the request is dropped if the buffer is full.
+    pub fn forward(&self, req: ExportProfilesServiceRequest) {
+        if let Err(e) = self.tx.try_send(req) {
+            self.stats.dropped.fetch_add(1, Relaxed);
+            self.stats.record_error(format!("request dropped: {e}"));
+        }
+    }
+
+    pub fn stats(&self) -> &ForwardStats {
+        &*self.stats
+    }
+}
+
+async fn forward_task(
+    mut rx: mpsc::Receiver<ExportProfilesServiceRequest>,
+    mut client: ProfilesServiceClient<Channel>,
+    headers: Vec<(AsciiMetadataKey, AsciiMetadataValue)>,
+    max_retries: u32,
+    stats: Arc<ForwardStats>,
+) {
+    while let Some(req) = rx.recv().await {
+        let mut backoff = INITIAL_BACKOFF;
+        let mut retries = 0;
+
+        loop {
+            let mut request = tonic::Request::new(req.clone());
+            for (key, value) in &headers {
+                request.metadata_mut().insert(key.clone(), value.clone());
+            }
+
+            match client.export(request).await {
+                Ok(_) => {
+                    stats.forwarded.fetch_add(1, Relaxed);
+                    break;
+                }
+                Err(status) if retries < max_retries && is_retryable(status.code()) => {
+                    retries += 1;
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                }
+                Err(status) => {
+                    stats.failed.fetch_add(1, Relaxed);
+                    stats.record_error(format!("{:?}: {}", status.code(), status.message()));
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Whether the OTLP specification permits retrying after the given status.
+fn is_retryable(code: Code) -> bool {
+    matches!(
+        code,
+        Code::Cancelled
+            | Code::DeadlineExceeded
+            | Code::ResourceExhausted
+            | Code::Aborted
+            | Code::OutOfRange
+            | Code::Unavailable
+            | Code::DataLoss
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collector::otlp::pb::collector::profiles::v1development::profiles_service_server::{
+        ProfilesService, ProfilesServiceServer,
+    };
+    use crate::collector::otlp::pb::collector::profiles::v1development::ExportProfilesServiceResponse;
+    use std::net::SocketAddr;
+    use std::sync::atomic::AtomicU32;
+    use tonic::metadata::MetadataMap;
+    use tonic::transport::server::TcpIncoming;
+    use tonic::transport::Server;
+    use tonic::{Request, Response, Status};
+
+    type Received = (MetadataMap, ExportProfilesServiceRequest);
+
+    /// Upstream stand-in that records all requests it receives.
+    struct StandIn {
+        tx: mpsc::UnboundedSender<Received>,
+        reject_first: AtomicU32,
+    }
+
+    #[tonic::async_trait]
+    impl ProfilesService for StandIn {
+        async fn export(
+            &self,
+            request: Request<ExportProfilesServiceRequest>,
+        ) -> Result<Response<ExportProfilesServiceResponse>, Status> {
+            let reject = self
+                .reject_first
+                .fetch_update(Relaxed, Relaxed, |x| x.checked_sub(1))
+                .is_ok();
+            if reject {
+                return Err(Status::unavailable("not ready yet"));
+            }
+
+            let meta = request.metadata().clone();
+            self.tx.send((meta, request.into_inner())).unwrap();
+            Ok(Response::new(ExportProfilesServiceResponse {
+                partial_success: None,
+            }))
+        }
+    }
+
+    async fn spawn_stand_in(reject_first: u32) -> (SocketAddr, mpsc::UnboundedReceiver<Received>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let incoming = TcpIncoming::from_listener(listener, true, None).unwrap();
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        let service = ProfilesServiceServer::new(StandIn {
+            tx,
+            reject_first: reject_first.into(),
+        })
+        .accept_compressed(CompressionEncoding::Gzip);
+
+        tokio::spawn(
+            Server::builder()
+                .add_service(service)
+                .serve_with_incoming(incoming),
+        );
+
+        (addr, rx)
+    }
+
+    async fn wait_for(cond: impl Fn() -> bool) {
+        for _ in 0..100 {
+            if cond() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        panic!("condition not met in time");
+    }
+
+    #[tokio::test]
+    async fn forwards_with_headers() {
+        let (addr, mut rx) = spawn_stand_in(0).await;
+
+        let mut cfg = ForwardConfig::new(format!("http://{addr}"));
+        cfg.headers.push(("x-tenant".into(), "devfiler".into()));
+        let forwarder = Forwarder::spawn(cfg).unwrap();
+
+        forwarder.forward(ExportProfilesServiceRequest::default());
+
+        let (meta, _) = tokio::time::timeout(Duration::from_secs(10), rx.recv())
+            .await
+            .expect("stand-in didn't receive the request")
+            .unwrap();
+        assert_eq!(meta.get("x-tenant").unwrap().to_str().unwrap(), "devfiler");
+
+        wait_for(|| forwarder.stats().forwarded.load(Relaxed) == 1).await;
+        assert_eq!(forwarder.stats().num_lost(), 0);
+    }
+
+    #[tokio::test]
+    async fn retries_unavailable_upstream() {
+        let (addr, mut rx) = spawn_stand_in(2).await;
+        let forwarder = Forwarder::spawn(ForwardConfig::new(format!("http://{addr}"))).unwrap();
+
+        forwarder.forward(ExportProfilesServiceRequest::default());
+
+        tokio::time::timeout(Duration::from_secs(10), rx.recv())
+            .await
+            .expect("stand-in didn't receive the request")
+            .unwrap();
+
+        wait_for(|| forwarder.stats().forwarded.load(Relaxed) == 1).await;
+        assert_eq!(forwarder.stats().num_lost(), 0);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_retries() {
+        let (addr, _rx) = spawn_stand_in(u32::MAX).await;
+
+        let mut cfg = ForwardConfig::new(format!("http://{addr}"));
+        cfg.max_retries = 1;
+        let forwarder = Forwarder::spawn(cfg).unwrap();
+
+        forwarder.forward(ExportProfilesServiceRequest::default());
+
+        wait_for(|| forwarder.stats().failed.load(Relaxed) == 1).await;
+        assert_eq!(forwarder.stats().forwarded.load(Relaxed), 0);
+        assert!(forwarder.stats().last_error.lock().unwrap().is_some());
+    }
+}
@@ -17,6 +17,7 @@
 
 //! Collection agent service implementation.
 
+use anyhow::Result;
 use std::collections::VecDeque;
 use std::net::SocketAddr;
 use std::sync::atomic::{AtomicU64, Ordering};
@@ -38,6 +39,9 @@ pub struct LoggedRequest {
 
     /// Payload after conversion to JSON-like data-structure.
     pub payload: serde_json::Value,
+
+    /// Conformance check results, if validation is enabled.
+    pub report: Option<validate::Report>,
 }
 
 /// Collector info and statistics.
@@ -50,7 +54,11 @@ pub struct Stats {
 
 impl Stats {
     /// Log a gRPC message into the ring buffer.
-    pub fn log_request<R: serde::Serialize>(&self, req: &tonic::Request<R>) {
+    pub fn log_request<R: serde::Serialize>(
+        &self,
+        req: &tonic::Request<R>,
+        report: Option<validate::Report>,
+    ) {
         self.msgs_processed.fetch_add(1, Ordering::Relaxed);
 
         let Ok(payload) = serde_json::to_value(req.get_ref()) else {
@@ -62,6 +70,7 @@ impl Stats {
             timestamp: chrono::Utc::now(),
             kind: std::any::type_name::<R>(),
             meta: req.metadata().clone(),
+            report,
         });
 
         let mut ring = self.ring.write().unwrap();
@@ -78,6 +87,8 @@ impl Stats {
 #[derive(Debug, Clone)]
 pub struct Collector {
     stats: Arc<Stats>,
+    forwarder: Option<Arc<Forwarder>>,
+    validate: bool,
 }
 
 impl Collector {
@@ -88,11 +99,28 @@ impl Collector {
                 msgs_processed: 0.into(),
                 ring: RwLock::new(VecDeque::with_capacity(100)),
             }),
+            forwarder: None,
+            validate: false,
         }
     }
 
-    pub async fn serve(&self) -> anyhow::Result<()> {
-        let otlp_server = otlp::ProfilesService::new(self.stats.clone());
+    /// Check all received requests against the profiles data model.
+    pub fn with_validation(mut self) -> Self {
+        self.validate = true;
+        self
+    }
+
+    /// Forward all received requests to an upstream collector.
+    ///
+    /// Must be called from within the tokio runtime.
+    pub fn with_forwarding(mut self, cfg: ForwardConfig) -> Result<Self> {
+        self.forwarder = Some(Arc::new(Forwarder::spawn(cfg)?));
+        Ok(self)
+    }
+
+    pub async fn serve(&self) -> Result<()> {
+        let otlp_server =
+            otlp::ProfilesService::new(self.stats.clone(), self.forwarder.clone(), self.validate);
 
         tracing::info!("Collector listening on {}", self.stats.listen_addr);
 
@@ -111,6 +139,19 @@ impl Collector {
     pub fn stats(&self) -> &Stats {
         &*self.stats
     }
+
+    pub fn validation_enabled(&self) -> bool {
+        self.validate
+    }
+
+    /// Forwarding statistics, if forwarding is enabled.
+    pub fn forward_stats(&self) -> Option<&ForwardStats> {
+        self.forwarder.as_deref().map(Forwarder::stats)
+    }
 }
 
+mod forward;
 mod otlp;
+
+pub use forward::{ForwardConfig, ForwardStats, Forwarder};
+pub use otlp::validate;
@@ -15,8 +15,11 @@
 // specific language governing permissions and limitations
 // under the License.
 
-mod pb;
+pub mod pb;
 mod service;
+pub mod validate;
 
+pub use pb::collector::profiles::v1development::profiles_service_client::ProfilesServiceClient;
 pub use pb::collector::profiles::v1development::profiles_service_server::ProfilesServiceServer;
+pub use pb::collector::profiles::v1development::ExportProfilesServiceRequest;
 pub use service::ProfilesService;
@@ -16,30 +16,54 @@
 // under the License.
 
 use super::pb::collector::profiles::v1development as pb_collector;
+use super::validate;
 use crate::collector::otlp::pb::collector::profiles::v1development::{
     ExportProfilesServiceRequest, ExportProfilesServiceResponse,
 };
 use crate::collector::otlp::pb::common::v1::any_value::Value;
 use crate::collector::otlp::pb::profiles::v1development::{
-    KeyValueAndUnit, ProfilesDictionary, Sample, ValueType,
+    KeyValueAndUnit, Location, Mapping, ProfilesDictionary, Sample, ValueType,
 };
-use crate::collector::Stats;
+use crate::collector::otlp::pb::resource::v1::Resource;
+use crate::collector::{Forwarder, Stats};
 use crate::storage::*;
 use chrono::Utc;
+use std::collections::{HashMap, HashSet};
 use std::hash::Hash;
 use std::sync::Arc;
 use tonic::{Request, Response, Status};
 use xxhash_rust::xxh3;
 
+/// Minimum interval in seconds between updates of an executable's `last_seen`.
+const LAST_SEEN_GRANULARITY: UtcTimestamp = 60;
+
+/// Resource attributes holding the kernel release of the reporting host.
+const KERNEL_RELEASE_ATTRS: [&str; 2] = ["host.kernel.release", "os.version"];
+
+/// Sample or resource attributes holding the name of the process.
+const PROCESS_ATTRS: [&str; 1] = ["process.executable.name"];
+
+/// Sample or resource attributes identifying the container.
+const CONTAINER_ATTRS: [&str; 2] = ["container.name", "container.id"];
+
+/// Sample or resource attributes holding the Kubernetes pod name.
+const POD_ATTRS: [&str; 1] = ["k8s.pod.name"];
+
 /// gRPC server implementing the OTEL profiling collector protocol.
 #[derive(Debug)]
 pub struct ProfilesService {
     stats: Arc<Stats>,
+    forwarder: Option<Arc<Forwarder>>,
+    validate: bool,
 }
 
 impl ProfilesService {
-    pub fn new(stats: Arc<Stats>) -> Self {
-        ProfilesService { stats }
+    pub fn new(stats: Arc<Stats>, forwarder: Option<Arc<Forwarder>>, validate: bool) -> Self {
+        ProfilesService {
+            stats,
+            forwarder,
+            validate,
+        }
     }
 }
 
@@ -49,16 +73,44 @@ impl pb_collector::profiles_service_server::ProfilesService for ProfilesService
         &self,
         request: Request<ExportProfilesServiceRequest>,
     ) -> Result<Response<ExportProfilesServiceResponse>, Status> {
-        self.stats.log_request(&request);
+        let report = self.validate.then(|| validate::validate(request.get_ref()));
+        if let Some(ref report) = report {
+            let errors = report.count(validate::Severity::Error);
+            let warnings = report.count(validate::Severity::Warning);
+            if errors + warnings > 0 {
+                tracing::warn!("Request failed validation: {errors} errors, {warnings} warnings");
+            }
+        }
+
+        self.stats.log_request(&request, report);
         let r = request.into_inner();
 
+        if let Some(ref forwarder) = self.forwarder {
+            forwarder.forward(r.clone());
+        }
+
         let dict = match r.dictionary.as_ref() {
             Some(dictionary) => dictionary,
             None => return Err(Status::invalid_argument("ProfilesDictionary is required")),
         };
-        let loc_mapping = ingest_locations(dict)?;
+        let mut kernel_modules = HashMap::new();
+        let loc_mapping = ingest_locations(dict, &mut kernel_modules)?;
+        let mut interned_labels = HashSet::new();
 
         for resource_profile in r.resource_profiles {
+            // Kernel images are tracked per kernel release of the host.
+            let resource = resource_profile.resource.as_ref();
+            let release = resource_attr(resource, &KERNEL_RELEASE_ATTRS);
+            let mut kernels_seen = HashSet::new();
+
+            let resource_label =
+                |keys: &[&str]| resource_attr(resource, keys).map(ToOwned::to_owned);
+            let resource_labels = TraceLabels {
+                process: resource_label(&PROCESS_ATTRS),
+                container_name: resource_label(&CONTAINER_ATTRS),
+                pod_name: resource_label(&POD_ATTRS),
+            };
+
             for scope_profile in resource_profile.scope_profiles {
                 for profile in scope_profile.profiles {
                     if profile.sample_type.is_none() {
@@ -70,10 +122,31 @@ impl pb_collector::profiles_service_server::ProfilesService for ProfilesService
                         let stack = dict.stack_table.get(sample.stack_index as usize);
                         let frame_list =
                             collect_frame_list(&loc_mapping, &stack.unwrap().location_indices)?;
-                        process_sample(dict, &st, sample, frame_list)?;
+                        if !kernel_modules.is_empty() {
+                            kernels_seen.extend(
+                                frame_list
+                                    .iter()
+                                    .filter(|x| x.kind.interp() == Some(InterpKind::Kernel))
+                                    .map(|x| x.id.file_id),
+                            );
+                        }
+                        process_sample(
+                            dict,
+                            &st,
+                            sample,
+                            frame_list,
+                            &resource_labels,
+                            &mut interned_labels,
+                        )?;
                     }
                 }
             }
+
+            for file_id in kernels_seen {
+                if let Some(module) = kernel_modules.get(&file_id) {
+                    update_kernel_image(file_id, module, release);
+                }
+            }
         }
 
         Ok(Response::new(ExportProfilesServiceResponse {
@@ -154,13 +227,23 @@ fn get_attr<'tab>(
     )));
 }
 
-fn ingest_locations(dic: &ProfilesDictionary) -> Result<Vec<Frame>, Status> {
+/// Ingest the location table, returning the frame for each location.
+///
+/// The module names of the kernel images referenced by kernel frames are
+/// collected into `kernel_modules`.
+fn ingest_locations(
+    dic: &ProfilesDictionary,
+    kernel_modules: &mut HashMap<FileId, String>,
+) -> Result<Vec<Frame>, Status> {
     let stab = &dic.string_table;
     let atab = &dic.attribute_table;
     let ftab = &dic.function_table;
     let locs = &dic.location_table;
     let mut batch = DB.stack_frames.batched_insert();
     let mut mappings = Vec::with_capacity(locs.len());
+    let mut seen_executables = HashSet::new();
+    let mut frame_kinds = HashMap::new();
+    let now = Utc::now().timestamp() as UtcTimestamp;
 
     for loc in locs {
         let kind = match get_attr(
@@ -178,36 +261,25 @@ fn ingest_locations(dic: &ProfilesDictionary) -> Result<Vec<Frame>, Status> {
             }
             Err(e) => return Err(e),
         };
-        let kind = match kind {
-            "native" => FrameKind::Regular(InterpKind::Native),
-            "kernel" => FrameKind::Regular(InterpKind::Kernel),
-            "jvm" => FrameKind::Regular(InterpKind::Jvm),
-            "perl" => FrameKind::Regular(InterpKind::Perl),
-            "cpython" => FrameKind::Regular(InterpKind::Python),
-            "php" => FrameKind::Regular(InterpKind::Php),
-            "phpjit" => FrameKind::Regular(InterpKind::PhpJit),
-            "ruby" => FrameKind::Regular(InterpKind::Ruby),
-            "dotnet" => FrameKind::Regular(InterpKind::DotNet),
-            "v8js" => FrameKind::Regular(InterpKind::Js),
-            "beam" => FrameKind::Regular(InterpKind::Beam),
-            "go" => FrameKind::Regular(InterpKind::Go),
-            "abort-marker" => FrameKind::Abort,
-            _ => {
-                return Err(Status::invalid_argument(format!(
-                    "unsupported frame kind: {}",
-                    kind
-                )));
-            }
+        let kind = *frame_kinds
+            .entry(kind)
+            .or_insert_with(|| parse_frame_type(kind));
+
+        // Error frames do not have a backing mapping: the address holds the
+        // error code. Use a synthetic file ID per frame kind instead.
+        let error_file_id = match kind {
+            FrameKind::Abort => Some(FileId::from_parts(1, 1)),
+            FrameKind::Error(interp) => Some(FileId::from_parts(2, interp as u64)),
+            FrameKind::UnknownError(code) => Some(FileId::from_parts(2, code as u64)),
+            _ => None,
         };
 
-        if kind == FrameKind::Abort {
+        if let Some(file_id) = error_file_id {
             let id = FrameId {
-                file_id: FileId::from_parts(1, 1),
+                file_id,
                 addr_or_line: loc.address,
             };
             mappings.push(Frame { id, kind });
-            // Error frames do not have a backing mapping,
-            // so we just push the frame and continue.
             continue;
         }
 
@@ -269,63 +341,255 @@ fn ingest_locations(dic: &ProfilesDictionary) -> Result<Vec<Frame>, Status> {
             return Err(Status::invalid_argument("failed to parse file ID"));
         };
 
+        // Kernel addresses are randomized per boot (KASLR): store them
+        // relative to the image if the agent reports where it was mapped.
+        let mut addr_or_line = loc.address;
+        if kind.interp() == Some(InterpKind::Kernel) && mapping.memory_start != 0 {
+            addr_or_line = addr_or_line
+                .checked_sub(mapping.memory_start)
+                .unwrap_or(addr_or_line);
+        }
+
         let id = FrameId {
             file_id,
-            addr_or_line: loc.address,
+            addr_or_line,
         };
 
         mappings.push(Frame { id, kind });
 
+        if kind.interp() == Some(InterpKind::Kernel) && !kernel_modules.contains_key(&file_id) {
+            let file_name = get_str_opt(stab, mapping.filename_strindex as usize, "file name")?;
+            kernel_modules.insert(file_id, kernel_module_name(file_name.unwrap_or_default()));
+        }
+
         if matches!(kind.interp(), Some(InterpKind::Native)) {
-            if !DB.executables.contains_key(file_id) {
-                DB.executables.insert(
-                    file_id,
-                    ExecutableMeta {
-                        build_id: None,
-                        file_name: get_str_opt(
-                            stab,
-                            mapping.filename_strindex as usize,
-                            "file name",
-                        )?
-                        .map(ToOwned::to_owned),
-                        symb_status: SymbStatus::NotAttempted,
-                    },
-                );
+            if seen_executables.insert(file_id) {
+                update_executable(dic, file_id, mapping, now)?;
             }
 
             // Don't insert meta-data for native frames: we symbolize them on the fly.
             continue;
         }
 
-        let Some(line) = loc.lines.first() else {
+        if loc.lines.is_empty() {
             continue;
-        };
+        }
+
+        let lines = location_lines(dic, loc)?;
+        batch.insert(id, FrameMetaData { lines });
+    }
 
+    debug_assert_eq!(mappings.len(), locs.len());
+
+    batch.commit();
+    Ok(mappings)
+}
+
+/// Source lines of an interpreted location, outermost caller first.
+fn location_lines(dic: &ProfilesDictionary, loc: &Location) -> Result<Vec<SourceLine>, Status> {
+    let stab = &dic.string_table;
+
+    // OTLP lists inlined functions first, with the last line being the
+    // outermost caller: reverse to match the order used for native frames.
+    let mut lines = Vec::with_capacity(loc.lines.len());
+    for line in loc.lines.iter().rev() {
+        let (mut function_name, mut file_name) = (None, None);
         if line.function_index != 0 {
             let Some(fn_ref) = &dic.function_table.get(line.function_index as usize) else {
                 return Err(Status::invalid_argument("invalid function index"));
             };
 
-            let function_name = get_str_opt(stab, fn_ref.name_strindex as usize, "function name")?;
-            let file_name =
-                get_str_opt(stab, fn_ref.filename_strindex as usize, "function filename")?;
-
-            batch.insert(
-                id,
-                FrameMetaData {
-                    file_name: file_name.map(str::to_owned),
-                    function_name: function_name.map(str::to_owned),
-                    line_number: line.line as u64,
-                    function_offset: 0,
-                },
-            );
-        };
+            function_name = get_str_opt(stab, fn_ref.name_strindex as usize, "function name")?;
+            file_name = get_str_opt(stab, fn_ref.filename_strindex as usize, "function filename")?;
+        }
+
+        lines.push(SourceLine {
+            file_name: file_name.map(str::to_owned),
+            function_name: function_name.map(str::to_owned),
+            line_number: (line.line > 0).then_some(line.line as u64),
+            column: (line.column > 0).then_some(line.column as u64),
+        });
     }
 
-    debug_assert_eq!(mappings.len(), locs.len());
+    Ok(lines)
+}
 
-    batch.commit();
-    Ok(mappings)
+/// Map a `profile.frame.type` attribute value to a frame kind.
+///
+/// Error frames are reported as `<type>-error`. Frame types that we don't know
+/// are registered in [`FrameTypes`] to keep their name for display.
+fn parse_frame_type(name: &str) -> FrameKind {
+    if let Some(kind) = FrameKind::from_frame_type(name) {
+        return kind;
+    }
+
+    let (base, is_error) = match name.strip_suffix("-error") {
+        Some(base) => (base, true),
+        None => (name, false),
+    };
+
+    let code = DB.frame_types.code_for_name(base).unwrap_or_else(|| {
+        tracing::warn!("Out of codes for unknown frame types: can't register {base}");
+        0
+    });
+
+    if is_error {
+        FrameKind::UnknownError(code | FrameKind::ERR_MASK)
+    } else {
+        FrameKind::Unknown(code)
+    }
+}
+
+/// Create or update the executable record for a native mapping.
+fn update_executable(
+    dic: &ProfilesDictionary,
+    file_id: FileId,
+    mapping: &Mapping,
+    now: UtcTimestamp,
+) -> Result<(), Status> {
+    let file_name = get_str_opt(
+        &dic.string_table,
+        mapping.filename_strindex as usize,
+        "file name",
+    )?;
+
+    let gnu_build_id = get_attr(
+        &dic.string_table,
+        &dic.attribute_table,
+        mapping.attribute_indices.to_vec(),
+        "process.executable.build_id.gnu",
+    )
+    .ok();
+
+    let range = MappingRange {
+        memory_start: mapping.memory_start,
+        memory_limit: mapping.memory_limit,
+        file_offset: mapping.file_offset,
+    };
+
+    let existing = DB.executables.get(file_id).map(|x| x.read());
+    let update = merge_executable(existing, gnu_build_id, file_name, range, now);
+    if let Some(meta) = update {
+        DB.executables.insert(file_id, meta);
+    }
+    Ok(())
+}
+
+/// Merge a sighting of an executable into its existing record.
+///
+/// Returns `None` if the record doesn't need to be written: nothing new was
+/// learned and `last_seen` is still within [`LAST_SEEN_GRANULARITY`].
+fn merge_executable(
+    existing: Option<ExecutableMeta>,
+    gnu_build_id: Option<&str>,
+    file_name: Option<&str>,
+    range: MappingRange,
+    now: UtcTimestamp,
+) -> Option<ExecutableMeta> {
+    Some(match existing {
+        None => ExecutableMeta {
+            build_id: gnu_build_id.map(ToOwned::to_owned),
+            file_name: file_name.map(ToOwned::to_owned),
+            symb_status: SymbStatus::NotAttempted,
+            mapping: Some(range),
+            first_seen: Some(now),
+            last_seen: Some(now),
+            symb_attempts: 0,
+            symb_error: None,
+            local_path: None,
+            symb_source: None,
+        },
+        Some(mut meta) => {
+            let outdated = meta
+                .last_seen
+                .map_or(true, |x| now.saturating_sub(x) >= LAST_SEEN_GRANULARITY);
+            let new_info = (meta.build_id.is_none() && gnu_build_id.is_some())
+                || (meta.file_name.is_none() && file_name.is_some())
+                || meta.mapping.is_none();
+
+            if !outdated && !new_info {
+                return None;
+            }
+
+            if meta.build_id.is_none() {
+                meta.build_id = gnu_build_id.map(ToOwned::to_owned);
+            }
+            if meta.file_name.is_none() {
+                meta.file_name = file_name.map(ToOwned::to_owned);
+            }
+            meta.mapping = Some(range);
+            meta.first_seen.get_or_insert(now);
+            meta.last_seen = Some(now);
+            meta
+        }
+    })
+}
+
+/// Value of the first of the given string attributes present on the resource.
+fn resource_attr<'a>(resource: Option<&'a Resource>, keys: &[&str]) -> Option<&'a str> {
+    let attrs = &resource?.attributes;
+
+    keys.iter().find_map(|key| {
+        let kv = attrs.iter().find(|x| x.key == *key)?;
+        match kv.value.as_ref()?.value.as_ref()? {
+            Value::StringValue(ref value) if !value.is_empty() => Some(value.as_str()),
+            _ => None,
+        }
+    })
+}
+
+/// Create or update the record of a kernel image seen in kernel frames.
+fn update_kernel_image(file_id: FileId, module: &str, release: Option<&str>) {
+    let existing = DB.kernel_images.get(file_id).map(|x| x.read());
+
+    // Don't forget a known release when a host stops reporting it.
+    let release = release
+        .map(ToOwned::to_owned)
+        .or_else(|| existing.as_ref().and_then(|x| x.release.clone()));
+    let image = KernelImage {
+        module: module.to_owned(),
+        release,
+    };
+
+    if existing.as_ref() != Some(&image) {
+        DB.kernel_images.insert(file_id, image);
+    }
+}
+
+/// Resolve the string attributes at the given indices to key/value pairs.
+///
+/// Invalid indices and attributes with non-string values are skipped.
+fn string_attrs<'a>(dict: &'a ProfilesDictionary, indices: &[i32]) -> Vec<(&'a str, &'a str)> {
+    indices
+        .iter()
+        .filter_map(|&idx| {
+            let kv = dict.attribute_table.get(usize::try_from(idx).ok()?)?;
+            let key = dict
+                .string_table
+                .get(usize::try_from(kv.key_strindex).ok()?)?;
+            match kv.value.as_ref()?.value.as_ref()? {
+                Value::StringValue(ref value) => Some((key.as_str(), value.as_str())),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Labels of a sample, falling back to the ones of its resource.
+fn sample_labels(attrs: &[(&str, &str)], resource_labels: &TraceLabels) -> TraceLabels {
+    // Sample attributes take precedence over the ones of the resource.
+    let label = |keys: &[&str], fallback: &Option<String>| {
+        keys.iter()
+            .find_map(|key| attrs.iter().find(|x| x.0 == *key && !x.1.is_empty()))
+            .map(|x| x.1.to_owned())
+            .or_else(|| fallback.clone())
+    };
+
+    TraceLabels {
+        process: label(&PROCESS_ATTRS, &resource_labels.process),
+        container_name: label(&CONTAINER_ATTRS, &resource_labels.container_name),
+        pod_name: label(&POD_ATTRS, &resource_labels.pod_name),
+    }
 }
 
 fn process_sample(
@@ -333,6 +597,8 @@ fn process_sample(
     sample_type: &ValueType,
     sample: &Sample,
     frame_list: Vec<Frame>,
+    resource_labels: &TraceLabels,
+    interned_labels: &mut HashSet<TraceLabelsId>,
 ) -> Result<(), Status> {
     // Insert frame list.
     let mut hasher = xxh3::Xxh3::new();
@@ -349,12 +615,21 @@ fn process_sample(
         &sample.timestamps_unix_nano[..]
     };
 
-    let comm = get_attr(
-        &dict.string_table,
-        &dict.attribute_table,
-        sample.attribute_indices.to_vec(),
-        "thread.name",
-    );
+    let attrs = string_attrs(dict, &sample.attribute_indices);
+    let comm = attrs
+        .iter()
+        .find(|x| x.0 == "thread.name")
+        .map(|x| x.1)
+        .unwrap_or_default();
+
+    // Labels are shared by many events: store them only once.
+    let labels = sample_labels(&attrs, resource_labels);
+    let labels_id = (!labels.is_empty()).then(|| labels.id());
+    if let Some(id) = labels_id {
+        if interned_labels.insert(id) && !DB.trace_labels.contains_key(id) {
+            DB.trace_labels.insert(id, labels);
+        }
+    }
 
     let mut event_batch = DB.trace_events.batched_insert();
     for timestamp in timestamps {
@@ -400,9 +675,8 @@ fn process_sample(
                 timestamp,
                 trace_hash,
                 count: 1,
-                comm: comm.clone().unwrap_or_default().to_owned(),
-                pod_name: None,
-                container_name: None,
+                comm: comm.to_owned(),
+                labels: labels_id,
             },
         );
     }
@@ -437,6 +711,7 @@ mod tests {
     use itertools::Itertools;
 
     use super::*;
+    use crate::collector::otlp::pb::profiles::v1development::{Function, Line};
 
     #[test]
     fn sample_frame_list() -> Result<(), Status> {
@@ -467,6 +742,121 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn frame_types() {
+        use FrameKind::*;
+        use InterpKind::*;
+
+        assert_eq!(parse_frame_type("abort-marker"), Abort);
+        assert_eq!(parse_frame_type("native"), Regular(Native));
+        assert_eq!(parse_frame_type("cpython"), Regular(Python));
+        assert_eq!(parse_frame_type("native-error"), Error(Native));
+        assert_eq!(parse_frame_type("v8js-error"), Error(Js));
+    }
+
+    #[test]
+    fn interp_location_lines() {
+        let dic = ProfilesDictionary {
+            string_table: ["", "inner", "outer", "app.py"]
+                .iter()
+                .map(|&x| x.into())
+                .collect(),
+            function_table: vec![
+                Function::default(),
+                Function {
+                    name_strindex: 1,
+                    filename_strindex: 3,
+                    ..Default::default()
+                },
+                Function {
+                    name_strindex: 2,
+                    filename_strindex: 3,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        // Innermost first, as sent by the agent.
+        let loc = Location {
+            lines: vec![
+                Line {
+                    function_index: 1,
+                    line: 10,
+                    column: 5,
+                },
+                Line {
+                    function_index: 2,
+                    line: 20,
+                    column: 0,
+                },
+            ],
+            ..Default::default()
+        };
+
+        let line = |func: &str, line_number, column| SourceLine {
+            file_name: Some("app.py".into()),
+            function_name: Some(func.into()),
+            line_number,
+            column,
+        };
+        assert_eq!(
+            location_lines(&dic, &loc).unwrap(),
+            [
+                line("outer", Some(20), None),
+                line("inner", Some(10), Some(5))
+            ]
+        );
+
+        let bad = Location {
+            lines: vec![Line {
+                function_index: 7,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert!(location_lines(&dic, &bad).is_err());
+    }
+
+    #[test]
+    fn executable_sightings() {
+        let range = |start| MappingRange {
+            memory_start: start,
+            memory_limit: start + 0x1000,
+            file_offset: 0,
+        };
+        let t0 = 1_750_000_000;
+
+        let meta = merge_executable(None, None, Some("app"), range(0x1000), t0).unwrap();
+        assert_eq!(meta.symb_status, SymbStatus::NotAttempted);
+        assert_eq!(meta.build_id, None);
+        assert_eq!(meta.file_name.as_deref(), Some("app"));
+        assert_eq!(meta.mapping, Some(range(0x1000)));
+        assert_eq!((meta.first_seen, meta.last_seen), (Some(t0), Some(t0)));
+
+        // Seen again shortly after with nothing new: no write.
+        let t1 = t0 + LAST_SEEN_GRANULARITY - 1;
+        assert!(merge_executable(Some(meta), None, Some("app"), range(0x2000), t1).is_none());
+
+        // A new GNU build ID is recorded right away, along with the mapping.
+        let meta = merge_executable(None, None, None, range(0x1000), t0).unwrap();
+        let meta = merge_executable(Some(meta), Some("abcd"), Some("app"), range(0x2000), t1);
+        let meta = meta.unwrap();
+        assert_eq!(meta.build_id.as_deref(), Some("abcd"));
+        assert_eq!(meta.file_name.as_deref(), Some("app"));
+        assert_eq!(meta.mapping, Some(range(0x2000)));
+        assert_eq!((meta.first_seen, meta.last_seen), (Some(t0), Some(t1)));
+
+        // Known values aren't replaced, but `last_seen` moves on once outdated.
+        let t2 = t1 + LAST_SEEN_GRANULARITY;
+        let meta = merge_executable(Some(meta), Some("ef01"), Some("other"), range(0x3000), t2);
+        let meta = meta.unwrap();
+        assert_eq!(meta.build_id.as_deref(), Some("abcd"));
+        assert_eq!(meta.file_name.as_deref(), Some("app"));
+        assert_eq!(meta.mapping, Some(range(0x3000)));
+        assert_eq!((meta.first_seen, meta.last_seen), (Some(t0), Some(t2)));
+    }
+
     #[test]
     fn sample_frame_list_err() -> Result<(), Status> {
         let loc_mapping = (0..11).collect_vec();
@@ -0,0 +1,910 @@
+// Copyright Elasticsearch B.V. and/or licensed to Elasticsearch B.V. under one
+// or more contributor license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Conformance checks of requests against the OTel profiles data model.
+//!
+//! Unlike ingestion, which bails on the first problem, validation inspects the
+//! whole request and collects everything it finds into a [`Report`].
+
+use super::pb::collector::profiles::v1development::ExportProfilesServiceRequest;
+use super::pb::common::v1::any_value::Value;
+use super::pb::profiles::v1development::{
+    KeyValueAndUnit, Profile, ProfilesDictionary, Sample, ValueType,
+};
+use crate::storage::FrameKind;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+
+/// Maximum number of findings recorded per report.
+const MAX_FINDINGS: usize = 1000;
+
+/// Timestamps before 2024/01/01 00:00 are interpreted as milliseconds.
+const MIN_NANO_TIMESTAMP: u64 = 1704063600 * 1_000_000_000;
+
+/// Sample `(type, unit)` pairs understood by devfiler.
+const KNOWN_SAMPLE_TYPES: &[(&str, &str)] = &[
+    ("samples", "count"),
+    ("off_cpu", "nanoseconds"),
+    ("events", "count"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Not a violation, but worth knowing about.
+    Info,
+    /// Allowed by the data model, but likely a mistake.
+    Warning,
+    /// Violation of the data model.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// Single problem found in a request.
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub severity: Severity,
+
+    /// Location within the request, e.g. `dictionary.location_table[3].mapping_index`.
+    pub path: String,
+
+    pub message: String,
+}
+
+/// Validation results for a single request.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub findings: Vec<Finding>,
+
+    /// Number of findings omitted after reaching [`MAX_FINDINGS`].
+    pub omitted: usize,
+}
+
+impl Report {
+    /// Number of recorded findings with the given severity.
+    pub fn count(&self, severity: Severity) -> usize {
+        self.findings
+            .iter()
+            .filter(|x| x.severity == severity)
+            .count()
+    }
+
+    /// Highest severity among all findings.
+    pub fn max_severity(&self) -> Option<Severity> {
+        self.findings.iter().map(|x| x.severity).max()
+    }
+
+    fn push(&mut self, severity: Severity, path: impl Into<String>, message: impl Into<String>) {
+        if self.findings.len() >= MAX_FINDINGS {
+            self.omitted += 1;
+            return;
+        }
+
+        self.findings.push(Finding {
+            severity,
+            path: path.into(),
+            message: message.into(),
+        });
+    }
+
+    fn error(&mut self, path: impl Into<String>, message: impl Into<String>) {
+        self.push(Severity::Error, path, message);
+    }
+
+    fn warn(&mut self, path: impl Into<String>, message: impl Into<String>) {
+        self.push(Severity::Warning, path, message);
+    }
+
+    fn info(&mut self, path: impl Into<String>, message: impl Into<String>) {
+        self.push(Severity::Info, path, message);
+    }
+}
+
+/// Check a request against the profiles data model.
+pub fn validate(req: &ExportProfilesServiceRequest) -> Report {
+    let mut report = Report::default();
+
+    let Some(dict) = &req.dictionary else {
+        report.error("dictionary", "ProfilesDictionary is required");
+        return report;
+    };
+
+    let mut v = Validator {
+        dict,
+        report: &mut report,
+    };
+
+    v.check_dictionary();
+
+    let mut units = HashMap::new();
+    for (i, resource_profile) in req.resource_profiles.iter().enumerate() {
+        for (j, scope_profile) in resource_profile.scope_profiles.iter().enumerate() {
+            for (k, profile) in scope_profile.profiles.iter().enumerate() {
+                let path = format!("resource_profiles[{i}].scope_profiles[{j}].profiles[{k}]");
+                v.check_profile(&path, profile, &mut units);
+            }
+        }
+    }
+
+    report
+}
+
+struct Validator<'a> {
+    dict: &'a ProfilesDictionary,
+    report: &'a mut Report,
+}
+
+impl<'a> Validator<'a> {
+    fn check_dictionary(&mut self) {
+        let dict = self.dict;
+
+        match dict.string_table.first() {
+            None => self.report.error(
+                "dictionary.string_table",
+                "must contain at least the empty string",
+            ),
+            Some(s) if !s.is_empty() => self
+                .report
+                .error("dictionary.string_table[0]", "must be the empty string"),
+            Some(_) => {}
+        }
+
+        check_zero_entry(self.report, "dictionary.mapping_table", &dict.mapping_table);
+        check_zero_entry(
+            self.report,
+            "dictionary.location_table",
+            &dict.location_table,
+        );
+        check_zero_entry(
+            self.report,
+            "dictionary.function_table",
+            &dict.function_table,
+        );
+        check_zero_entry(self.report, "dictionary.link_table", &dict.link_table);
+        check_zero_entry(
+            self.report,
+            "dictionary.attribute_table",
+            &dict.attribute_table,
+        );
+        check_zero_entry(self.report, "dictionary.stack_table", &dict.stack_table);
+
+        check_dedup(
+            self.report,
+            "dictionary.string_table",
+            &dict.string_table,
+            String::as_str,
+        );
+        check_dedup(
+            self.report,
+            "dictionary.mapping_table",
+            &dict.mapping_table,
+            encode,
+        );
+        check_dedup(
+            self.report,
+            "dictionary.location_table",
+            &dict.location_table,
+            encode,
+        );
+        check_dedup(
+            self.report,
+            "dictionary.function_table",
+            &dict.function_table,
+            encode,
+        );
+        check_dedup(
+            self.report,
+            "dictionary.link_table",
+            &dict.link_table,
+            encode,
+        );
+        check_dedup(
+            self.report,
+            "dictionary.attribute_table",
+            &dict.attribute_table,
+            encode,
+        );
+        check_dedup(
+            self.report,
+            "dictionary.stack_table",
+            &dict.stack_table,
+            encode,
+        );
+
+        self.check_attributes();
+        self.check_mappings();
+        self.check_functions();
+        self.check_locations();
+        self.check_links();
+        self.check_stacks();
+    }
+
+    fn check_attributes(&mut self) {
+        let dict = self.dict;
+        let mut units = HashMap::new();
+        for (i, kvu) in dict.attribute_table.iter().enumerate().skip(1) {
+            let path = format!("dictionary.attribute_table[{i}]");
+            let key = self.str_required(&format!("{path}.key_strindex"), kvu.key_strindex);
+            let unit = self.str_opt(&format!("{path}.unit_strindex"), kvu.unit_strindex);
+
+            if kvu.value.as_ref().and_then(|x| x.value.as_ref()).is_none() {
+                self.report
+                    .warn(format!("{path}.value"), "attribute has no value");
+            }
+
+            if let (Some(key), Some(unit)) = (key, unit) {
+                let prev = units.entry(key).or_insert((i, unit));
+                if prev.1 != unit {
+                    self.report.error(
+                        format!("{path}.unit_strindex"),
+                        format!(
+                            "unit {unit:?} for attribute {key:?} is inconsistent \
+                            with unit {:?} in attribute_table[{}]",
+                            prev.1, prev.0
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_mappings(&mut self) {
+        let dict = self.dict;
+        for (i, mapping) in dict.mapping_table.iter().enumerate().skip(1) {
+            let path = format!("dictionary.mapping_table[{i}]");
+
+            if mapping.memory_start > mapping.memory_limit {
+                self.report.error(
+                    format!("{path}.memory_limit"),
+                    format!(
+                        "memory_limit {:#x} is below memory_start {:#x}",
+                        mapping.memory_limit, mapping.memory_start
+                    ),
+                );
+            }
+
+            self.str_opt(
+                &format!("{path}.filename_strindex"),
+                mapping.filename_strindex,
+            );
+            self.check_attr_indices(&path, &mapping.attribute_indices);
+        }
+    }
+
+    fn check_functions(&mut self) {
+        let dict = self.dict;
+        for (i, func) in dict.function_table.iter().enumerate().skip(1) {
+            let path = format!("dictionary.function_table[{i}]");
+            self.str_opt(&format!("{path}.name_strindex"), func.name_strindex);
+            self.str_opt(
+                &format!("{path}.system_name_strindex"),
+                func.system_name_strindex,
+            );
+            self.str_opt(&format!("{path}.filename_strindex"), func.filename_strindex);
+
+            if func.start_line < 0 {
+                self.report
+                    .error(format!("{path}.start_line"), "must not be negative");
+            }
+        }
+    }
+
+    fn check_locations(&mut self) {
+        let dict = self.dict;
+        for (i, loc) in dict.location_table.iter().enumerate().skip(1) {
+            let path = format!("dictionary.location_table[{i}]");
+
+            self.check_attr_indices(&path, &loc.attribute_indices);
+
+            let frame_type = self.find_str_attr(&loc.attribute_indices, "profile.frame.type");
+            match frame_type {
+                None => self.report.error(
+                    format!("{path}.attribute_indices"),
+                    "required attribute profile.frame.type is missing",
+                ),
+                Some(ty) if FrameKind::from_frame_type(ty).is_none() => self.report.info(
+                    format!("{path}.attribute_indices"),
+                    format!("profile.frame.type {ty:?} is unknown to devfiler"),
+                ),
+                Some(_) => {}
+            }
+
+            let mapping = self.index(
+                &format!("{path}.mapping_index"),
+                loc.mapping_index,
+                dict.mapping_table.len(),
+            );
+
+            if let (Some("native"), Some(mapping)) = (frame_type, mapping) {
+                let indices = &dict.mapping_table[mapping].attribute_indices;
+                let build_id = ["htlhash", "profiling"].iter().find_map(|suffix| {
+                    let key = format!("process.executable.build_id.{suffix}");
+                    self.find_str_attr(indices, &key)
+                });
+
+                if build_id.is_none() {
+                    self.report.warn(
+                        format!("{path}.mapping_index"),
+                        "native frame without process.executable.build_id.htlhash \
+                        mapping attribute can't be symbolized",
+                    );
+                }
+            }
+
+            if frame_type
+                .is_some_and(|x| x != "native" && x != "abort-marker" && !x.ends_with("-error"))
+                && loc.lines.is_empty()
+            {
+                self.report.info(
+                    format!("{path}.lines"),
+                    "interpreted frame without line information",
+                );
+            }
+
+            for (j, line) in loc.lines.iter().enumerate() {
+                let path = format!("{path}.lines[{j}]");
+                self.index(
+                    &format!("{path}.function_index"),
+                    line.function_index,
+                    dict.function_table.len(),
+                );
+                if line.line < 0 {
+                    self.report
+                        .error(format!("{path}.line"), "must not be negative");
+                }
+                if line.column < 0 {
+                    self.report
+                        .error(format!("{path}.column"), "must not be negative");
+                }
+            }
+        }
+    }
+
+    fn check_links(&mut self) {
+        let dict = self.dict;
+        for (i, link) in dict.link_table.iter().enumerate().skip(1) {
+            let path = format!("dictionary.link_table[{i}]");
+            if link.trace_id.len() != 16 {
+                self.report.error(
+                    format!("{path}.trace_id"),
+                    format!("must be 16 bytes, got {}", link.trace_id.len()),
+                );
+            }
+            if link.span_id.len() != 8 {
+                self.report.error(
+                    format!("{path}.span_id"),
+                    format!("must be 8 bytes, got {}", link.span_id.len()),
+                );
+            }
+        }
+    }
+
+    fn check_stacks(&mut self) {
+        let dict = self.dict;
+        for (i, stack) in dict.stack_table.iter().enumerate().skip(1) {
+            let path = format!("dictionary.stack_table[{i}]");
+            if stack.location_indices.is_empty() {
+                self.report
+                    .warn(format!("{path}.location_indices"), "stack is empty");
+            }
+            for (j, &idx) in stack.location_indices.iter().enumerate() {
+                let path = format!("{path}.location_indices[{j}]");
+                if idx == 0 {
+                    self.report
+                        .error(path, "index 0 refers to the reserved zero location");
+                } else {
+                    self.index(&path, idx, dict.location_table.len());
+                }
+            }
+        }
+    }
+
+    fn check_profile(
+        &mut self,
+        path: &str,
+        profile: &Profile,
+        units: &mut HashMap<&'a str, (String, &'a str)>,
+    ) {
+        match &profile.sample_type {
+            None => self
+                .report
+                .error(format!("{path}.sample_type"), "sample_type is required"),
+            Some(st) => {
+                let st_path = format!("{path}.sample_type");
+                if let Some((ty, unit)) = self.value_type(&st_path, st) {
+                    if !KNOWN_SAMPLE_TYPES.contains(&(ty, unit)) {
+                        self.report.info(
+                            st_path.clone(),
+                            format!("sample type {ty}/{unit} is shown as Unknown"),
+                        );
+                    }
+
+                    let (prev_path, prev_unit) =
+                        units.entry(ty).or_insert_with(|| (st_path.clone(), unit));
+                    if *prev_unit != unit {
+                        self.report.error(
+                            format!("{st_path}.unit_strindex"),
+                            format!(
+                                "unit {unit:?} for sample type {ty:?} is inconsistent \
+                                with unit {prev_unit:?} in {prev_path}"
+                            ),
+                        );
+                    }
+                }
+            }
+        }
+
+        match &profile.period_type {
+            Some(pt) => {
+                self.value_type(&format!("{path}.period_type"), pt);
+            }
+            None if profile.period != 0 => self.report.warn(
+                format!("{path}.period_type"),
+                "period is set without period_type",
+            ),
+            None => {}
+        }
+
+        if profile.period < 0 {
+            self.report
+                .error(format!("{path}.period"), "must not be negative");
+        }
+
+        if profile.time_unix_nano == 0 {
+            self.report.warn(
+                format!("{path}.time_unix_nano"),
+                "profile start time is not set",
+            );
+        } else if profile.time_unix_nano < MIN_NANO_TIMESTAMP {
+            self.report.warn(
+                format!("{path}.time_unix_nano"),
+                "timestamp is before 2024: not in nanoseconds?",
+            );
+        }
+
+        if !profile.profile_id.is_empty() && profile.profile_id.len() != 16 {
+            self.report.error(
+                format!("{path}.profile_id"),
+                format!("must be 16 bytes, got {}", profile.profile_id.len()),
+            );
+        }
+
+        self.check_attr_indices(path, &profile.attribute_indices);
+
+        if profile.samples.is_empty() {
+            self.report
+                .info(format!("{path}.samples"), "profile has no samples");
+        }
+
+        for (i, sample) in profile.samples.iter().enumerate() {
+            self.check_sample(&format!("{path}.samples[{i}]"), profile, sample);
+        }
+    }
+
+    fn check_sample(&mut self, path: &str, profile: &Profile, sample: &Sample) {
+        let dict = self.dict;
+        if sample.stack_index == 0 {
+            self.report.warn(
+                format!("{path}.stack_index"),
+                "sample refers to the empty stack",
+            );
+        } else {
+            self.index(
+                &format!("{path}.stack_index"),
+                sample.stack_index,
+                dict.stack_table.len(),
+            );
+        }
+
+        self.index(
+            &format!("{path}.link_index"),
+            sample.link_index,
+            dict.link_table.len(),
+        );
+
+        self.check_attr_indices(path, &sample.attribute_indices);
+
+        if sample.values.is_empty() && sample.timestamps_unix_nano.is_empty() {
+            self.report
+                .error(path, "sample has neither values nor timestamps_unix_nano");
+        }
+
+        if !sample.values.is_empty()
+            && !sample.timestamps_unix_nano.is_empty()
+            && sample.values.len() != sample.timestamps_unix_nano.len()
+        {
+            self.report.error(
+                format!("{path}.values"),
+                format!(
+                    "{} values for {} timestamps",
+                    sample.values.len(),
+                    sample.timestamps_unix_nano.len()
+                ),
+            );
+        }
+
+        let start = profile.time_unix_nano;
+        let end = start.saturating_add(profile.duration_nano);
+        for (i, &ts) in sample.timestamps_unix_nano.iter().enumerate() {
+            let ts_path = format!("{path}.timestamps_unix_nano[{i}]");
+            if ts < MIN_NANO_TIMESTAMP {
+                self.report.warn(
+                    ts_path,
+                    "timestamp is before 2024: devfiler interprets it as milliseconds",
+                );
+            } else if start != 0 && profile.duration_nano != 0 && !(start..=end).contains(&ts) {
+                self.report.warn(
+                    ts_path,
+                    format!("timestamp outside of profile time range [{start}, {end}]"),
+                );
+            }
+        }
+    }
+
+    /// Check a value type, returning its `(type, unit)` strings if valid.
+    fn value_type(&mut self, path: &str, vt: &ValueType) -> Option<(&'a str, &'a str)> {
+        let ty = self.str_required(&format!("{path}.type_strindex"), vt.type_strindex);
+        let unit = self.str_required(&format!("{path}.unit_strindex"), vt.unit_strindex);
+        Some((ty?, unit?))
+    }
+
+    fn check_attr_indices(&mut self, path: &str, indices: &[i32]) {
+        let dict = self.dict;
+        let mut keys = HashMap::new();
+
+        for (i, &idx) in indices.iter().enumerate() {
+            let idx_path = format!("{path}.attribute_indices[{i}]");
+            if idx == 0 {
+                self.report
+                    .warn(idx_path, "index 0 refers to the reserved zero attribute");
+                continue;
+            }
+            let Some(idx) = self.index(&idx_path, idx, dict.attribute_table.len()) else {
+                continue;
+            };
+
+            let key = dict.attribute_table[idx].key_strindex;
+            if let Some(prev) = keys.insert(key, i) {
+                self.report.error(
+                    idx_path,
+                    format!("duplicate attribute key, also set by attribute_indices[{prev}]"),
+                );
+            }
+        }
+    }
+
+    /// Find a string attribute without reporting problems.
+    fn find_str_attr(&self, indices: &[i32], key: &str) -> Option<&'a str> {
+        let dict = self.dict;
+        indices
+            .iter()
+            .filter_map(|&idx| dict.attribute_table.get(usize::try_from(idx).ok()?))
+            .filter(|kvu: &&KeyValueAndUnit| {
+                dict.string_table
+                    .get(kvu.key_strindex as usize)
+                    .map(String::as_str)
+                    == Some(key)
+            })
+            .find_map(|kvu| match kvu.value.as_ref()?.value.as_ref()? {
+                Value::StringValue(s) => Some(s.as_str()),
+                _ => None,
+            })
+    }
+
+    /// Check that an index is within `0..len`, returning it as `usize` if so.
+    ///
+    /// Index 0 is accepted for empty tables: producers commonly omit tables they
+    /// don't use entirely instead of sending just the zero value.
+    fn index(&mut self, path: &str, idx: i32, len: usize) -> Option<usize> {
+        match usize::try_from(idx) {
+            Ok(idx) if idx < len => Some(idx),
+            Ok(0) => None,
+            _ => {
+                self.report.error(
+                    path,
+                    format!("index {idx} is out of bounds (table length {len})"),
+                );
+                None
+            }
+        }
+    }
+
+    /// Resolve a string index that must not be 0.
+    fn str_required(&mut self, path: &str, idx: i32) -> Option<&'a str> {
+        if idx == 0 {
+            self.report
+                .error(path, "string index 0 is not permitted for required fields");
+            return None;
+        }
+        self.str_opt(path, idx)
+    }
+
+    /// Resolve a string index where 0 means "not set".
+    fn str_opt(&mut self, path: &str, idx: i32) -> Option<&'a str> {
+        let dict = self.dict;
+        let idx = self.index(path, idx, dict.string_table.len())?;
+        (idx != 0).then(|| dict.string_table[idx].as_str())
+    }
+}
+
+/// Check that the first entry of a table is the zero value, as required by the spec.
+fn check_zero_entry<T: Default + PartialEq>(report: &mut Report, path: &str, table: &[T]) {
+    if table.first().is_some_and(|first| *first != T::default()) {
+        report.error(format!("{path}[0]"), "first entry must be the zero value");
+    }
+}
+
+fn encode<T: prost::Message>(entry: &T) -> Vec<u8> {
+    entry.encode_to_vec()
+}
+
+/// Report entries that occur more than once in a dictionary table.
+fn check_dedup<'t, T, K>(report: &mut Report, path: &str, table: &'t [T], key: impl Fn(&'t T) -> K)
+where
+    K: Hash + Eq,
+{
+    let mut seen = HashMap::with_capacity(table.len());
+    for (i, entry) in table.iter().enumerate() {
+        match seen.entry(key(entry)) {
+            Entry::Occupied(first) => report.warn(
+                format!("{path}[{i}]"),
+                format!(
+                    "duplicate of entry {}: table is not deduplicated",
+                    first.get()
+                ),
+            ),
+            Entry::Vacant(slot) => {
+                slot.insert(i);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collector::otlp::pb::common::v1::AnyValue;
+    use crate::collector::otlp::pb::profiles::v1development::{
+        Function, Line, Location, Mapping, ResourceProfiles, ScopeProfiles, Stack,
+    };
+
+    const NOW: u64 = 1_750_000_000 * 1_000_000_000;
+
+    fn str_attr(key: i32, value: &str) -> KeyValueAndUnit {
+        KeyValueAndUnit {
+            key_strindex: key,
+            value: Some(AnyValue {
+                value: Some(Value::StringValue(value.into())),
+            }),
+            unit_strindex: 0,
+        }
+    }
+
+    /// Minimal request with a single native and a single interpreted frame.
+    fn valid_request() -> ExportProfilesServiceRequest {
+        let string_table = [
+            "",
+            "profile.frame.type",
+            "process.executable.build_id.htlhash",
+            "samples",
+            "count",
+            "main",
+            "main.py",
+            "libc.so.6",
+        ];
+
+        let dictionary = ProfilesDictionary {
+            string_table: string_table.iter().map(|&x| x.into()).collect(),
+            attribute_table: vec![
+                KeyValueAndUnit::default(),
+                str_attr(1, "native"),
+                str_attr(1, "cpython"),
+                str_attr(2, "2a6b1b3b2e0b4e6c9f0e0b0b0b0b0b0b"),
+            ],
+            mapping_table: vec![
+                Mapping::default(),
+                Mapping {
+                    memory_start: 0x1000,
+                    memory_limit: 0x2000,
+                    file_offset: 0,
+                    filename_strindex: 7,
+                    attribute_indices: vec![3],
+                },
+            ],
+            function_table: vec![
+                Function::default(),
+                Function {
+                    name_strindex: 5,
+                    filename_strindex: 6,
+                    ..Default::default()
+                },
+            ],
+            location_table: vec![
+                Location::default(),
+                Location {
+                    mapping_index: 1,
+                    address: 0x1234,
+                    lines: vec![],
+                    attribute_indices: vec![1],
+                },
+                Location {
+                    mapping_index: 1,
+                    address: 0,
+                    lines: vec![Line {
+                        function_index: 1,
+                        line: 42,
+                        column: 0,
+                    }],
+                    attribute_indices: vec![2],
+                },
+            ],
+            stack_table: vec![
+                Stack::default(),
+                Stack {
+                    location_indices: vec![1, 2],
+                },
+            ],
+            ..Default::default()
+        };
+
+        let profile = Profile {
+            sample_type: Some(ValueType {
+                type_strindex: 3,
+                unit_strindex: 4,
+            }),
+            samples: vec![Sample {
+                stack_index: 1,
+                timestamps_unix_nano: vec![NOW + 1],
+                ..Default::default()
+            }],
+            time_unix_nano: NOW,
+            duration_nano: 1_000_000_000,
+            ..Default::default()
+        };
+
+        ExportProfilesServiceRequest {
+            resource_profiles: vec![ResourceProfiles {
+                scope_profiles: vec![ScopeProfiles {
+                    profiles: vec![profile],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            dictionary: Some(dictionary),
+        }
+    }
+
+    fn find<'r>(report: &'r Report, path: &str) -> &'r Finding {
+        report
+            .findings
+            .iter()
+            .find(|x| x.path == path)
+            .unwrap_or_else(|| panic!("no finding for {path} in {report:#?}"))
+    }
+
+    #[test]
+    fn valid() {
+        let report = validate(&valid_request());
+        assert!(report.findings.is_empty(), "{report:#?}");
+    }
+
+    #[test]
+    fn missing_dictionary() {
+        let mut req = valid_request();
+        req.dictionary = None;
+        let report = validate(&req);
+        assert_eq!(find(&report, "dictionary").severity, Severity::Error);
+    }
+
+    #[test]
+    fn index_bounds() {
+        let mut req = valid_request();
+        let dict = req.dictionary.as_mut().unwrap();
+        dict.location_table[1].mapping_index = 9;
+        dict.stack_table[1].location_indices.push(-1);
+        dict.function_table[1].name_strindex = 100;
+
+        let report = validate(&req);
+        assert_eq!(report.max_severity(), Some(Severity::Error));
+        find(&report, "dictionary.location_table[1].mapping_index");
+        find(&report, "dictionary.stack_table[1].location_indices[2]");
+        find(&report, "dictionary.function_table[1].name_strindex");
+    }
+
+    #[test]
+    fn string_index_zero() {
+        let mut req = valid_request();
+        req.dictionary.as_mut().unwrap().string_table[0] = "oops".into();
+        let profile = &mut req.resource_profiles[0].scope_profiles[0].profiles[0];
+        profile.sample_type.as_mut().unwrap().unit_strindex = 0;
+
+        let report = validate(&req);
+        find(&report, "dictionary.string_table[0]");
+        let path = "resource_profiles[0].scope_profiles[0].profiles[0].sample_type.unit_strindex";
+        assert_eq!(find(&report, path).severity, Severity::Error);
+    }
+
+    #[test]
+    fn required_attributes() {
+        let mut req = valid_request();
+        let dict = req.dictionary.as_mut().unwrap();
+        dict.location_table[2].attribute_indices.clear();
+        dict.mapping_table[1].attribute_indices.clear();
+
+        let report = validate(&req);
+        let missing_type = find(&report, "dictionary.location_table[2].attribute_indices");
+        assert_eq!(missing_type.severity, Severity::Error);
+        let missing_build_id = find(&report, "dictionary.location_table[1].mapping_index");
+        assert_eq!(missing_build_id.severity, Severity::Warning);
+    }
+
+    #[test]
+    fn dedup() {
+        let mut req = valid_request();
+        let dict = req.dictionary.as_mut().unwrap();
+        dict.string_table.push("main".into());
+        let dup = dict.function_table[1].clone();
+        dict.function_table.push(dup);
+
+        let report = validate(&req);
+        assert_eq!(report.count(Severity::Warning), 2, "{report:#?}");
+        find(&report, "dictionary.string_table[8]");
+        find(&report, "dictionary.function_table[2]");
+    }
+
+    #[test]
+    fn timestamps() {
+        let mut req = valid_request();
+        let profile = &mut req.resource_profiles[0].scope_profiles[0].profiles[0];
+        profile.samples[0].timestamps_unix_nano = vec![NOW / 1_000_000, NOW + 10_000_000_000];
+        profile.samples[0].values = vec![1];
+
+        let report = validate(&req);
+        let sample = "resource_profiles[0].scope_profiles[0].profiles[0].samples[0]";
+        find(&report, &format!("{sample}.timestamps_unix_nano[0]"));
+        find(&report, &format!("{sample}.timestamps_unix_nano[1]"));
+        assert_eq!(
+            find(&report, &format!("{sample}.values")).severity,
+            Severity::Error
+        );
+    }
+
+    #[test]
+    fn unit_consistency() {
+        let mut req = valid_request();
+        let dict = req.dictionary.as_mut().unwrap();
+        dict.string_table.push("nanoseconds".into());
+        let scope = &mut req.resource_profiles[0].scope_profiles[0];
+        let mut other = scope.profiles[0].clone();
+        other.sample_type.as_mut().unwrap().unit_strindex = 8;
+        scope.profiles.push(other);
+
+        let report = validate(&req);
+        let path = "resource_profiles[0].scope_profiles[0].profiles[1].sample_type.unit_strindex";
+        assert_eq!(find(&report, path).severity, Severity::Error);
+    }
+}
@@ -15,8 +15,10 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use anyhow::Ok;
+use anyhow::{Context, Ok};
+use std::path::PathBuf;
 
+mod cli;
 mod collector;
 mod log;
 mod storage;
@@ -26,6 +28,24 @@ mod ui;
 #[global_allocator]
 static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
 
+/// Global command line flags that are followed by a value.
+const VALUE_FLAGS: &[&str] = &[
+    "--db-dir",
+    "--serve-symbols",
+    "--symb-endpoint",
+    "--symb-concurrency",
+    "--symbol-dir",
+    "--debuginfod-url",
+    "--path-map",
+    "--editor",
+    "--forward-endpoint",
+    "--forward-header",
+    "--forward-ca-cert",
+    "--forward-tls-domain",
+    "--forward-buffer",
+    "--forward-retries",
+];
+
 fn main() -> anyhow::Result<()> {
     log::install();
 
@@ -34,27 +54,180 @@ fn main() -> anyhow::Result<()> {
         return Ok(());
     }
 
+    if let Some(dir) = arg_value("--db-dir") {
+        storage::set_db_dir(dir.into());
+    }
+
     let rt = tokio::runtime::Runtime::new()?;
     let _rt_guard = rt.enter(); // make rt avail on main thread
 
+    let command = command_args();
+    if command.first().map(String::as_str) == Some("symbols") {
+        return cli::symbols(&command[1..]);
+    }
+
     let collector_addr = "0.0.0.0:11000".parse().unwrap();
-    let collector = collector::Collector::new(collector_addr);
+    let mut collector = collector::Collector::new(collector_addr);
+
+    if let Some(cfg) = forward_config()? {
+        collector = collector.with_forwarding(cfg)?;
+    }
+    if std::env::args().any(|x| x == "--validate") {
+        collector = collector.with_validation();
+    }
+
+    if let Some(addr) = arg_value("--serve-symbols") {
+        let addr = addr.parse().context("invalid --serve-symbols address")?;
+        rt.spawn(async move {
+            if let Err(e) = symbolizer::serve_symbols(addr).await {
+                tracing::error!("{e:?}");
+            }
+        });
+    }
 
     if std::env::args().any(|x| x == "--collector-only") {
         rt.block_on(collector.serve())?;
     } else {
-        let symb_endpoint = std::env::args()
-            .collect::<Vec<String>>()
-            .windows(2)
-            .find(|pair| pair[0] == "--symb-endpoint")
-            .map(|pair| pair[1].clone())
-            .unwrap_or_else(|| String::new());
-
-        rt.spawn(symbolizer::monitor_executables(symb_endpoint));
+        let symb_sources = symbol_sources()?;
+        let debuginfod = symbolizer::Debuginfod::from_args_or_env(arg_values("--debuginfod-url"));
+
+        let symb_concurrency = match arg_value("--symb-concurrency") {
+            Some(n) => n.parse().context("invalid --symb-concurrency")?,
+            None => symbolizer::SYMB_DEFAULT_PAR,
+        };
+
+        rt.spawn(symbolizer::monitor_executables(
+            symb_sources,
+            debuginfod,
+            symb_concurrency,
+        ));
+
+        let symbol_dirs: Vec<_> = arg_values("--symbol-dir").map(PathBuf::from).collect();
+        if !symbol_dirs.is_empty() {
+            rt.spawn(symbolizer::watch_symbol_dirs(symbol_dirs));
+        }
+
+        let path_map = ui::PathMap::from_args(arg_values("--path-map"))?;
+        let editor = arg_value("--editor");
+
         let collector2 = collector.clone();
         rt.spawn(async move { collector2.serve().await });
-        ui::gui_thread(collector).unwrap();
+        ui::gui_thread(collector, path_map, editor).unwrap();
     }
 
     Ok(())
 }
+
+/// Value of the first occurrence of the given command line flag.
+fn arg_value(flag: &str) -> Option<String> {
+    arg_values(flag).next()
+}
+
+/// Sub-command and its arguments, with the global flags removed.
+fn command_args() -> Vec<String> {
+    strip_global_flags(std::env::args().skip(1))
+}
+
+/// Remove global flags (and the values of [`VALUE_FLAGS`]) from the given
+/// arguments.
+///
+/// The first remaining argument is the sub-command. Flags that don't take
+/// a value are only removed before it.
+fn strip_global_flags(args: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut command = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if VALUE_FLAGS.contains(&arg.as_str()) {
+            args.next();
+        } else if command.is_empty() && arg.starts_with('-') {
+            continue;
+        } else {
+            command.push(arg);
+        }
+    }
+    command
+}
+
+/// Values of all occurrences of the given command line flag.
+fn arg_values(flag: &str) -> impl Iterator<Item = String> {
+    std::env::args()
+        .collect::<Vec<String>>()
+        .windows(2)
+        .filter(|pair| pair[0] == flag)
+        .map(|pair| pair[1].clone())
+        .collect::<Vec<_>>()
+        .into_iter()
+}
+
+/// Build the symbol sources from the `--symb-endpoint` command line flags.
+///
+/// Sources are tried in the order given. Empty values are ignored.
+fn symbol_sources() -> anyhow::Result<Vec<symbolizer::SymbolSource>> {
+    arg_values("--symb-endpoint")
+        .filter(|x| !x.trim().is_empty())
+        .map(|spec| {
+            let cfg =
+                symbolizer::SymbolSourceConfig::parse(&spec).context("invalid --symb-endpoint")?;
+            symbolizer::SymbolSource::new(&cfg)
+                .with_context(|| format!("failed to set up symbol endpoint {}", cfg.url))
+        })
+        .collect()
+}
+
+/// Build the forwarding config from the `--forward-*` command line flags.
+fn forward_config() -> anyhow::Result<Option<collector::ForwardConfig>> {
+    let Some(endpoint) = arg_value("--forward-endpoint") else {
+        return Ok(None);
+    };
+
+    let mut cfg = collector::ForwardConfig::new(endpoint);
+
+    for header in arg_values("--forward-header") {
+        let (key, value) = header.split_once('=').with_context(|| {
+            format!("expected `key=value` for --forward-header, got {header:?}")
+        })?;
+        cfg.headers
+            .push((key.trim().to_owned(), value.trim().to_owned()));
+    }
+
+    cfg.ca_cert = arg_value("--forward-ca-cert").map(Into::into);
+    cfg.tls_domain = arg_value("--forward-tls-domain");
+
+    if let Some(size) = arg_value("--forward-buffer") {
+        cfg.buffer_size = size.parse().context("invalid --forward-buffer")?;
+    }
+    if let Some(retries) = arg_value("--forward-retries") {
+        cfg.max_retries = retries.parse().context("invalid --forward-retries")?;
+    }
+
+    Ok(Some(cfg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(args: &str) -> Vec<String> {
+        strip_global_flags(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn global_flags() {
+        assert_eq!(
+            command("--symb-concurrency 4 --db-dir /tmp/db symbols list"),
+            ["symbols", "list"]
+        );
+        assert_eq!(
+            command("--validate --symbol-dir symbols symbols add a.out"),
+            ["symbols", "add", "a.out"]
+        );
+        assert_eq!(
+            command("symbols export abc --db-dir /tmp/db out.symbfile"),
+            ["symbols", "export", "abc", "out.symbfile"]
+        );
+
+        // A flag value named like the sub-command isn't mistaken for it.
+        assert!(command("--editor symbols --collector-only").is_empty());
+        assert!(command("").is_empty());
+    }
+}
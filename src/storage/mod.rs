@@ -18,7 +18,8 @@
 //! Defines the schema of our tables and abstracts access to the underlying
 //! storage solution.
 
-use std::sync::Arc;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
 use tracing::warn;
 
 /// DB schema version.
@@ -26,7 +27,19 @@ use tracing::warn;
 /// Bump this on any breaking schema change. Both the serialization scheme for
 /// our keys and our values doesn't support schema evolution, so essentially any
 /// change other than adding or deleting tables is a breaking one.
-const DB_VERSION: u32 = 5;
+const DB_VERSION: u32 = 13;
+
+/// Directory overriding the default database location.
+///
+/// Must be set before [`DB`] is first accessed.
+static DB_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Use the given directory instead of `~/.cache/devfiler` for the database.
+pub fn set_db_dir(dir: PathBuf) {
+    if DB_DIR.set(dir).is_err() {
+        panic!("DB directory was already set");
+    }
+}
 
 lazy_static::lazy_static! {
     /// Global database instance.
@@ -36,9 +49,13 @@ lazy_static::lazy_static! {
 pub struct Db {
     // RocksDB tables.
     pub trace_events: TraceEvents,
+    pub trace_labels: TraceLabelSets,
     pub stack_traces: StackTraces,
     pub stack_frames: StackFrames,
     pub executables: Executables,
+    pub frame_types: FrameTypes,
+    pub kernel_images: KernelImages,
+    pub kernel_symbols: KernelSymbolSets,
 
     // Custom data storage.
     pub symbols: SymDb,
@@ -46,27 +63,31 @@ pub struct Db {
 
 impl Db {
     /// Number of tables.
-    pub const NUM_TABLES: usize = 4;
+    pub const NUM_TABLES: usize = 8;
 
     /// Create or open the database.
     fn open() -> anyhow::Result<Arc<Self>> {
-        let home = home::home_dir().unwrap_or_else(|| {
-            warn!("Unable to determine home directory: fallback to /tmp.");
-            "/tmp".into()
+        let base_dir = DB_DIR.get().cloned().unwrap_or_else(|| {
+            let home = home::home_dir().unwrap_or_else(|| {
+                warn!("Unable to determine home directory: fallback to /tmp.");
+                "/tmp".into()
+            });
+            home.join(".cache").join("devfiler")
         });
 
-        let db_dir = &home
-            .join(".cache")
-            .join("devfiler")
-            .join(DB_VERSION.to_string());
+        let db_dir = &base_dir.join(DB_VERSION.to_string());
 
         std::fs::create_dir_all(db_dir)?;
 
         Ok(Arc::new(Db {
             trace_events: open_or_create(db_dir)?,
+            trace_labels: open_or_create(db_dir)?,
             stack_traces: open_or_create(db_dir)?,
             stack_frames: open_or_create(db_dir)?,
             executables: open_or_create(db_dir)?,
+            frame_types: open_or_create(db_dir)?,
+            kernel_images: open_or_create(db_dir)?,
+            kernel_symbols: open_or_create(db_dir)?,
             symbols: SymDb::open_at(db_dir.join("symbols"))?,
         }))
     }
@@ -76,6 +97,9 @@ impl Db {
         for (key, _) in self.trace_events.iter() {
             self.trace_events.remove(key);
         }
+        for (key, _) in self.trace_labels.iter() {
+            self.trace_labels.remove(key);
+        }
         for (key, _) in self.stack_traces.iter() {
             self.stack_traces.remove(key);
         }
@@ -85,6 +109,9 @@ impl Db {
         for (key, _) in self.executables.iter() {
             self.executables.remove(key);
         }
+        for (key, _) in self.kernel_images.iter() {
+            self.kernel_images.remove(key);
+        }
     }
 
     /// Generate a unique ID.
@@ -97,9 +124,13 @@ impl Db {
     pub fn tables(&self) -> [&dyn RawTable; Self::NUM_TABLES] {
         [
             &self.trace_events,
+            &self.trace_labels,
             &self.stack_traces,
             &self.stack_frames,
             &self.executables,
+            &self.frame_types,
+            &self.kernel_images,
+            &self.kernel_symbols,
         ]
     }
 }
@@ -142,6 +142,11 @@ where
         todo
     }
 
+    /// Iterates over all elements, ordered by the start of their range.
+    pub fn elements(&self) -> impl Iterator<Item = &ArchivedElement<K, V>> {
+        self.data.iter().map(|node| &node.element)
+    }
+
     /// Queries the interval tree for all elements overlapping a given interval.
     ///
     /// This runs in `O(log n + m)`.
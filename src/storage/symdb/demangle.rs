@@ -0,0 +1,266 @@
+// Copyright Elasticsearch B.V. and/or licensed to Elasticsearch B.V. under one
+// or more contributor license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Demangling and simplification of native function names.
+
+/// Demangle an Itanium C++ or Rust (legacy and v0) symbol name.
+///
+/// Names that don't look mangled are returned unchanged.
+pub fn demangle(name: &str) -> String {
+    if !name.starts_with("_Z") && !name.starts_with("_R") {
+        return name.to_owned();
+    }
+
+    // Legacy Rust symbols are also valid Itanium symbols: try Rust first to
+    // get the nicer Rust specific formatting.
+    if let Ok(sym) = rustc_demangle::try_demangle(name) {
+        return sym.to_string();
+    }
+
+    cpp_demangle::Symbol::new(name)
+        .ok()
+        .and_then(|sym| sym.demangle().ok())
+        .unwrap_or_else(|| name.to_owned())
+}
+
+/// Simplify a demangled function name for display.
+///
+/// Strips template and generic arguments, argument lists, Rust hash suffixes
+/// and crate disambiguators. Qualified paths like `<T as Trait>::method` and
+/// operator names are preserved.
+pub fn simplify_name(name: &str) -> String {
+    let chars: Vec<char> = strip_rust_hash(name).chars().collect();
+    let mut out = String::with_capacity(name.len());
+    let mut operator_end = None;
+    let mut kept = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        // Copy operator symbols verbatim: `operator<`, `operator()`, ...
+        if ends_with_operator(&out) {
+            let len = operator_len(&chars[i..]);
+            if len > 0 {
+                out.extend(&chars[i..i + len]);
+                i += len;
+                if chars[i..].starts_with(&[' ', '<']) {
+                    i += 1;
+                }
+                operator_end = Some(out.len());
+                continue;
+            }
+        }
+
+        // Argument lists nested in kept brackets are part of a type, e.g.
+        // `<impl Fn(u8) -> u8>::call`: only strip generics there.
+        let after_name = out.ends_with(is_name_char) || operator_end == Some(out.len());
+        let strip = match c {
+            '<' => after_name,
+            '(' => after_name && kept.is_empty(),
+            '[' if after_name => {
+                if let Some(end) = disambiguator_end(&chars, i) {
+                    i = end;
+                    continue;
+                }
+                false
+            }
+            _ => false,
+        };
+
+        if strip {
+            i = group_end(&chars, i);
+            if c == '(' {
+                i = skip_qualifiers(&chars, i);
+            }
+            continue;
+        }
+
+        match c {
+            '<' => kept.push('>'),
+            '(' => kept.push(')'),
+            '[' => kept.push(']'),
+            '{' => kept.push('}'),
+            _ if kept.last() == Some(&c) && !out.ends_with('-') => {
+                kept.pop();
+            }
+            _ => {}
+        }
+
+        out.push(c);
+        i += 1;
+    }
+
+    out
+}
+
+fn ends_with_operator(s: &str) -> bool {
+    s.strip_suffix("operator")
+        .is_some_and(|prefix| !prefix.ends_with(is_name_char))
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '$' | '}')
+}
+
+/// Strip the `::h0123456789abcdef` suffix of legacy Rust symbols.
+fn strip_rust_hash(name: &str) -> &str {
+    let Some((prefix, hash)) = name.rsplit_once("::h") else {
+        return name;
+    };
+
+    if hash.len() == 16 && hash.bytes().all(|x| x.is_ascii_hexdigit()) {
+        prefix
+    } else {
+        name
+    }
+}
+
+/// Length of the operator symbol at the start of `chars`, if any.
+fn operator_len(chars: &[char]) -> usize {
+    if chars.starts_with(&['(', ')']) || chars.starts_with(&['[', ']']) {
+        return 2;
+    }
+
+    chars
+        .iter()
+        .take_while(|x| "<>=!+-*/%^&|~,".contains(**x))
+        .count()
+}
+
+/// Index after the bracket closing the group opened at `start`.
+///
+/// Returns the length of the input if the group isn't closed.
+fn group_end(chars: &[char], start: usize) -> usize {
+    let mut stack = Vec::new();
+
+    for (i, &c) in chars.iter().enumerate().skip(start) {
+        let closer = match c {
+            '<' => Some('>'),
+            '(' => Some(')'),
+            '[' => Some(']'),
+            '{' => Some('}'),
+            _ => None,
+        };
+
+        if let Some(closer) = closer {
+            stack.push(closer);
+            continue;
+        }
+
+        // `>` within nested parentheses or part of `->` is not a closer.
+        let is_arrow = c == '>' && i > 0 && chars[i - 1] == '-';
+        if stack.last() == Some(&c) && !is_arrow {
+            stack.pop();
+            if stack.is_empty() {
+                return i + 1;
+            }
+        }
+    }
+
+    chars.len()
+}
+
+/// Skip C++ method qualifiers following an argument list.
+fn skip_qualifiers(chars: &[char], mut i: usize) -> usize {
+    const QUALIFIERS: &[&str] = &[" const", " volatile", " &&", " &"];
+
+    'outer: loop {
+        for qualifier in QUALIFIERS {
+            let len = qualifier.len();
+            let Some(candidate) = chars.get(i..i + len) else {
+                continue;
+            };
+            let followed_by_name = chars.get(i + len).is_some_and(|x| is_name_char(*x));
+            if candidate.iter().copied().eq(qualifier.chars()) && !followed_by_name {
+                i += len;
+                continue 'outer;
+            }
+        }
+
+        return i;
+    }
+}
+
+/// Index after a Rust v0 crate disambiguator like `[d5a8b1e3c4f2a9b0]`.
+fn disambiguator_end(chars: &[char], start: usize) -> Option<usize> {
+    let len = chars[start + 1..].iter().position(|x| *x == ']')?;
+    let hex = &chars[start + 1..start + 1 + len];
+
+    if hex.is_empty() || !hex.iter().all(|x| x.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    Some(start + len + 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn demangle_names() {
+        assert_eq!(demangle("main"), "main");
+        assert_eq!(demangle("_Zfoo"), "_Zfoo");
+        assert_eq!(demangle("_ZN3foo3barEv"), "foo::bar()");
+        assert_eq!(
+            demangle("_ZN4core3fmt5write17h0123456789abcdefE"),
+            "core::fmt::write::h0123456789abcdef"
+        );
+        assert_eq!(demangle("_RNvC9backtrace3foo"), "backtrace::foo");
+    }
+
+    #[test]
+    fn simplify_names() {
+        let cases = [
+            ("main", "main"),
+            ("foo::bar(int, char const*)", "foo::bar"),
+            (
+                "std::vector<int, std::allocator<int> >::push_back(int const&)",
+                "std::vector::push_back",
+            ),
+            ("Foo::get() const &", "Foo::get"),
+            (
+                "(anonymous namespace)::run(int)",
+                "(anonymous namespace)::run",
+            ),
+            ("Foo::operator<(Foo const&) const", "Foo::operator<"),
+            ("Foo::operator<< <int>(int)", "Foo::operator<<"),
+            ("cooperator<int>::run()", "cooperator::run"),
+            ("Foo::operator()(int)", "Foo::operator()"),
+            ("core::fmt::write::h0123456789abcdef", "core::fmt::write"),
+            (
+                "<alloc::vec::Vec<T> as core::fmt::Debug>::fmt",
+                "<alloc::vec::Vec as core::fmt::Debug>::fmt",
+            ),
+            (
+                "std[8d8f1ccb2b45e2bb]::rt::lang_start",
+                "std::rt::lang_start",
+            ),
+            ("<[u8]>::len", "<[u8]>::len"),
+            (
+                "foo::<impl Fn(u8) -> u8>::call",
+                "foo::<impl Fn(u8) -> u8>::call",
+            ),
+            ("bar<Box<dyn Fn() -> u8>>::baz", "bar::baz"),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(simplify_name(input), expected, "input: {input}");
+        }
+    }
+}
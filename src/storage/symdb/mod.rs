@@ -28,6 +28,9 @@ use std::ops::{Deref, Range};
 use std::path::PathBuf;
 use std::sync::RwLock;
 
+mod demangle;
+pub use demangle::{demangle, simplify_name};
+
 /// Custom data store for symbol information.
 pub struct SymDb {
     dir: PathBuf,
@@ -53,6 +56,25 @@ impl SymDb {
         self.dir.join(name)
     }
 
+    /// Path that the symbols for the given file ID are cached at when
+    /// encoded as a zstd compressed symbfile.
+    ///
+    /// The file is removed whenever the symbols change.
+    pub fn encoded_path(&self, file_id: FileId) -> PathBuf {
+        self.dir
+            .join(format!("{}.symbfile.zst", file_id.format_hex()))
+    }
+
+    /// Remove the encoded symbfile cached for the given file ID, if any.
+    fn remove_encoded(&self, file_id: FileId) -> Result<()> {
+        match std::fs::remove_file(self.encoded_path(file_id)) {
+            Err(e) if e.kind() != ErrorKind::NotFound => {
+                Err(e).context("failed to remove encoded symbfile")
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// Retrieve symbols for the given file ID.
     pub fn get(&self, file_id: FileId) -> Result<Option<Arc<MappedSymTree>>> {
         let cache = self.cache.read().unwrap();
@@ -134,9 +156,27 @@ impl SymDb {
 
         // Invalidate cache for this file ID.
         self.cache.write().unwrap().remove(&file_id);
+        self.remove_encoded(file_id)?;
 
         Ok(())
     }
+
+    /// Remove symbols for the given file ID.
+    ///
+    /// Returns `false` if there were no symbols for the file ID.
+    pub fn remove(&self, file_id: FileId) -> Result<bool> {
+        let removed = match std::fs::remove_file(self.path_for_id(file_id, false)) {
+            Ok(()) => true,
+            Err(e) if e.kind() == ErrorKind::NotFound => false,
+            Err(e) => return Err(e).context("failed to remove symtree"),
+        };
+
+        // Invalidate cache for this file ID. Existing mappings stay valid.
+        self.cache.write().unwrap().remove(&file_id);
+        self.remove_encoded(file_id)?;
+
+        Ok(removed)
+    }
 }
 
 /// [`SymTree`] that was stored to disk and is now `mmap`ed into the process.
@@ -242,41 +282,108 @@ pub struct LineTableEntry {
     pub line_number: u32,
 }
 
+/// Address range and name of the outermost function containing the given
+/// address of a native executable.
+pub fn function_range(
+    file_id: FileId,
+    addr: VirtAddr,
+) -> Option<(Range<VirtAddr>, Option<String>)> {
+    let tree = DB.symbols.get(file_id).ok()??;
+    let sym = tree.tree.query_point(addr).min_by_key(|x| x.value.depth)?;
+    let func = tree.str_by_ref(sym.value.func).map(demangle);
+    Some((sym.range.start..sym.range.end, func))
+}
+
 /// Symbolize a frame (and it's inline children, if they exist).
 pub fn symbolize_frame(frame: Frame, inline_frames: bool) -> SmallVec<[SymbolizedFrame; 2]> {
     if frame.kind == FrameKind::Regular(InterpKind::Native) {
-        symbolize_native_frame(frame, inline_frames)
+        symbolize_native_frame(
+            frame,
+            frame.id.file_id,
+            frame.id.addr_or_line,
+            inline_frames,
+        )
     } else {
-        smallvec![symbolize_iterp_frame(frame)]
+        symbolize_iterp_frame(frame, inline_frames)
+    }
+}
+
+/// Frame used to attribute the samples of a trace frame to an instruction or
+/// source line.
+///
+/// Non-leaf native frames point at the return address, which can already
+/// belong to the next source line: the byte before it lies within the call
+/// instruction instead. Other frames are returned as-is.
+pub fn call_site_frame(mut frame: Frame, is_leaf: bool) -> Frame {
+    if !is_leaf && frame.kind == FrameKind::Regular(InterpKind::Native) {
+        frame.id.addr_or_line = frame.id.addr_or_line.saturating_sub(1);
     }
+    frame
 }
 
-fn symbolize_iterp_frame(raw: Frame) -> SymbolizedFrame {
+fn symbolize_iterp_frame(raw: Frame, inline_frames: bool) -> SmallVec<[SymbolizedFrame; 2]> {
     let Some(frame) = DB.stack_frames.get(raw.id.into()) else {
-        return SymbolizedFrame::unsymbolized(raw.into());
+        if raw.kind == FrameKind::Regular(InterpKind::Kernel) {
+            return symbolize_kernel_frame(raw, inline_frames);
+        }
+        return smallvec![SymbolizedFrame::unsymbolized(raw)];
     };
 
-    let frame = frame.get();
-    SymbolizedFrame {
-        raw,
-        func: frame.function_name.as_ref().map(|x| x.to_string()),
-        file: frame.file_name.as_ref().map(|x| x.to_string()),
-        line_no: if frame.line_number == 0 {
-            None
-        } else {
-            Some(frame.line_number as u32)
-        },
+    let lines = &frame.get().lines;
+    if lines.is_empty() {
+        return smallvec![SymbolizedFrame::unsymbolized(raw)];
     }
+
+    let depth = if inline_frames { lines.len() } else { 1 };
+    lines
+        .iter()
+        .take(depth)
+        .map(|line| SymbolizedFrame {
+            raw,
+            func: line.function_name.as_ref().map(|x| x.to_string()),
+            file: line.file_name.as_ref().map(|x| x.to_string()),
+            line_no: line.line_number.as_ref().map(|&x| x as u32),
+            column: line.column.as_ref().map(|&x| x as u32),
+        })
+        .collect()
 }
 
-fn symbolize_native_frame(raw: Frame, inline_frames: bool) -> SmallVec<[SymbolizedFrame; 2]> {
+/// Symbolize a kernel frame that the agent didn't send symbols for, using
+/// the kernel symbols imported for the image's release.
+fn symbolize_kernel_frame(raw: Frame, inline_frames: bool) -> SmallVec<[SymbolizedFrame; 2]> {
+    let Some(image) = DB.kernel_images.get(raw.id.file_id) else {
+        return smallvec![SymbolizedFrame::unsymbolized(raw)];
+    };
+    let image = image.read();
+
+    // Symbols are stored relative to the image start: absolute addresses
+    // depend on the KASLR offset of the boot and can't be resolved.
+    let release = image.release.as_deref();
+    let Some((symbols_id, symbols)) = DB.kernel_symbols.lookup(release, &image.module) else {
+        return smallvec![SymbolizedFrame::unsymbolized(raw)];
+    };
+    let Some(addr) = raw.id.addr_or_line.checked_add(symbols.text_start) else {
+        return smallvec![SymbolizedFrame::unsymbolized(raw)];
+    };
+
+    symbolize_native_frame(raw, symbols_id, addr, inline_frames)
+}
+
+/// Symbolize a native frame at `addr` using the symbols stored for
+/// `symbols_id`.
+fn symbolize_native_frame(
+    raw: Frame,
+    symbols_id: FileId,
+    addr: VirtAddr,
+    inline_frames: bool,
+) -> SmallVec<[SymbolizedFrame; 2]> {
     // No symbols for executable at all? Fast path.
-    let Some(tree) = DB.symbols.get(raw.id.file_id.into()).unwrap() else {
+    let Some(tree) = DB.symbols.get(symbols_id).unwrap() else {
         return smallvec![SymbolizedFrame::unsymbolized(raw)];
     };
 
     // Collect and sort symbols by depth, in ascending order.
-    let mut syms: SmallVec<[_; 2]> = tree.tree.query_point(raw.id.addr_or_line).collect();
+    let mut syms: SmallVec<[_; 2]> = tree.tree.query_point(addr).collect();
     syms.sort_unstable_by_key(|x| x.value.depth as i32);
     syms.dedup_by_key(|x| x.value.depth);
 
@@ -296,14 +403,15 @@ fn symbolize_native_frame(raw: Frame, inline_frames: bool) -> SmallVec<[Symboliz
         } else {
             // For the leaf record, resolve the line using the line table.
             let r = range.start..range.end;
-            (sym.file, sym.line_number_for_va(r, raw.id.addr_or_line))
+            (sym.file, sym.line_number_for_va(r, addr))
         };
 
         out.push(SymbolizedFrame {
             raw,
-            func: tree.str_by_ref(sym.func).map(Into::into),
+            func: tree.str_by_ref(sym.func).map(demangle),
             file: tree.str_by_ref(file).map(Into::into),
             line_no: line,
+            column: None,
         });
 
         if !inline_frames {
@@ -315,7 +423,7 @@ fn symbolize_native_frame(raw: Frame, inline_frames: bool) -> SmallVec<[Symboliz
 }
 
 /// Frame with corresponding symbol information.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SymbolizedFrame {
     /// Raw frame info.
     pub raw: Frame,
@@ -328,9 +436,19 @@ pub struct SymbolizedFrame {
 
     // Line numer, if known.
     pub line_no: Option<u32>,
+
+    /// Column, if known.
+    pub column: Option<u32>,
 }
 
 impl SymbolizedFrame {
+    /// Simplify the function name for display (see [`simplify_name`]).
+    pub fn simplify_name(&mut self) {
+        if let Some(ref mut func) = self.func {
+            *func = simplify_name(func);
+        }
+    }
+
     /// Create a fully unsymbolized frame.
     fn unsymbolized(raw: Frame) -> Self {
         SymbolizedFrame {
@@ -338,6 +456,7 @@ impl SymbolizedFrame {
             func: None,
             file: None,
             line_no: None,
+            column: None,
         }
     }
 }
@@ -371,6 +490,9 @@ impl fmt::Display for SymbolizedFrame {
             }
             if let Some(ref line) = self.line_no {
                 write!(f, ":{line}")?;
+                if let Some(ref column) = self.column {
+                    write!(f, ":{column}")?;
+                }
             }
         } else {
             write!(f, "+0x{:016x}", self.raw.id.addr_or_line)?;
@@ -379,3 +501,26 @@ impl fmt::Display for SymbolizedFrame {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_and_remove() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = SymDb::open_at(dir.path().to_owned()).unwrap();
+        let file_id = FileId::from_parts(0x1234, 0x5678);
+
+        let tree = SymTree {
+            strings: vec!["main".into()],
+            tree: rkyvtree::Tree::from_iter(Vec::<rkyvtree::Element<u64, SymRange>>::new()),
+        };
+        db.insert(file_id, tree).unwrap();
+        assert!(db.get(file_id).unwrap().is_some());
+
+        assert!(db.remove(file_id).unwrap());
+        assert!(db.get(file_id).unwrap().is_none());
+        assert!(!db.remove(file_id).unwrap());
+    }
+}
@@ -22,9 +22,25 @@ use crate::storage::*;
 #[archive_attr(derive(Clone, Copy, Debug))]
 pub enum SymbStatus {
     NotAttempted,
-    TempError { last_attempt: UtcTimestamp },
+    TempError {
+        last_attempt: UtcTimestamp,
+    },
     NotPresentGlobally,
-    Complete { num_symbols: u64 },
+    /// Excluded from automatic symbolization by the user.
+    Ignored,
+    Complete {
+        num_symbols: u64,
+    },
+}
+
+/// Virtual address range that an executable was mapped at.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+#[archive_attr(derive(Clone, Copy, Debug))]
+pub struct MappingRange {
+    pub memory_start: u64,
+    pub memory_limit: u64,
+    pub file_offset: u64,
 }
 
 /// Meta-data about an executable.
@@ -32,9 +48,33 @@ pub enum SymbStatus {
 #[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
 #[archive_attr(derive(Debug))]
 pub struct ExecutableMeta {
+    /// GNU build ID, if known.
     pub build_id: Option<String>,
     pub file_name: Option<String>,
     pub symb_status: SymbStatus,
+
+    /// Mapping the executable was most recently observed with.
+    pub mapping: Option<MappingRange>,
+
+    /// Time at which the executable was first observed in a profile.
+    pub first_seen: Option<UtcTimestamp>,
+
+    /// Time at which the executable was last observed in a profile.
+    ///
+    /// Updated with reduced granularity to keep DB writes in check.
+    pub last_seen: Option<UtcTimestamp>,
+
+    /// Number of consecutive failed symbolization attempts.
+    pub symb_attempts: u32,
+
+    /// Error message of the most recent failed symbolization attempt.
+    pub symb_error: Option<String>,
+
+    /// Path of a local copy of the executable, if known.
+    pub local_path: Option<String>,
+
+    /// Where the symbols were taken from: endpoint URL or local file.
+    pub symb_source: Option<String>,
 }
 
 new_table!(Executables: FileId => ExecutableMeta);
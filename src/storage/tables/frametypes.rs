@@ -0,0 +1,97 @@
+// Copyright Elasticsearch B.V. and/or licensed to Elasticsearch B.V. under one
+// or more contributor license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Names of frame types that devfiler doesn't natively know about.
+//!
+//! Newer profiling agents may introduce interpreters that don't have an
+//! [`InterpKind`] yet. Such frame types are assigned a code at runtime that
+//! is stored in [`FrameKind::Unknown`] and [`FrameKind::UnknownError`].
+
+use crate::storage::*;
+use std::sync::Mutex;
+
+/// Runtime-assigned code of a frame type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FrameTypeCode(pub u8);
+
+impl TableKey for FrameTypeCode {
+    type B = [u8; 1];
+
+    fn from_raw(data: Self::B) -> Self {
+        Self(data[0])
+    }
+
+    fn into_raw(self) -> Self::B {
+        [self.0]
+    }
+}
+
+impl_ord_from_table_key!(FrameTypeCode);
+
+new_table!(FrameTypes: FrameTypeCode => String {
+    const CACHE_SIZE: usize = 128;
+});
+
+impl FrameTypes {
+    /// First code handed out. Lower values are reserved for [`InterpKind`].
+    const FIRST_CODE: u8 = 0x20;
+
+    /// Last code handed out. Higher values have the error bit set.
+    const LAST_CODE: u8 = 0x7E;
+
+    /// Look up the code for the given frame type name, assigning a new one if
+    /// the name wasn't seen before.
+    ///
+    /// Returns `None` if all codes are taken.
+    pub fn code_for_name(&self, name: &str) -> Option<u8> {
+        static ALLOC_LOCK: Mutex<()> = Mutex::new(());
+        let _guard = ALLOC_LOCK.lock().unwrap();
+
+        let mut next = Self::FIRST_CODE;
+        for (code, existing) in self.iter() {
+            if existing.get().as_str() == name {
+                return Some(code.0);
+            }
+            next = next.max(code.0 + 1);
+        }
+
+        if next > Self::LAST_CODE {
+            return None;
+        }
+
+        self.insert(FrameTypeCode(next), name.to_owned());
+        Some(next)
+    }
+
+    /// Look up the name for a code returned from [`Self::code_for_name`].
+    pub fn name_for_code(&self, code: u8) -> Option<String> {
+        let value = self.get(FrameTypeCode(code & !FrameKind::ERR_MASK))?;
+        Some(value.get().to_string())
+    }
+}
+
+/// Human readable name of a frame kind, excluding whether it is an error.
+pub fn frame_kind_name(kind: FrameKind) -> String {
+    match kind {
+        FrameKind::Regular(interp) | FrameKind::Error(interp) => interp.to_string(),
+        FrameKind::Abort => "Abort".into(),
+        FrameKind::Unknown(code) | FrameKind::UnknownError(code) => DB
+            .frame_types
+            .name_for_code(code)
+            .unwrap_or_else(|| format!("Unknown ({code:#x})")),
+    }
+}
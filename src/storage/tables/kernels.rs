@@ -0,0 +1,156 @@
+// Copyright Elasticsearch B.V. and/or licensed to Elasticsearch B.V. under one
+// or more contributor license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Kernel images observed in profiles and symbols imported for them.
+//!
+//! Kernel symbols are imported per kernel release and module. They are
+//! stored in the regular symbol database under a synthetic file ID derived
+//! from both (see [`kernel_symbols_id`]).
+//!
+//! Kernel addresses are randomized on every boot (KASLR), so kernel frames
+//! are stored relative to the start of their image: `_stext` for the core
+//! kernel and the module's load address for modules.
+
+use crate::storage::*;
+use std::collections::BTreeSet;
+use xxhash_rust::xxh3;
+
+/// Module name used for the core kernel image.
+pub const VMLINUX: &str = "vmlinux";
+
+/// Kernel image (core kernel or module) that kernel frames were observed in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+#[archive_attr(derive(Debug))]
+pub struct KernelImage {
+    /// Module name, [`VMLINUX`] for the core kernel.
+    pub module: String,
+
+    /// Kernel release of the host that reported the image, if known.
+    pub release: Option<String>,
+}
+
+new_table!(KernelImages: FileId => KernelImage {
+    const CACHE_SIZE: usize = 1024;
+});
+
+/// Meta-data about imported symbols for a kernel image.
+#[derive(Debug, Clone)]
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+#[archive_attr(derive(Debug))]
+pub struct KernelSymbols {
+    pub release: String,
+    pub module: String,
+
+    /// File that the symbols were imported from.
+    pub source: String,
+    pub num_symbols: u64,
+
+    /// Address of the image start in the symbol tree.
+    ///
+    /// The link-time address of `_stext` for `vmlinux` images, 0 for symbols
+    /// that are stored relative to the image already.
+    pub text_start: u64,
+}
+
+new_table!(KernelSymbolSets: FileId => KernelSymbols {
+    const CACHE_SIZE: usize = 1024;
+});
+
+impl KernelSymbolSets {
+    /// Kernel releases that symbols were imported for.
+    pub fn releases(&self) -> BTreeSet<String> {
+        self.iter().map(|(_, x)| x.read().release).collect()
+    }
+
+    /// Find the symbols to use for the given kernel image.
+    ///
+    /// If the release is unknown, the symbols of the only imported release are
+    /// used, if unambiguous. A known release never falls back to the symbols
+    /// of another release.
+    pub fn lookup(&self, release: Option<&str>, module: &str) -> Option<(FileId, KernelSymbols)> {
+        let release = match release {
+            Some(release) => release.to_owned(),
+            None => {
+                let releases = self.releases();
+                if releases.len() != 1 {
+                    return None;
+                }
+                releases.into_iter().next()?
+            }
+        };
+
+        let id = kernel_symbols_id(&release, module);
+        self.get(id).map(|x| (id, x.read()))
+    }
+}
+
+/// Synthetic file ID that symbols for a kernel image are stored under.
+pub fn kernel_symbols_id(release: &str, module: &str) -> FileId {
+    let hash = xxh3::xxh3_64(format!("{release}\0{module}").as_bytes());
+    FileId::from_parts(3, hash)
+}
+
+/// Normalize the file name of a kernel image to its module name.
+///
+/// Strips directories and `.ko` suffixes (including compression) and maps the
+/// various names of the core kernel to [`VMLINUX`].
+pub fn kernel_module_name(file_name: &str) -> String {
+    let name = file_name.rsplit('/').next().unwrap_or_default();
+    let name = name.trim_start_matches('[').trim_end_matches(']');
+
+    if name.is_empty() || name == "kernel.kallsyms" || name.starts_with(VMLINUX) {
+        return VMLINUX.to_owned();
+    }
+
+    let name = [".xz", ".zst", ".gz"]
+        .into_iter()
+        .find_map(|ext| name.strip_suffix(ext))
+        .unwrap_or(name);
+    let name = name.strip_suffix(".ko").unwrap_or(name);
+
+    // Module names are normalized to underscores by the kernel.
+    name.replace('-', "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn module_names() {
+        assert_eq!(kernel_module_name(""), VMLINUX);
+        assert_eq!(kernel_module_name("vmlinux"), VMLINUX);
+        assert_eq!(kernel_module_name("vmlinux-6.8.0-45-generic"), VMLINUX);
+        assert_eq!(kernel_module_name("[kernel.kallsyms]"), VMLINUX);
+        assert_eq!(kernel_module_name("nf_tables"), "nf_tables");
+        assert_eq!(kernel_module_name("[nf_tables]"), "nf_tables");
+        assert_eq!(
+            kernel_module_name("/lib/modules/6.8.0/kernel/net/nf-tables.ko.zst"),
+            "nf_tables"
+        );
+        assert_eq!(kernel_module_name("ext4.ko"), "ext4");
+    }
+
+    #[test]
+    fn symbols_ids() {
+        let a = kernel_symbols_id("6.8.0", VMLINUX);
+        assert_eq!(a, kernel_symbols_id("6.8.0", VMLINUX));
+        assert_ne!(a, kernel_symbols_id("6.8.1", VMLINUX));
+        assert_ne!(a, kernel_symbols_id("6.8.0", "ext4"));
+    }
+}
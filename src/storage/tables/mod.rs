@@ -28,11 +28,15 @@
 //! could come up for devfiler.
 
 mod executables;
+mod frametypes;
+mod kernels;
 mod stackframes;
 mod stacktraces;
 mod traceevents;
 
 pub use executables::*;
+pub use frametypes::*;
+pub use kernels::*;
 pub use stackframes::*;
 pub use stacktraces::*;
 pub use traceevents::*;
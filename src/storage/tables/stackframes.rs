@@ -56,15 +56,24 @@ impl From<ArchivedFrameId> for FrameId {
 
 impl_ord_from_table_key!(FrameId);
 
-/// Symbol information for a frame.
+/// Source location of a single (possibly inlined) function in a frame.
 #[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
 #[archive_attr(derive(Debug, PartialEq, Eq, Hash))]
-pub struct FrameMetaData {
+pub struct SourceLine {
     pub file_name: Option<String>,
     pub function_name: Option<String>,
-    pub line_number: u64,     // TODO: option
-    pub function_offset: u32, // TODO: option
+    pub line_number: Option<u64>,
+    pub column: Option<u64>,
+}
+
+/// Symbol information for a frame.
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+#[archive_attr(derive(Debug, PartialEq, Eq, Hash))]
+pub struct FrameMetaData {
+    /// Inline chain, ordered from the outermost caller to the innermost callee.
+    pub lines: Vec<SourceLine>,
 }
 
 new_table!(StackFrames: FrameId => FrameMetaData {
@@ -100,6 +100,25 @@ impl InterpKind {
             _ => return None,
         })
     }
+
+    /// Parse an OTLP `profile.frame.type` name (without `-error` suffix).
+    pub fn from_frame_type(name: &str) -> Option<Self> {
+        Some(match name {
+            "native" => InterpKind::Native,
+            "kernel" => InterpKind::Kernel,
+            "jvm" => InterpKind::Jvm,
+            "perl" => InterpKind::Perl,
+            "cpython" => InterpKind::Python,
+            "php" => InterpKind::Php,
+            "phpjit" => InterpKind::PhpJit,
+            "ruby" => InterpKind::Ruby,
+            "dotnet" => InterpKind::DotNet,
+            "v8js" => InterpKind::Js,
+            "beam" => InterpKind::Beam,
+            "go" => InterpKind::Go,
+            _ => return None,
+        })
+    }
 }
 
 /// Type of a frame (e.g. native, Python, etc).
@@ -116,7 +135,7 @@ pub enum FrameKind {
 }
 
 impl FrameKind {
-    const ERR_MASK: u8 = 0b1000_0000;
+    pub const ERR_MASK: u8 = 0b1000_0000;
 
     pub const fn from_raw(raw: u8) -> Self {
         if raw == 0xFF {
@@ -133,6 +152,20 @@ impl FrameKind {
         }
     }
 
+    /// Parse an OTLP `profile.frame.type` name.
+    ///
+    /// Returns `None` for frame types unknown to devfiler.
+    pub fn from_frame_type(name: &str) -> Option<Self> {
+        if name == "abort-marker" {
+            return Some(FrameKind::Abort);
+        }
+
+        Some(match name.strip_suffix("-error") {
+            Some(base) => FrameKind::Error(InterpKind::from_frame_type(base)?),
+            None => FrameKind::Regular(InterpKind::from_frame_type(name)?),
+        })
+    }
+
     pub const fn interp(self) -> Option<InterpKind> {
         match self {
             FrameKind::Regular(x) => Some(x),
@@ -193,4 +226,16 @@ mod tests {
         assert_eq!(FrameKind::from_raw(0x0A), Regular(DotNet));
         assert_eq!(FrameKind::from_raw(0), Unknown(0));
     }
+
+    #[test]
+    fn kind_from_frame_type() {
+        use FrameKind::*;
+        use InterpKind::*;
+
+        assert_eq!(FrameKind::from_frame_type("abort-marker"), Some(Abort));
+        assert_eq!(FrameKind::from_frame_type("cpython"), Some(Regular(Python)));
+        assert_eq!(FrameKind::from_frame_type("v8js-error"), Some(Error(Js)));
+        assert_eq!(FrameKind::from_frame_type("cobol"), None);
+        assert_eq!(FrameKind::from_frame_type("cobol-error"), None);
+    }
 }
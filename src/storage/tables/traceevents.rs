@@ -95,14 +95,65 @@ pub struct TraceCount {
     pub trace_hash: TraceHash,
     pub count: u32,
     pub comm: String,
-    pub pod_name: Option<String>,
-    pub container_name: Option<String>,
+
+    /// Process and container labels, interned in [`TraceLabelSets`].
+    pub labels: Option<TraceLabelsId>,
 }
 
 new_table!(TraceEvents: TraceCountId => TraceCount {
     const STORAGE_OPT: StorageOpt = StorageOpt::SeqRead;
 });
 
+/// Labels of the process that trace events were recorded in.
+///
+/// Shared by all events of a process, so they are stored once in
+/// [`TraceLabelSets`] rather than in every event.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+#[archive_attr(derive(Debug))]
+pub struct TraceLabels {
+    /// Name of the process's executable, if reported.
+    pub process: Option<String>,
+    pub container_name: Option<String>,
+    pub pod_name: Option<String>,
+}
+
+impl TraceLabels {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// ID that the labels are interned under.
+    pub fn id(&self) -> TraceLabelsId {
+        let mut hasher = xxhash_rust::xxh3::Xxh3::new();
+        std::hash::Hash::hash(self, &mut hasher);
+        TraceLabelsId(hasher.digest())
+    }
+}
+
+/// Hash identifying a set of [`TraceLabels`].
+#[derive(Debug, PartialEq, Eq, Default, Hash, Copy, Clone)]
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+#[repr(transparent)]
+#[archive(as = "TraceLabelsId")]
+pub struct TraceLabelsId(pub u64);
+
+impl TableKey for TraceLabelsId {
+    type B = [u8; 8];
+
+    fn from_raw(data: Self::B) -> Self {
+        Self(u64::from_le_bytes(data))
+    }
+
+    fn into_raw(self) -> Self::B {
+        self.0.to_le_bytes()
+    }
+}
+
+new_table!(TraceLabelSets: TraceLabelsId => TraceLabels {
+    const CACHE_SIZE: usize = 4096;
+});
+
 impl TraceEvents {
     /// Iterate over events in the given time range.
     ///
@@ -0,0 +1,312 @@
+// Copyright Elasticsearch B.V. and/or licensed to Elasticsearch B.V. under one
+// or more contributor license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Locating separate debug files for stripped executables.
+//!
+//! Follows the same conventions as GDB: debug files are looked up by GNU
+//! build ID in `/usr/lib/debug/.build-id` and by the name stored in the
+//! `.gnu_debuglink` section next to the executable.
+
+use super::elf::ElfInfo;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Global directory holding separate debug files.
+const DEBUG_ROOT: &str = "/usr/lib/debug";
+
+/// Set of files to extract the symbols for one executable from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolFiles {
+    /// Executable the symbols are for: determines the file ID.
+    pub executable: PathBuf,
+
+    /// Separate debug file with DWARF and symbol table.
+    pub debug: Option<PathBuf>,
+
+    /// DWARF supplementary file referenced by the debug info.
+    pub sup: Option<PathBuf>,
+}
+
+impl SymbolFiles {
+    /// Extract symbols from the executable alone.
+    pub fn single(executable: PathBuf) -> Self {
+        SymbolFiles {
+            executable,
+            debug: None,
+            sup: None,
+        }
+    }
+
+    /// Use the given executable, discovering its separate debug file and
+    /// supplementary file on the local system.
+    pub fn discover(executable: PathBuf) -> Self {
+        let mut files = Self::single(executable);
+        files.resolve();
+        files
+    }
+
+    /// Fill in missing debug and supplementary files from the local system.
+    pub fn resolve(&mut self) {
+        let Ok(info) = ElfInfo::from_path(&self.executable) else {
+            return;
+        };
+
+        if self.debug.is_none() && !info.has_dwarf {
+            self.debug = find_debug_file(&self.executable, &info);
+        }
+
+        if self.sup.is_none() {
+            let dwarf_file = self.dwarf_file();
+            let dwarf_info = match self.debug {
+                Some(ref debug) => ElfInfo::from_path(debug).ok(),
+                None => Some(info),
+            };
+            if let Some(dwarf_info) = dwarf_info {
+                self.sup = find_sup_file(dwarf_file, &dwarf_info);
+            }
+        }
+    }
+
+    /// File that DWARF info is read from.
+    pub fn dwarf_file(&self) -> &Path {
+        self.debug.as_deref().unwrap_or(&self.executable)
+    }
+}
+
+/// Group the given files into executables and their separate debug files.
+///
+/// Debug files are paired with an executable from the same set if the build
+/// IDs match or the executable's `.gnu_debuglink` names the debug file. For
+/// executables without a partner, the debug file is searched on the system.
+pub fn pair_files(paths: Vec<PathBuf>) -> Vec<SymbolFiles> {
+    let infos: Vec<_> = paths
+        .into_iter()
+        .map(|path| {
+            let info = ElfInfo::from_path(&path).unwrap_or_default();
+            (path, info)
+        })
+        .collect();
+
+    let (debug_files, executables): (Vec<_>, Vec<_>) = infos
+        .into_iter()
+        .partition(|(_, info)| !info.has_code && info.has_dwarf);
+
+    let mut paired = vec![false; debug_files.len()];
+    let mut result = Vec::with_capacity(executables.len());
+
+    for (exe, exe_info) in executables {
+        let partner = debug_files.iter().position(|(debug, debug_info)| {
+            let same_build_id =
+                exe_info.build_id.is_some() && exe_info.build_id == debug_info.build_id;
+            let linked = exe_info.debuglink.as_ref().is_some_and(|(name, _)| {
+                debug
+                    .file_name()
+                    .is_some_and(|x| x.to_string_lossy() == *name)
+            });
+            same_build_id || (linked && build_ids_compatible(&exe_info, debug_info))
+        });
+
+        let mut files = SymbolFiles::single(exe);
+        if let Some(idx) = partner {
+            paired[idx] = true;
+            files.debug = Some(debug_files[idx].0.clone());
+        }
+        files.resolve();
+        result.push(files);
+    }
+
+    // Debug files without an executable are ingested under their own file ID.
+    for ((debug, _), paired) in debug_files.into_iter().zip(paired) {
+        if !paired {
+            result.push(SymbolFiles::discover(debug));
+        }
+    }
+
+    result
+}
+
+/// Locate the separate debug file for the given executable.
+pub fn find_debug_file(exe: &Path, info: &ElfInfo) -> Option<PathBuf> {
+    let debug_root = Path::new(DEBUG_ROOT);
+    let mut candidates = Vec::new();
+
+    if let Some(ref build_id) = info.build_id {
+        if let Some(path) = build_id_path(debug_root, build_id) {
+            candidates.push(path);
+        }
+    }
+
+    if let Some((ref name, _)) = info.debuglink {
+        let dir = exe.parent().unwrap_or(Path::new("."));
+        candidates.push(dir.join(name));
+        candidates.push(dir.join(".debug").join(name));
+        if let Ok(abs_dir) = dir.canonicalize() {
+            let rel_dir = abs_dir.strip_prefix("/").unwrap_or(&abs_dir);
+            candidates.push(debug_root.join(rel_dir).join(name));
+        }
+    }
+
+    let exe_canon = exe.canonicalize().ok();
+    candidates.into_iter().find(|candidate| {
+        if !candidate.is_file() || candidate.canonicalize().ok() == exe_canon {
+            return false;
+        }
+        let Ok(candidate_info) = ElfInfo::from_path(candidate) else {
+            return false;
+        };
+        candidate_info.has_dwarf && debug_file_matches(info, candidate, &candidate_info)
+    })
+}
+
+/// Locate the DWARF supplementary file referenced by the given debug file.
+pub fn find_sup_file(debug: &Path, info: &ElfInfo) -> Option<PathBuf> {
+    let (ref name, ref build_id) = *info.debugaltlink.as_ref()?;
+
+    let mut candidates = Vec::new();
+    let name = Path::new(name);
+    if name.is_absolute() {
+        candidates.push(name.to_owned());
+    } else {
+        let dir = debug.parent().unwrap_or(Path::new("."));
+        candidates.push(dir.join(name));
+    }
+    if let Some(path) = build_id_path(Path::new(DEBUG_ROOT), build_id) {
+        candidates.push(path);
+    }
+
+    candidates.into_iter().find(|candidate| {
+        ElfInfo::from_path(candidate).is_ok_and(|x| x.build_id.as_ref() == Some(build_id))
+    })
+}
+
+/// Path of a debug file in the `.build-id` directory of the given root.
+fn build_id_path(root: &Path, build_id: &str) -> Option<PathBuf> {
+    if build_id.len() < 3 || !build_id.is_ascii() {
+        return None;
+    }
+    let (dir, rest) = build_id.split_at(2);
+    Some(
+        root.join(".build-id")
+            .join(dir)
+            .join(format!("{rest}.debug")),
+    )
+}
+
+/// Check whether the candidate debug file belongs to the executable.
+fn debug_file_matches(exe: &ElfInfo, candidate: &Path, candidate_info: &ElfInfo) -> bool {
+    if exe.build_id.is_some() && candidate_info.build_id.is_some() {
+        return exe.build_id == candidate_info.build_id;
+    }
+
+    // No build IDs to compare: fall back to the debug link checksum.
+    match exe.debuglink {
+        Some((_, crc)) => file_crc32(candidate).is_some_and(|x| x == crc),
+        None => false,
+    }
+}
+
+fn build_ids_compatible(a: &ElfInfo, b: &ElfInfo) -> bool {
+    match (&a.build_id, &b.build_id) {
+        (Some(a), Some(b)) => a == b,
+        _ => true,
+    }
+}
+
+/// CRC32 as used by `.gnu_debuglink` (IEEE polynomial, reflected).
+fn file_crc32(path: &Path) -> Option<u32> {
+    const TABLE: [u32; 256] = crc32_table();
+
+    let mut file = std::fs::File::open(path).ok()?;
+    let mut buf = vec![0; 64 * 1024];
+    let mut crc = !0u32;
+
+    loop {
+        let n = file.read(&mut buf).ok()?;
+        if n == 0 {
+            break;
+        }
+        for &byte in &buf[..n] {
+            crc = TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8);
+        }
+    }
+
+    Some(!crc)
+}
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                0xEDB88320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"123456789").unwrap();
+        assert_eq!(file_crc32(file.path()), Some(0xCBF43926));
+    }
+
+    #[test]
+    fn build_id_paths() {
+        assert_eq!(
+            build_id_path(Path::new("/usr/lib/debug"), "abcdef"),
+            Some(PathBuf::from("/usr/lib/debug/.build-id/ab/cdef.debug"))
+        );
+        assert_eq!(build_id_path(Path::new("/"), "ab"), None);
+    }
+
+    #[test]
+    fn debuglink_crc_fallback() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"123456789").unwrap();
+
+        let mut exe = ElfInfo {
+            debuglink: Some(("x.debug".into(), 0xCBF43926)),
+            ..ElfInfo::default()
+        };
+        assert!(debug_file_matches(&exe, file.path(), &ElfInfo::default()));
+
+        exe.debuglink = Some(("x.debug".into(), 0x1234));
+        assert!(!debug_file_matches(&exe, file.path(), &ElfInfo::default()));
+
+        // Build IDs take precedence over the checksum.
+        exe.build_id = Some("aa".into());
+        let candidate = ElfInfo {
+            build_id: Some("aa".into()),
+            ..ElfInfo::default()
+        };
+        assert!(debug_file_matches(&exe, file.path(), &candidate));
+    }
+}
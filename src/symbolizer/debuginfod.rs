@@ -0,0 +1,198 @@
+// Copyright Elasticsearch B.V. and/or licensed to Elasticsearch B.V. under one
+// or more contributor license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Client for [debuginfod] servers.
+//!
+//! [debuginfod]: https://sourceware.org/elfutils/Debuginfod.html
+
+use super::{DownloadProgress, CLIENT};
+use anyhow::{ensure, Context, Result};
+use std::io::Write;
+use std::sync::atomic::Ordering::Relaxed;
+use tempfile::NamedTempFile;
+
+/// Environment variable holding a space separated list of server URLs.
+const URLS_ENV_VAR: &str = "DEBUGINFOD_URLS";
+
+/// Set of debuginfod servers to query for debug info.
+#[derive(Debug, Clone, Default)]
+pub struct Debuginfod {
+    urls: Vec<String>,
+}
+
+impl Debuginfod {
+    pub fn new(urls: impl IntoIterator<Item = String>) -> Self {
+        Debuginfod {
+            urls: urls
+                .into_iter()
+                .map(|url| url.trim_end_matches('/').to_owned())
+                .filter(|url| !url.is_empty())
+                .collect(),
+        }
+    }
+
+    /// Use the given URLs, falling back to the ones in `DEBUGINFOD_URLS`
+    /// when none were passed explicitly.
+    pub fn from_args_or_env(urls: impl IntoIterator<Item = String>) -> Self {
+        let this = Self::new(urls);
+        if !this.is_empty() {
+            return this;
+        }
+
+        let env = std::env::var(URLS_ENV_VAR).unwrap_or_default();
+        Self::new(env.split_whitespace().map(str::to_owned))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.urls.is_empty()
+    }
+
+    pub fn urls(&self) -> &[String] {
+        &self.urls
+    }
+
+    /// Download the debug ELF file for the given GNU build ID.
+    ///
+    /// Servers are queried in order and the first one that has the file wins.
+    /// Returns `None` if none of the servers knows the build ID.
+    pub async fn fetch_debuginfo(&self, build_id: &str) -> Result<Option<NamedTempFile>> {
+        let result = self
+            .fetch_debuginfo_with_progress(build_id, &DownloadProgress::default())
+            .await?;
+        Ok(result.map(|(file, _)| file))
+    }
+
+    /// Like [`Self::fetch_debuginfo`], reporting the download progress.
+    ///
+    /// Also returns the URL of the server that the file was downloaded from.
+    pub async fn fetch_debuginfo_with_progress(
+        &self,
+        build_id: &str,
+        progress: &DownloadProgress,
+    ) -> Result<Option<(NamedTempFile, String)>> {
+        ensure!(
+            !build_id.is_empty() && build_id.bytes().all(|x| x.is_ascii_hexdigit()),
+            "invalid build ID: {build_id:?}"
+        );
+
+        let build_id = build_id.to_ascii_lowercase();
+        let mut last_error = None;
+
+        for url in &self.urls {
+            match fetch_from_server(url, &build_id, progress).await {
+                Ok(Some(file)) => return Ok(Some((file, url.clone()))),
+                Ok(None) => continue,
+                Err(e) => {
+                    tracing::warn!("debuginfod request to {url} failed: {e:?}");
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        // Only report "not present" if all servers agreed on that: otherwise
+        // we want to retry later.
+        match last_error {
+            Some(e) => Err(e),
+            None => Ok(None),
+        }
+    }
+}
+
+async fn fetch_from_server(
+    url: &str,
+    build_id: &str,
+    progress: &DownloadProgress,
+) -> Result<Option<NamedTempFile>> {
+    let mut resp = CLIENT
+        .get(format!("{url}/buildid/{build_id}/debuginfo"))
+        .send()
+        .await
+        .context("debuginfo HTTP request failed")?;
+
+    if resp.status() == 404 {
+        return Ok(None);
+    }
+
+    resp = resp
+        .error_for_status()
+        .context("debuginfo HTTP request returned non-success status")?;
+
+    progress
+        .total
+        .store(resp.content_length().unwrap_or(0), Relaxed);
+    progress.bytes.store(0, Relaxed);
+
+    let mut file = NamedTempFile::new().context("failed to create temporary file")?;
+    while let Some(chunk) = resp.chunk().await.context("debuginfo body read failed")? {
+        file.write_all(&chunk)?;
+        progress.bytes.fetch_add(chunk.len() as u64, Relaxed);
+    }
+    file.flush()?;
+
+    Ok(Some(file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::symbolizer::testserver::{self, Response};
+
+    /// Minimal debuginfod stand-in serving a single file for a single build ID.
+    fn spawn_stand_in(build_id: &'static str, content: &'static [u8]) -> String {
+        let addr = testserver::spawn(move |req| {
+            if req.path == format!("/buildid/{build_id}/debuginfo") {
+                Response::new("200 OK", content)
+            } else {
+                Response::new("404 Not Found", "")
+            }
+        });
+
+        format!("http://{addr}/")
+    }
+
+    #[tokio::test]
+    async fn fetch_debuginfo() {
+        let url = spawn_stand_in("abcdef0123", b"\x7fELF debug");
+        let client = Debuginfod::new([url]);
+
+        let file = client.fetch_debuginfo("ABCDEF0123").await.unwrap().unwrap();
+        assert_eq!(std::fs::read(file.path()).unwrap(), b"\x7fELF debug");
+
+        assert!(client.fetch_debuginfo("0123").await.unwrap().is_none());
+        assert!(client.fetch_debuginfo("../etc").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn falls_through_servers() {
+        let first = spawn_stand_in("1111", b"first");
+        let second = spawn_stand_in("2222", b"second");
+        let client = Debuginfod::new([first, second]);
+
+        let file = client.fetch_debuginfo("2222").await.unwrap().unwrap();
+        assert_eq!(std::fs::read(file.path()).unwrap(), b"second");
+    }
+
+    #[tokio::test]
+    async fn unreachable_server_is_error() {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let client = Debuginfod::new([format!("http://{addr}")]);
+        assert!(client.fetch_debuginfo("1111").await.is_err());
+    }
+}
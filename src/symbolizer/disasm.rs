@@ -0,0 +1,154 @@
+// Copyright Elasticsearch B.V. and/or licensed to Elasticsearch B.V. under one
+// or more contributor license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Disassembly of native functions from local executables.
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use capstone::prelude::*;
+use object::{Architecture, Object, ObjectSection};
+use std::fs::File;
+use std::ops::Range;
+use std::path::Path;
+
+/// Maximum size of a function to disassemble.
+const MAX_FUNC_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Single disassembled machine instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    /// Virtual address of the instruction.
+    pub addr: u64,
+
+    /// Length of the instruction in bytes.
+    pub len: usize,
+
+    /// Mnemonic and operands in assembler syntax.
+    pub text: String,
+}
+
+impl Instruction {
+    /// Virtual address range occupied by the instruction.
+    pub fn range(&self) -> Range<u64> {
+        self.addr..self.addr + self.len as u64
+    }
+}
+
+/// Disassemble the code in the given virtual address range of an executable.
+pub fn disassemble(path: &Path, range: Range<u64>) -> Result<Vec<Instruction>> {
+    ensure!(
+        range.end > range.start && range.end - range.start <= MAX_FUNC_SIZE,
+        "invalid function range {:#x}..{:#x}",
+        range.start,
+        range.end,
+    );
+
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    // SAFETY: the file might change while mapped. `object` validates all
+    //         offsets, so this can at worst result in garbage output.
+    let data = unsafe { memmap2::Mmap::map(&file)? };
+    let obj = object::File::parse(&*data).context("failed to parse executable")?;
+
+    let code = obj
+        .sections()
+        .find_map(|x| x.data_range(range.start, range.end - range.start).ok()?)
+        .ok_or_else(|| anyhow!("no code at {:#x} in {}", range.start, path.display()))?;
+
+    let cs = match obj.architecture() {
+        Architecture::X86_64 => Capstone::new()
+            .x86()
+            .mode(arch::x86::ArchMode::Mode64)
+            .build(),
+        Architecture::Aarch64 => Capstone::new()
+            .arm64()
+            .mode(arch::arm64::ArchMode::Arm)
+            .build(),
+        other => bail!("disassembly not supported for {other:?}"),
+    }
+    .map_err(|e| anyhow!("failed to create disassembler: {e}"))?;
+
+    let insns = cs
+        .disasm_all(code, range.start)
+        .map_err(|e| anyhow!("failed to disassemble: {e}"))?;
+
+    Ok(insns
+        .iter()
+        .map(|insn| Instruction {
+            addr: insn.address(),
+            len: insn.len(),
+            text: format!(
+                "{} {}",
+                insn.mnemonic().unwrap_or("???"),
+                insn.op_str().unwrap_or_default()
+            )
+            .trim_end()
+            .to_owned(),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use object::ObjectSymbol;
+
+    #[no_mangle]
+    #[inline(never)]
+    extern "C" fn devfiler_disasm_test_func(x: u64) -> u64 {
+        x.wrapping_mul(31).rotate_left(7)
+    }
+
+    #[test]
+    #[cfg(all(
+        target_os = "linux",
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    ))]
+    fn own_function() {
+        std::hint::black_box(devfiler_disasm_test_func as usize);
+
+        let exe = std::env::current_exe().unwrap();
+        let data = std::fs::read(&exe).unwrap();
+        let obj = object::File::parse(&*data).unwrap();
+        let sym = obj
+            .symbols()
+            .find(|x| x.name() == Ok("devfiler_disasm_test_func"))
+            .unwrap();
+        let range = sym.address()..sym.address() + sym.size();
+
+        let insns = disassemble(&exe, range.clone()).unwrap();
+        assert!(!insns.is_empty());
+        assert_eq!(insns.first().unwrap().addr, range.start);
+        assert_eq!(insns.last().unwrap().range().end, range.end);
+        for pair in insns.windows(2) {
+            assert_eq!(pair[0].range().end, pair[1].addr);
+        }
+        assert!(insns.iter().any(|x| x.text.starts_with("ret")));
+    }
+
+    #[test]
+    fn invalid_range() {
+        let exe = std::env::current_exe().unwrap();
+        assert!(disassemble(&exe, 0x1000..0x1000).is_err());
+        assert!(disassemble(&exe, 0..MAX_FUNC_SIZE + 1).is_err());
+    }
+
+    #[test]
+    fn not_an_executable() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"#!/bin/sh\n").unwrap();
+        assert!(disassemble(file.path(), 0x1000..0x1010).is_err());
+    }
+}
@@ -0,0 +1,357 @@
+// Copyright Elasticsearch B.V. and/or licensed to Elasticsearch B.V. under one
+// or more contributor license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Streaming symbol downloads with progress reporting.
+//!
+//! Response bodies are handed to a blocking consumer through a bounded
+//! channel, so the compressed download is never buffered as a whole. Note
+//! that the decoded symbols still are: the symbol tree is only built once
+//! all ranges have been read (see [`super::insert_symbols`]). Interrupted
+//! downloads are resumed with HTTP range requests where the server allows
+//! and the first response carries a validator (`ETag` or `Last-Modified`)
+//! that guarantees the remainder belongs to the same file.
+
+use crate::storage::FileId;
+use anyhow::{bail, Context, Result};
+use lazy_static::lazy_static;
+use reqwest::header::{self, HeaderValue};
+use reqwest::{Client, Response, StatusCode};
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::ops::Deref;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+/// Number of body chunks buffered between the download and its consumer.
+const CHANNEL_CAP: usize = 64;
+
+/// Maximum number of times an interrupted download is resumed.
+const MAX_RESUMES: u32 = 5;
+
+lazy_static! {
+    /// Progress of the downloads that are currently running.
+    static ref ACTIVE: Mutex<HashMap<FileId, Arc<DownloadProgress>>> = Default::default();
+}
+
+/// Progress of the symbol download for an executable.
+#[derive(Debug, Default)]
+pub struct DownloadProgress {
+    /// Number of bytes received.
+    pub bytes: AtomicU64,
+
+    /// Size of the download in bytes, 0 if unknown.
+    pub total: AtomicU64,
+
+    /// Number of symbols ingested from the download.
+    pub symbols: AtomicU64,
+}
+
+impl DownloadProgress {
+    /// Fraction of the download completed, if the size is known.
+    pub fn fraction(&self) -> Option<f32> {
+        let total = self.total.load(Relaxed);
+        (total > 0).then(|| self.bytes.load(Relaxed) as f32 / total as f32)
+    }
+}
+
+/// Progress of the running download for the given executable, if any.
+pub fn download_progress(file_id: FileId) -> Option<Arc<DownloadProgress>> {
+    ACTIVE.lock().unwrap().get(&file_id).cloned()
+}
+
+/// Registers download progress for an executable while alive.
+pub struct ProgressGuard {
+    file_id: FileId,
+    progress: Arc<DownloadProgress>,
+}
+
+impl ProgressGuard {
+    pub fn new(file_id: FileId) -> Self {
+        let progress = Arc::new(DownloadProgress::default());
+        ACTIVE
+            .lock()
+            .unwrap()
+            .insert(file_id, Arc::clone(&progress));
+        ProgressGuard { file_id, progress }
+    }
+
+    pub fn progress(&self) -> Arc<DownloadProgress> {
+        Arc::clone(&self.progress)
+    }
+}
+
+impl Deref for ProgressGuard {
+    type Target = DownloadProgress;
+
+    fn deref(&self) -> &DownloadProgress {
+        &self.progress
+    }
+}
+
+impl Drop for ProgressGuard {
+    fn drop(&mut self) {
+        let mut active = ACTIVE.lock().unwrap();
+        if active
+            .get(&self.file_id)
+            .is_some_and(|x| Arc::ptr_eq(x, &self.progress))
+        {
+            active.remove(&self.file_id);
+        }
+    }
+}
+
+/// Start downloading the given URL in the background.
+///
+/// Returns `None` if the server responds with 404. The returned reader
+/// blocks while waiting for data and must thus be consumed from a blocking
+/// task.
+pub async fn stream(
+    client: &Client,
+    url: String,
+    progress: Arc<DownloadProgress>,
+) -> Result<Option<BodyReader>> {
+    let resp = client
+        .get(&url)
+        .send()
+        .await
+        .context("HTTP request failed")?;
+
+    if resp.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+
+    let resp = resp
+        .error_for_status()
+        .context("HTTP request returned non-success status")?;
+
+    progress
+        .total
+        .store(resp.content_length().unwrap_or(0), Relaxed);
+    progress.bytes.store(0, Relaxed);
+
+    let (tx, rx) = mpsc::channel(CHANNEL_CAP);
+    let client = client.clone();
+    tokio::spawn(async move {
+        if let Err(e) = feed(&client, url, resp, &tx, &progress).await {
+            let _ = tx.send(Err(e)).await;
+        }
+    });
+
+    Ok(Some(BodyReader {
+        rx,
+        chunk: Vec::new(),
+        pos: 0,
+    }))
+}
+
+/// Forward the response body to the channel, resuming on interruptions.
+async fn feed(
+    client: &Client,
+    url: String,
+    mut resp: Response,
+    tx: &mpsc::Sender<Result<Vec<u8>>>,
+    progress: &DownloadProgress,
+) -> Result<()> {
+    let total = resp.content_length();
+    let accepts_ranges = resp
+        .headers()
+        .get(header::ACCEPT_RANGES)
+        .is_some_and(|x| x.as_bytes() == b"bytes");
+    let validator = accepts_ranges.then(|| range_validator(&resp)).flatten();
+    let mut received = 0;
+    let mut resumes = 0;
+
+    loop {
+        let error = match resp.chunk().await {
+            Ok(Some(chunk)) => {
+                received += chunk.len() as u64;
+                progress.bytes.store(received, Relaxed);
+                if tx.send(Ok(chunk.to_vec())).await.is_err() {
+                    return Ok(()); // Consumer gave up.
+                }
+                continue;
+            }
+            Ok(None) if total.is_none_or(|x| received >= x) => return Ok(()),
+            Ok(None) => anyhow::anyhow!("connection closed after {received} bytes"),
+            Err(e) => anyhow::Error::new(e).context("body read failed"),
+        };
+
+        let Some(validator) = &validator else {
+            return Err(error.context("download can't be resumed"));
+        };
+        if resumes >= MAX_RESUMES {
+            return Err(error);
+        }
+
+        resumes += 1;
+        tracing::warn!("Download of {url} interrupted, resuming at byte {received}: {error:#}");
+        resp = resume(client, &url, received, validator).await?;
+    }
+}
+
+/// Value for the `If-Range` header of requests resuming the given response.
+///
+/// `If-Range` only accepts strong entity tags, so weak ones fall back to
+/// `Last-Modified`. Returns `None` if the response has neither.
+fn range_validator(resp: &Response) -> Option<HeaderValue> {
+    let headers = resp.headers();
+    headers
+        .get(header::ETAG)
+        .filter(|x| !x.as_bytes().starts_with(b"W/"))
+        .or_else(|| headers.get(header::LAST_MODIFIED))
+        .cloned()
+}
+
+/// Request the remainder of a download, starting at the given offset.
+///
+/// The request is conditional on `validator`: if the file changed on the
+/// server since the first response, the server sends all of it instead of
+/// a range and the download fails.
+async fn resume(
+    client: &Client,
+    url: &str,
+    offset: u64,
+    validator: &HeaderValue,
+) -> Result<Response> {
+    let resp = client
+        .get(url)
+        .header(header::RANGE, format!("bytes={offset}-"))
+        .header(header::IF_RANGE, validator.clone())
+        .send()
+        .await
+        .context("resume request failed")?
+        .error_for_status()
+        .context("resume request returned non-success status")?;
+
+    if resp.status() != StatusCode::PARTIAL_CONTENT {
+        bail!("file changed on the server or server ignored range request");
+    }
+
+    Ok(resp)
+}
+
+/// Blocking reader for a download started with [`stream`].
+pub struct BodyReader {
+    rx: mpsc::Receiver<Result<Vec<u8>>>,
+    chunk: Vec<u8>,
+    pos: usize,
+}
+
+impl Read for BodyReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.chunk.len() {
+            match self.rx.blocking_recv() {
+                Some(Ok(chunk)) => {
+                    self.chunk = chunk;
+                    self.pos = 0;
+                }
+                Some(Err(e)) => return Err(io::Error::other(format!("{e:#}"))),
+                None => return Ok(0),
+            }
+        }
+
+        let n = buf.len().min(self.chunk.len() - self.pos);
+        buf[..n].copy_from_slice(&self.chunk[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::symbolizer::testserver::{self, Response};
+
+    /// Serves `content`, cutting every full response short after `cut` bytes.
+    ///
+    /// Responses carry `etag` if given, and range requests are honored if
+    /// their `If-Range` header matches it.
+    fn spawn_flaky_server(
+        content: &'static [u8],
+        cut: usize,
+        etag: Option<&'static str>,
+    ) -> String {
+        let addr = testserver::spawn(move |req| {
+            let offset = req
+                .header("Range")
+                .and_then(|x| x.strip_prefix("bytes="))
+                .and_then(|x| x.trim_end_matches('-').parse::<usize>().ok())
+                .filter(|_| etag.is_some() && req.header("If-Range") == etag);
+
+            let resp = match offset {
+                Some(offset) => Response::new("206 Partial Content", &content[offset..]),
+                None => Response::new("200 OK", content).cut(cut),
+            };
+            let resp = resp.header("Accept-Ranges", "bytes");
+            match etag {
+                Some(etag) => resp.header("ETag", etag),
+                None => resp,
+            }
+        });
+
+        format!("http://{addr}/file")
+    }
+
+    async fn download(url: String, progress: &Arc<DownloadProgress>) -> io::Result<Vec<u8>> {
+        let mut reader = stream(&Client::new(), url, Arc::clone(progress))
+            .await
+            .unwrap()
+            .unwrap();
+        tokio::task::spawn_blocking(move || {
+            let mut data = Vec::new();
+            reader.read_to_end(&mut data).map(|_| data)
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn resumes_interrupted_download() {
+        let content: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+        let url = spawn_flaky_server(content, 10, Some("\"v1\""));
+        let progress = Arc::new(DownloadProgress::default());
+
+        let data = download(url, &progress).await.unwrap();
+        assert_eq!(data, content);
+        assert_eq!(progress.bytes.load(Relaxed), content.len() as u64);
+        assert_eq!(progress.fraction(), Some(1.0));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn no_resume_without_validator() {
+        let content: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+        let url = spawn_flaky_server(content, 10, None);
+        let progress = Arc::new(DownloadProgress::default());
+
+        let err = download(url, &progress).await.unwrap_err();
+        assert!(err.to_string().contains("can't be resumed"), "{err}");
+        assert_eq!(progress.bytes.load(Relaxed), 10);
+    }
+
+    #[test]
+    fn progress_registration() {
+        let file_id = FileId::from_parts(0x1234, 0x5678);
+        let guard = ProgressGuard::new(file_id);
+        guard.symbols.fetch_add(3, Relaxed);
+        assert_eq!(download_progress(file_id).unwrap().symbols.load(Relaxed), 3);
+
+        drop(guard);
+        assert!(download_progress(file_id).is_none());
+    }
+}
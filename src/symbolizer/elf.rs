@@ -0,0 +1,130 @@
+// Copyright Elasticsearch B.V. and/or licensed to Elasticsearch B.V. under one
+// or more contributor license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Helpers for inspecting ELF files without running the full extractors.
+
+use anyhow::{Context, Result};
+use object::{Object, ObjectKind, ObjectSection};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Identifying information read from an ELF file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ElfInfo {
+    /// GNU build ID in lowercase hex.
+    pub build_id: Option<String>,
+
+    /// File name and CRC32 of the separate debug file (`.gnu_debuglink`).
+    pub debuglink: Option<(String, u32)>,
+
+    /// File name and build ID of the DWARF supplementary file
+    /// (`.gnu_debugaltlink`), as created by `dwz`.
+    pub debugaltlink: Option<(String, String)>,
+
+    /// Whether the file contains machine code, as opposed to being a
+    /// separate debug file with the code sections stripped.
+    pub has_code: bool,
+
+    /// Whether the file contains DWARF debug info.
+    pub has_dwarf: bool,
+}
+
+impl ElfInfo {
+    /// Parse the given ELF file.
+    pub fn from_data(data: &[u8]) -> Result<Self> {
+        let obj = object::File::parse(data).context("failed to parse ELF file")?;
+
+        let has_data = |name| {
+            obj.section_by_name(name)
+                .is_some_and(|x| x.file_range().is_some_and(|(_, size)| size > 0))
+        };
+
+        Ok(ElfInfo {
+            build_id: obj.build_id().ok().flatten().map(hex),
+            debuglink: obj
+                .gnu_debuglink()
+                .ok()
+                .flatten()
+                .map(|(name, crc)| (String::from_utf8_lossy(name).into_owned(), crc)),
+            debugaltlink: obj
+                .gnu_debugaltlink()
+                .ok()
+                .flatten()
+                .map(|(name, id)| (String::from_utf8_lossy(name).into_owned(), hex(id))),
+            has_code: has_data(".text"),
+            has_dwarf: has_data(".debug_info") || has_data(".zdebug_info"),
+        })
+    }
+
+    /// Read and parse the ELF file at the given path.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: the file might change while mapped. `object` validates all
+        //         offsets, so this can at worst result in garbage info.
+        let data = unsafe { memmap2::Mmap::map(&file)? };
+        Self::from_data(&data)
+    }
+}
+
+/// Check whether the file at the given path starts with the ELF magic and
+/// contains executable code or debug info (i.e. isn't a relocatable object).
+pub fn is_elf(path: &Path) -> bool {
+    let Ok(mut file) = File::open(path) else {
+        return false;
+    };
+    let mut magic = [0; 4];
+    if file.read_exact(&mut magic).is_err() || magic != *b"\x7fELF" {
+        return false;
+    }
+
+    // SAFETY: see `ElfInfo::from_path`.
+    let Ok(data) = (unsafe { memmap2::Mmap::map(&file) }) else {
+        return false;
+    };
+    object::File::parse(&*data).is_ok_and(|obj| obj.kind() != ObjectKind::Relocatable)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|x| format!("{x:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn own_executable() {
+        let exe = std::env::current_exe().unwrap();
+        assert!(is_elf(&exe));
+
+        let info = ElfInfo::from_path(&exe).unwrap();
+        if let Some(build_id) = info.build_id {
+            assert!(build_id.bytes().all(|x| x.is_ascii_hexdigit()));
+            assert_eq!(build_id, build_id.to_ascii_lowercase());
+        }
+    }
+
+    #[test]
+    fn non_elf() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"#!/bin/sh\n").unwrap();
+        assert!(!is_elf(file.path()));
+        assert!(ElfInfo::from_path(file.path()).is_err());
+    }
+}
@@ -0,0 +1,369 @@
+// Copyright Elasticsearch B.V. and/or licensed to Elasticsearch B.V. under one
+// or more contributor license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Import of kernel symbols from `/proc/kallsyms` dumps, `vmlinux` images and
+//! kernel modules (`.ko`).
+//!
+//! Kernel addresses are randomized on every boot (KASLR), so core kernel
+//! symbols are stored relative to `_stext` and module symbols relative to the
+//! start of their module (the `.text` section for `.ko` files). Kernel frames can only be symbolized if the agent
+//! reports their addresses relative to the image as well, or the start of the
+//! image in the mapping.
+
+use super::{extract_and_insert_symbols, SymbolFiles};
+use crate::storage::*;
+use anyhow::{bail, ensure, Context, Result};
+use indexmap::IndexSet;
+use object::{Object, ObjectKind, ObjectSection, ObjectSymbol, SymbolKind};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::atomic::AtomicUsize;
+use std::sync::Arc;
+
+/// Size assumed for a kallsyms symbol if the next symbol is further away.
+///
+/// kallsyms only lists start addresses: this keeps addresses in gaps (e.g.
+/// between the core kernel and the module area) from being attributed to
+/// the symbol preceding them.
+const MAX_KALLSYMS_SIZE: u64 = 64 * 1024;
+
+/// Import kernel symbols for the given kernel release.
+///
+/// Relocatable ELF files are treated as kernel modules (`.ko`), other ELF
+/// files as `vmlinux` images and anything else is parsed as a kallsyms dump.
+/// Returns the number of symbols imported per module.
+///
+/// Must be called from a blocking task.
+pub fn import_kernel_symbols(release: &str, path: &Path) -> Result<Vec<(String, u64)>> {
+    ensure!(!release.is_empty(), "kernel release must not be empty");
+
+    let mut file =
+        File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let mut magic = [0; 4];
+    let is_elf = file.read_exact(&mut magic).is_ok() && magic == *b"\x7fELF";
+    let source = path.display().to_string();
+
+    // Symbol trees are stored as extracted, along with the address that
+    // image-relative addresses need to be rebased to.
+    let imported = if is_elf {
+        let file = File::open(path)?;
+        // SAFETY: see `ElfInfo::from_path`.
+        let data = unsafe { memmap2::Mmap::map(&file)? };
+        let obj = object::File::parse(&*data).context("failed to parse ELF file")?;
+
+        if obj.kind() == ObjectKind::Relocatable {
+            let module =
+                module_name(&obj).unwrap_or_else(|| kernel_module_name(&path.to_string_lossy()));
+            let ranges = module_text_symbols(&obj)
+                .with_context(|| format!("failed to read symbols of {source}"))?;
+            let num_symbols = ranges.len() as u64;
+            insert_plain_symbols(kernel_symbols_id(release, &module), ranges)?;
+            vec![(module, num_symbols, 0)]
+        } else {
+            let text_start = vmlinux_text_start(&obj)
+                .with_context(|| format!("{source} has no `_stext` symbol"))?;
+            let num_symbols = extract_and_insert_symbols(
+                &SymbolFiles::single(path.to_owned()),
+                kernel_symbols_id(release, VMLINUX),
+                &AtomicUsize::new(0),
+                Arc::new(AtomicUsize::new(0)),
+            )?;
+            vec![(VMLINUX.to_owned(), num_symbols, text_start)]
+        }
+    } else {
+        let file = File::open(path)?;
+        let modules = parse_kallsyms(BufReader::new(file))
+            .with_context(|| format!("{source} is neither an ELF file nor a kallsyms dump"))?;
+
+        let mut imported = Vec::with_capacity(modules.len());
+        for (module, ranges) in modules {
+            let num_symbols = ranges.len() as u64;
+            insert_plain_symbols(kernel_symbols_id(release, &module), ranges)?;
+            imported.push((module, num_symbols, 0));
+        }
+        imported
+    };
+
+    for (module, num_symbols, text_start) in &imported {
+        DB.kernel_symbols.insert(
+            kernel_symbols_id(release, module),
+            KernelSymbols {
+                release: release.to_owned(),
+                module: module.clone(),
+                source: source.clone(),
+                num_symbols: *num_symbols,
+                text_start: *text_start,
+            },
+        );
+    }
+
+    tracing::info!(
+        "Imported kernel symbols for {} modules of release {release} from {source}",
+        imported.len()
+    );
+
+    Ok(imported
+        .into_iter()
+        .map(|(module, num_symbols, _)| (module, num_symbols))
+        .collect())
+}
+
+/// Read the link-time address of `_stext` from a `vmlinux` image.
+fn vmlinux_text_start(obj: &object::File) -> Option<u64> {
+    obj.symbols()
+        .find(|x| x.name() == Ok("_stext"))
+        .map(|x| x.address())
+}
+
+/// Read the module name from the `.modinfo` section of a kernel module.
+fn module_name(obj: &object::File) -> Option<String> {
+    let modinfo = obj.section_by_name(".modinfo")?.data().ok()?;
+    modinfo_name(modinfo)
+}
+
+/// Find the `name=` entry in the NUL separated `.modinfo` data.
+fn modinfo_name(modinfo: &[u8]) -> Option<String> {
+    modinfo
+        .split(|&x| x == 0)
+        .find_map(|x| x.strip_prefix(b"name="))
+        .and_then(|x| std::str::from_utf8(x).ok())
+        .filter(|x| !x.is_empty())
+        .map(kernel_module_name)
+}
+
+/// Function symbols in the `.text` section of a kernel module, relative to
+/// the section start.
+///
+/// Symbols of a relocatable object hold their offset within their section,
+/// which is where the kernel places the module's code. Functions in other
+/// sections (e.g. `.init.text`) aren't imported.
+fn module_text_symbols(obj: &object::File) -> Result<Vec<(Range<u64>, String)>> {
+    let text = obj.section_by_name(".text").context("no `.text` section")?;
+    let syms = obj
+        .symbols()
+        .filter(|x| x.section_index() == Some(text.index()) && x.kind() == SymbolKind::Text)
+        .filter_map(|x| Some((x.address(), x.size(), x.name().ok()?.to_owned())))
+        .filter(|x| !x.2.is_empty())
+        .collect();
+    Ok(sized_symbol_ranges(syms))
+}
+
+/// Derive symbol ranges from symbols with start address and size.
+///
+/// Symbols without a size extend to the next symbol, see
+/// [`symbol_ranges`].
+fn sized_symbol_ranges(syms: Vec<(u64, u64, String)>) -> Vec<(Range<u64>, String)> {
+    let sizes: HashMap<_, _> = syms
+        .iter()
+        .filter(|x| x.1 > 0)
+        .map(|x| (x.0, x.1))
+        .collect();
+    let starts = syms
+        .into_iter()
+        .map(|(start, _, name)| (start, name))
+        .collect();
+
+    symbol_ranges(starts)
+        .into_iter()
+        .map(|(range, name)| match sizes.get(&range.start) {
+            Some(&size) => (range.start..range.start.saturating_add(size), name),
+            None => (range, name),
+        })
+        .collect()
+}
+
+/// Parse a kallsyms dump into symbol ranges per module.
+///
+/// The [`VMLINUX`] entry contains the core kernel symbols relative to
+/// `_stext`. The entries for modules contain their symbols relative to the
+/// lowest address of the module.
+fn parse_kallsyms(reader: impl BufRead) -> Result<BTreeMap<String, Vec<(Range<u64>, String)>>> {
+    let mut core = Vec::new();
+    let mut per_module: BTreeMap<String, Vec<(u64, String)>> = BTreeMap::new();
+
+    for (idx, line) in reader.lines().enumerate() {
+        let line = line?;
+        let mut fields = line.split_whitespace();
+        let (Some(addr), Some(kind), Some(name)) = (fields.next(), fields.next(), fields.next())
+        else {
+            bail!("line {}: expected `<address> <type> <name>`", idx + 1);
+        };
+
+        let addr = u64::from_str_radix(addr, 16)
+            .with_context(|| format!("line {}: invalid address {addr:?}", idx + 1))?;
+        ensure!(kind.len() == 1, "line {}: invalid type {kind:?}", idx + 1);
+
+        // Only code symbols are of interest.
+        if !matches!(kind, "t" | "T" | "w" | "W") || addr == 0 {
+            continue;
+        }
+
+        match fields.next() {
+            Some(module) => per_module
+                .entry(kernel_module_name(module))
+                .or_default()
+                .push((addr, name.to_owned())),
+            None => core.push((addr, name.to_owned())),
+        }
+    }
+
+    ensure!(
+        !core.is_empty(),
+        "no code symbols with addresses found: dump /proc/kallsyms as root"
+    );
+    let text_start = core
+        .iter()
+        .find(|x| x.1 == "_stext")
+        .map(|x| x.0)
+        .context("no `_stext` symbol found")?;
+    core.retain(|x| x.0 >= text_start);
+    for sym in &mut core {
+        sym.0 -= text_start;
+    }
+
+    let mut modules = BTreeMap::new();
+    for (module, mut syms) in per_module {
+        let base = syms.iter().map(|x| x.0).min().unwrap_or(0);
+        for sym in &mut syms {
+            sym.0 -= base;
+        }
+        modules.insert(module, symbol_ranges(syms));
+    }
+    modules.insert(VMLINUX.to_owned(), symbol_ranges(core));
+
+    Ok(modules)
+}
+
+/// Derive symbol ranges from symbol start addresses.
+fn symbol_ranges(mut syms: Vec<(u64, String)>) -> Vec<(Range<u64>, String)> {
+    syms.sort_by_key(|x| x.0);
+    syms.dedup_by_key(|x| x.0);
+
+    let ends: Vec<_> = syms.iter().skip(1).map(|x| x.0).collect();
+    syms.into_iter()
+        .enumerate()
+        .map(|(idx, (start, name))| {
+            let max_end = start.saturating_add(MAX_KALLSYMS_SIZE);
+            let end = ends.get(idx).map_or(max_end, |&x| x.min(max_end));
+            (start..end, name)
+        })
+        .collect()
+}
+
+/// Insert symbols that only consist of a function name.
+fn insert_plain_symbols(file_id: FileId, ranges: Vec<(Range<u64>, String)>) -> Result<()> {
+    let mut strings = IndexSet::with_capacity(ranges.len());
+    let elements: Vec<_> = ranges
+        .into_iter()
+        .map(|(range, func)| rkyvtree::Element {
+            range,
+            value: SymRange {
+                func: StringRef(strings.insert_full(func).0 as u32),
+                file: StringRef::NONE,
+                call_file: StringRef::NONE,
+                call_line: None,
+                depth: 0,
+                line_table: vec![],
+            },
+        })
+        .collect();
+
+    DB.symbols.insert(
+        file_id,
+        SymTree {
+            strings: strings.into_iter().collect(),
+            tree: rkyvtree::Tree::from_iter(elements),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kallsyms() {
+        let dump = "\
+ffffffff80ff0000 T _text
+ffffffff81000000 T _stext
+ffffffff81000000 T startup_64
+ffffffff81000100 t secondary_startup_64
+ffffffff81000200 D some_data
+0000000000000000 A fixed_percpu_data
+ffffffffc0a00000 t nft_do_chain\t[nf_tables]
+ffffffffc0a00400 t nft_lookup_eval\t[nf_tables]
+ffffffffc0b00000 T ext4_sync_file\t[ext4]
+";
+
+        let modules = parse_kallsyms(dump.as_bytes()).unwrap();
+        assert_eq!(
+            modules.keys().collect::<Vec<_>>(),
+            ["ext4", "nf_tables", VMLINUX]
+        );
+
+        let vmlinux = &modules[VMLINUX];
+        assert_eq!(
+            vmlinux,
+            &[
+                (0..0x100, "_stext".to_owned()),
+                (
+                    0x100..0x100 + MAX_KALLSYMS_SIZE,
+                    "secondary_startup_64".to_owned()
+                ),
+            ]
+        );
+
+        assert_eq!(
+            modules["nf_tables"],
+            [
+                (0..0x400, "nft_do_chain".to_owned()),
+                (
+                    0x400..0x400 + MAX_KALLSYMS_SIZE,
+                    "nft_lookup_eval".to_owned()
+                ),
+            ]
+        );
+
+        assert!(parse_kallsyms("not kallsyms".as_bytes()).is_err());
+        assert!(parse_kallsyms("0000000000000000 T _stext\n".as_bytes()).is_err());
+        assert!(parse_kallsyms("ffffffff81000000 T startup_64\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn module_symbols() {
+        let modinfo = b"license=GPL\0depends=nf_tables\0name=nft-chain-nat\0vermagic=6.8\0";
+        assert_eq!(modinfo_name(modinfo).as_deref(), Some("nft_chain_nat"));
+        assert_eq!(modinfo_name(b"license=GPL\0"), None);
+
+        let ranges = sized_symbol_ranges(vec![
+            (0x40, 0, "nft_nat_do_chain".into()),
+            (0, 0x20, "nft_chain_nat_init".into()),
+            (0x80, 0x10, "nft_chain_nat_exit".into()),
+        ]);
+        assert_eq!(
+            ranges,
+            [
+                (0..0x20, "nft_chain_nat_init".to_owned()),
+                (0x40..0x80, "nft_nat_do_chain".to_owned()),
+                (0x80..0x90, "nft_chain_nat_exit".to_owned()),
+            ]
+        );
+    }
+}
@@ -15,7 +15,8 @@
 // specific language governing permissions and limitations
 // under the License.
 
-//! Automatically fetches symbols from the global infra.
+//! Automatically fetches symbols from the global infra, debuginfod servers
+//! and local symbol directories.
 
 #![cfg_attr(not(feature = "automagic-symbols"), allow(dead_code))]
 
@@ -25,8 +26,7 @@ use fallible_iterator::{FallibleIterator, IteratorExt};
 use indexmap::{IndexMap, IndexSet};
 use lazy_static::lazy_static;
 use std::collections::HashSet;
-use std::io::{self, Cursor};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering::Relaxed;
 use std::sync::{mpsc, Arc};
@@ -35,16 +35,50 @@ use symblib::symbconv::RangeExtractor;
 use symblib::{objfile, symbconv, symbfile};
 use tokio::task::JoinHandle;
 
+mod debugfile;
+mod debuginfod;
+mod disasm;
+mod download;
+mod elf;
+mod kernel;
+mod server;
+mod sources;
+mod symbfile_io;
+mod symdirs;
+#[cfg(test)]
+mod testserver;
+
+pub use debugfile::SymbolFiles;
+pub use debuginfod::Debuginfod;
+pub use disasm::{disassemble, Instruction};
+pub use download::{download_progress, DownloadProgress};
+pub use kernel::import_kernel_symbols;
+pub use server::serve_symbols;
+pub use sources::{SymbolSource, SymbolSourceConfig};
+pub use symbfile_io::export_symbfile;
+pub use symdirs::watch_symbol_dirs;
+
+use debugfile::pair_files;
+use symbfile_io::{file_id_from_symbfile_name, import_symbfile, is_symbfile_name};
+
 /// Frequency at which the executable table is checked for new entries.
 const SYMB_FREQ: Duration = Duration::from_secs(1);
 
 /// How long to wait if the first symbolization attempt for an executable failed.
+///
+/// Doubled with every further failed attempt.
 const SYMB_RETRY_FREQ: Duration = Duration::from_secs(30);
 
-/// Maximum number of executable to process in parallel.
-const SYMB_MAX_PAR: usize = 16;
+/// Upper bound for the delay between two symbolization attempts.
+const SYMB_MAX_RETRY_DELAY: Duration = Duration::from_secs(60 * 60);
+
+/// Default for the maximum number of executables to process in parallel.
+pub const SYMB_DEFAULT_PAR: usize = 16;
 
 lazy_static! {
+    /// Executables whose symbols are currently being fetched.
+    static ref FETCHING: std::sync::Mutex<HashSet<FileId>> = Default::default();
+
     /// HTTPS connection pool.
     static ref CLIENT: reqwest::Client = reqwest::Client::builder()
         .user_agent(concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")))
@@ -53,21 +87,33 @@ lazy_static! {
 }
 
 /// Periodically check the executable table for new entries and attempt to pull
-/// in the corresponding symbols from global infra.
+/// in the corresponding symbols from the symbol sources or debuginfod.
+///
+/// At most `max_par` executables are processed in parallel.
 #[cfg(feature = "automagic-symbols")]
-pub async fn monitor_executables(symb_endpoint: String) -> Result<()> {
+pub async fn monitor_executables(
+    sources: Vec<SymbolSource>,
+    debuginfod: Debuginfod,
+    max_par: usize,
+) -> Result<()> {
     let (tx, rx) = tokio::sync::mpsc::channel(32);
-    tokio::spawn(ingest_task_controller(rx, symb_endpoint));
+    tokio::spawn(ingest_task_controller(
+        rx,
+        sources.into(),
+        debuginfod,
+        max_par.max(1),
+    ));
 
     loop {
         tokio::time::sleep(SYMB_FREQ).await;
         let now = chrono::Utc::now().timestamp() as UtcTimestamp;
 
         for (file_id, meta_ref) in DB.executables.iter() {
-            match meta_ref.get().symb_status {
+            let meta = meta_ref.get();
+            match meta.symb_status {
                 ArchivedSymbStatus::NotAttempted => {}
                 ArchivedSymbStatus::TempError { last_attempt, .. }
-                    if now > last_attempt + SYMB_RETRY_FREQ.as_secs() => {}
+                    if now > last_attempt + retry_delay(meta.symb_attempts).as_secs() => {}
                 _ => continue,
             }
 
@@ -78,24 +124,61 @@ pub async fn monitor_executables(symb_endpoint: String) -> Result<()> {
     }
 }
 
+/// Delay before retrying symbolization after the given number of failed attempts.
+pub fn retry_delay(attempts: u32) -> Duration {
+    let factor = 1 << attempts.saturating_sub(1).min(16);
+    (SYMB_RETRY_FREQ * factor).min(SYMB_MAX_RETRY_DELAY)
+}
+
 #[cfg(not(feature = "automagic-symbols"))]
-pub async fn monitor_executables() -> Result<()> {
+pub async fn monitor_executables(
+    _sources: Vec<SymbolSource>,
+    _debuginfod: Debuginfod,
+    _max_par: usize,
+) -> Result<()> {
     std::future::pending::<()>().await;
     unreachable!()
 }
 
+/// Symbolization status and source of a finished symbol fetch.
+type FetchResult = Result<(SymbStatus, Option<String>)>;
+
 /// Spawns and manages ingestion tasks.
 async fn ingest_task_controller(
     mut rx: tokio::sync::mpsc::Receiver<(FileId, ExecutableMeta)>,
-    symb_endpoint: String,
+    sources: Arc<[SymbolSource]>,
+    debuginfod: Debuginfod,
+    max_par: usize,
 ) {
+    if sources.is_empty() && debuginfod.is_empty() {
+        while rx.recv().await.is_some() {}
+        return;
+    }
+
+    let fetch = move |file_id, meta| {
+        fetch_and_insert_symbols(Arc::clone(&sources), debuginfod.clone(), file_id, meta)
+    };
+    run_fetch_tasks(rx, max_par, fetch, store_fetch_result).await
+}
+
+/// Run `fetch` for the executables received from `rx`, with at most
+/// `max_par` of them in parallel, and pass the results to `complete`.
+async fn run_fetch_tasks<F, Fut>(
+    mut rx: tokio::sync::mpsc::Receiver<(FileId, ExecutableMeta)>,
+    max_par: usize,
+    fetch: F,
+    mut complete: impl FnMut(FileId, FetchResult),
+) where
+    F: Fn(FileId, ExecutableMeta) -> Fut,
+    Fut: std::future::Future<Output = FetchResult> + Send + 'static,
+{
     let mut pending = IndexMap::<FileId, ExecutableMeta>::new();
-    let mut active = HashSet::with_capacity(SYMB_MAX_PAR);
+    let mut active = HashSet::with_capacity(max_par);
     let mut tasks = tokio::task::JoinSet::new();
 
     // Spawn idle task to make sure that the task set never runs empty.
     // `join_next` returns immediately when no more tasks are in the set,
-    // but we want it to always wait.
+    // but we want it to always wait. It doesn't count toward `max_par`.
     tasks.spawn(std::future::pending());
 
     loop {
@@ -112,41 +195,109 @@ async fn ingest_task_controller(
                 let (file_id, result) = completion
                     .expect("idle task ensures never running out of tasks")
                     .expect("task panicked or was canceled");
-                let mut exe = DB.executables.get(file_id).unwrap().read();
-                let now = chrono::Utc::now().timestamp() as UtcTimestamp;
-                exe.symb_status = match result {
-                    Ok(status) => status,
-                    Err(e) => {
-                        tracing::error!("Failed to pull symbols: {e:?}");
-                        SymbStatus::TempError { last_attempt: now }
-                    }
-                };
-                DB.executables.insert(file_id, exe);
+                complete(file_id, result);
                 active.remove(&file_id);
+                FETCHING.lock().unwrap().remove(&file_id);
             },
         }
 
-        assert!(active.len() <= SYMB_MAX_PAR);
-        assert!(tasks.len() <= SYMB_MAX_PAR);
+        assert!(active.len() <= max_par);
+        assert!(tasks.len() <= max_par + 1);
 
         // In both cases: spawn as many new tasks as the limit permits.
-        while !pending.is_empty() && tasks.len() < SYMB_MAX_PAR {
+        while !pending.is_empty() && active.len() < max_par {
             let (file_id, meta) = pending.pop().unwrap();
-            if !symb_endpoint.is_empty() {
-                let task = fetch_and_insert_symbols(symb_endpoint.clone(), file_id, meta);
-                tasks.spawn(async move { (file_id, task.await) });
-                active.insert(file_id);
-            }
+            let task = fetch(file_id, meta);
+            tasks.spawn(async move { (file_id, task.await) });
+            active.insert(file_id);
+            FETCHING.lock().unwrap().insert(file_id);
         }
     }
 }
 
-/// Pull symbols for the given executable from Elastic's global symbolization
-/// infrastructure and insert them into the database.
+/// Whether symbols for the given executable are currently being fetched
+/// from the symbol sources or debuginfod.
+pub fn fetch_in_progress(file_id: FileId) -> bool {
+    FETCHING.lock().unwrap().contains(&file_id)
+}
+
+/// Record the result of a symbol fetch for an executable.
+fn store_fetch_result(file_id: FileId, result: FetchResult) {
+    let Some(exe) = DB.executables.get(file_id) else {
+        return;
+    };
+    let mut exe = exe.read();
+    let now = chrono::Utc::now().timestamp() as UtcTimestamp;
+    let complete = matches!(exe.symb_status, SymbStatus::Complete { .. });
+
+    match result {
+        // Don't override the user's choice made in the meantime.
+        _ if exe.symb_status == SymbStatus::Ignored => return,
+        // Symbols were ingested manually in the meantime: keep their status.
+        Ok((SymbStatus::NotPresentGlobally, _)) | Err(_) if complete => return,
+        Ok((status, source)) => {
+            exe.symb_status = status;
+            exe.symb_attempts = 0;
+            exe.symb_error = None;
+            exe.symb_source = source;
+        }
+        Err(e) => {
+            tracing::error!("Failed to pull symbols: {e:?}");
+            exe.symb_status = SymbStatus::TempError { last_attempt: now };
+            exe.symb_attempts += 1;
+            exe.symb_error = Some(format!("{e:#}"));
+        }
+    }
+    DB.executables.insert(file_id, exe);
+}
+
+/// Pull symbols for the given executable from the symbol sources or debuginfod
+/// and insert them into the database.
+///
+/// Sources are tried in order and debuginfod is only consulted if none of them
+/// has symbols. Returns the source that the symbols were taken from.
 async fn fetch_and_insert_symbols(
-    symb_endpoint: String,
+    sources: Arc<[SymbolSource]>,
+    debuginfod: Debuginfod,
     file_id: FileId,
     meta: ExecutableMeta,
+) -> Result<(SymbStatus, Option<String>)> {
+    let mut last_error = None;
+
+    for source in sources.iter() {
+        match fetch_and_insert_global_symbols(source, file_id, &meta).await {
+            Ok(SymbStatus::NotPresentGlobally) => {}
+            Ok(status) => return Ok((status, Some(source.url.clone()))),
+            Err(e) => {
+                tracing::warn!("Symbolization via {} failed: {e:?}", source.url);
+                last_error = Some(e);
+            }
+        }
+    }
+
+    if !debuginfod.is_empty() {
+        match fetch_and_insert_debuginfod_symbols(&debuginfod, file_id, &meta).await {
+            Ok((SymbStatus::NotPresentGlobally, _)) => {}
+            Ok(result) => return Ok(result),
+            Err(e) => last_error = Some(e),
+        }
+    }
+
+    // Only report "not present" if all sources agreed on that: otherwise we
+    // want to retry later.
+    match last_error {
+        Some(e) => Err(e),
+        None => Ok((SymbStatus::NotPresentGlobally, None)),
+    }
+}
+
+/// Pull symbols for the given executable from a symbol source serving the
+/// global symbolization infrastructure's layout and insert them into the
+/// database.
+async fn fetch_and_insert_global_symbols(
+    source: &SymbolSource,
+    file_id: FileId,
+    meta: &ExecutableMeta,
 ) -> Result<SymbStatus> {
     let exe = meta
         .file_name
@@ -154,26 +305,43 @@ async fn fetch_and_insert_symbols(
         .unwrap_or_else(|| format!("{file_id:?}"));
 
     tracing::info!(
-        r#"Fetching symbols for "{}" (file ID: {})"#,
+        r#"Fetching symbols for "{}" (file ID: {}) from {}"#,
         exe,
-        file_id.format_hex()
+        file_id.format_hex(),
+        source.url
     );
 
-    let Some(dbg_file_id) = fetch_dbg_file_id(symb_endpoint.clone(), file_id).await? else {
+    let Some(dbg_file_id) = fetch_dbg_file_id(source, file_id).await? else {
         tracing::info!("No symbols present for file ID {}", file_id.format_hex());
         return Ok(SymbStatus::NotPresentGlobally);
     };
 
-    let sym_reader = fetch_symbols(symb_endpoint, dbg_file_id).await?;
+    let progress = download::ProgressGuard::new(file_id);
+    let url = build_sym_url(&source.url, dbg_file_id, "ranges");
+    let Some(body) = download::stream(&source.client, url, progress.progress())
+        .await
+        .context("range request failed")?
+    else {
+        bail!(
+            "range file missing for file ID {}",
+            dbg_file_id.format_hex()
+        );
+    };
 
-    // Inserting the symbols is CPU bound: spawn extra task.
+    // Decompression and insertion are CPU bound and consume the download as
+    // it arrives: spawn extra task.
+    let progress2 = progress.progress();
     let num_symbols = tokio::task::spawn_blocking(move || -> Result<u64> {
+        let r = zstd::Decoder::new(body).context("failed to init decompressor")?;
+        let sym_reader = symbfile::Reader::new(r).context("failed to open symbfile")?;
+
         let mut num_symbols = 0;
         insert_symbols(
             file_id,
             sym_reader
                 .inspect(|_| {
                     num_symbols += 1;
+                    progress2.symbols.fetch_add(1, Relaxed);
                     Ok(())
                 })
                 .map_err(|e| e.into()),
@@ -185,8 +353,72 @@ async fn fetch_and_insert_symbols(
     Ok(SymbStatus::Complete { num_symbols })
 }
 
+/// Download the debug ELF for the given executable from debuginfod, extract
+/// its symbols and insert them into the database.
+async fn fetch_and_insert_debuginfod_symbols(
+    debuginfod: &Debuginfod,
+    file_id: FileId,
+    meta: &ExecutableMeta,
+) -> Result<(SymbStatus, Option<String>)> {
+    let Some(build_id) = meta.build_id.as_deref() else {
+        return Ok((SymbStatus::NotPresentGlobally, None));
+    };
+
+    tracing::info!(
+        "Fetching debug info for build ID {build_id} (file ID: {}) from debuginfod",
+        file_id.format_hex()
+    );
+
+    let progress = download::ProgressGuard::new(file_id);
+    let Some((file, server)) = debuginfod
+        .fetch_debuginfo_with_progress(build_id, &progress)
+        .await?
+    else {
+        tracing::info!("debuginfod has no debug info for build ID {build_id}");
+        return Ok((SymbStatus::NotPresentGlobally, None));
+    };
+
+    // DWARF supplementary files are served under their own build ID.
+    let sup_build_id = elf::ElfInfo::from_path(file.path())?
+        .debugaltlink
+        .map(|(_, build_id)| build_id);
+    let sup = match sup_build_id {
+        Some(ref sup_build_id) => debuginfod
+            .fetch_debuginfo_with_progress(sup_build_id, &progress)
+            .await?
+            .map(|(file, _)| file),
+        None => None,
+    };
+
+    // Extraction is CPU bound: spawn extra task.
+    let num_symbols = tokio::task::spawn_blocking(move || {
+        let num_extracted = AtomicUsize::new(0);
+        let num_ingested = Arc::new(AtomicUsize::new(0));
+        let files = SymbolFiles {
+            executable: file.path().to_owned(),
+            debug: None,
+            sup: sup.as_ref().map(|x| x.path().to_owned()),
+        };
+        extract_and_insert_symbols(&files, file_id, &num_extracted, num_ingested)
+    })
+    .await??;
+
+    Ok((SymbStatus::Complete { num_symbols }, Some(server)))
+}
+
 /// Insert symbols for the given file ID into the database.
-pub fn insert_symbols<T>(file_id: FileId, mut sym_reader: T) -> Result<()>
+pub fn insert_symbols<T>(file_id: FileId, sym_reader: T) -> Result<()>
+where
+    T: FallibleIterator<Item = symbfile::Record, Error = anyhow::Error>,
+{
+    DB.symbols.insert(file_id, build_sym_tree(sym_reader)?)
+}
+
+/// Convert symbfile records to the database format.
+///
+/// All ranges and strings are collected in memory before the tree is built,
+/// so memory use grows with the number of symbols, not just the file size.
+fn build_sym_tree<T>(mut sym_reader: T) -> Result<SymTree>
 where
     T: FallibleIterator<Item = symbfile::Record, Error = anyhow::Error>,
 {
@@ -228,42 +460,16 @@ where
         });
     }
 
-    // Construct tree & insert it.
-    DB.symbols.insert(
-        file_id,
-        SymTree {
-            strings: strings.into_iter().collect(),
-            tree: rkyvtree::Tree::from_iter(ranges),
-        },
-    )
-}
-
-/// Tries to fetch symbols for the given file ID.
-async fn fetch_symbols(
-    symb_endpoint: String,
-    file_id: FileId,
-) -> Result<symbfile::Reader<impl io::Read>> {
-    // TODO: stream response
-    let response = CLIENT
-        .get(build_sym_url(&symb_endpoint, file_id, "ranges"))
-        .send()
-        .await
-        .context("range request failed")?
-        .bytes()
-        .await
-        .context("range request body read failed")?;
-
-    let r = Cursor::new(response);
-    let r = zstd::Decoder::new(r).context("failed to init decompressor")?;
-    let r = symbfile::Reader::new(r).context("failed to open symbfile")?;
-
-    Ok(r)
+    Ok(SymTree {
+        strings: strings.into_iter().collect(),
+        tree: rkyvtree::Tree::from_iter(ranges),
+    })
 }
 
 /// Fetches the file ID containing the actual debug info for the given executable.
 ///
 /// The two can vary when split DWARF is being used.
-async fn fetch_dbg_file_id(symb_endpoint: String, file_id: FileId) -> Result<Option<FileId>> {
+async fn fetch_dbg_file_id(source: &SymbolSource, file_id: FileId) -> Result<Option<FileId>> {
     #[derive(serde::Deserialize)]
     struct MetaData {
         version: u32,
@@ -277,8 +483,9 @@ async fn fetch_dbg_file_id(symb_endpoint: String, file_id: FileId) -> Result<Opt
         dwarf_file_id: Option<String>,
     }
 
-    let resp = CLIENT
-        .get(build_sym_url(&symb_endpoint, file_id, "metadata.json"))
+    let resp = source
+        .client
+        .get(build_sym_url(&source.url, file_id, "metadata.json"))
         .send()
         .await
         .context("meta-data HTTP request failed")?;
@@ -315,20 +522,86 @@ fn build_sym_url(symb_endpoint: &str, file_id: FileId, file: &str) -> String {
     [symb_endpoint, &s[0..2], &s[2..4], &s, file].join("/")
 }
 
+/// Something to ingest symbols from.
+#[derive(Debug, Clone)]
+pub enum IngestSource {
+    /// Executable and its debug files to extract symbols from.
+    Executable(SymbolFiles),
+
+    /// Existing symbfile with symbols for the given file ID.
+    Symbfile {
+        path: PathBuf,
+        file_id: Option<FileId>,
+    },
+}
+
+impl IngestSource {
+    /// Classify the given files, pairing executables with their debug files.
+    ///
+    /// Symbfiles are expected to be named after the file ID they belong to.
+    pub fn classify(paths: Vec<PathBuf>) -> Vec<Self> {
+        let (symbfiles, others): (Vec<_>, Vec<_>) =
+            paths.into_iter().partition(|x| is_symbfile_name(x));
+
+        let symbfiles = symbfiles.into_iter().map(|path| IngestSource::Symbfile {
+            file_id: file_id_from_symbfile_name(&path),
+            path,
+        });
+
+        pair_files(others)
+            .into_iter()
+            .map(IngestSource::Executable)
+            .chain(symbfiles)
+            .collect()
+    }
+
+    /// Path of the primary file.
+    pub fn path(&self) -> &Path {
+        match self {
+            IngestSource::Executable(files) => &files.executable,
+            IngestSource::Symbfile { path, .. } => path,
+        }
+    }
+}
+
 /// Extract and ingest executable symbols in a background thread.
 pub struct IngestTask {
-    task: JoinHandle<Result<()>>,
+    task: JoinHandle<Result<FileId>>,
     ranges_extracted: Arc<AtomicUsize>,
     ranges_ingested: Arc<AtomicUsize>,
 }
 
 impl IngestTask {
-    pub fn spawn(path: PathBuf) -> Self {
+    pub fn spawn(source: IngestSource) -> Self {
+        Self::spawn_impl(source, None)
+    }
+
+    /// Ingest symbols from the given source for a specific file ID, e.g. to
+    /// re-symbolize an executable with an unstripped build of it.
+    pub fn spawn_as(source: IngestSource, file_id: FileId) -> Self {
+        Self::spawn_impl(source, Some(file_id))
+    }
+
+    fn spawn_impl(source: IngestSource, target: Option<FileId>) -> Self {
         let ranges_ingested = Arc::new(AtomicUsize::new(0));
         let ranges_ingested2 = Arc::clone(&ranges_ingested);
         let ranges_extracted = Arc::new(AtomicUsize::new(0));
         let ranges_extracted2 = Arc::clone(&ranges_extracted);
-        let task = move || Self::ingest(path, ranges_ingested2, ranges_extracted2);
+        let task = move || match source {
+            IngestSource::Executable(files) => {
+                Self::ingest(files, target, ranges_ingested2, ranges_extracted2)
+            }
+            IngestSource::Symbfile { path, file_id } => {
+                let file_id = target.or(file_id).ok_or_else(|| {
+                    anyhow!(
+                        "symbfile must be named after its file ID: {}",
+                        path.display()
+                    )
+                })?;
+                import_symbfile(&path, file_id, &ranges_extracted2, &ranges_ingested2)?;
+                Ok(file_id)
+            }
+        };
         Self {
             ranges_ingested,
             ranges_extracted,
@@ -348,95 +621,253 @@ impl IngestTask {
         self.task.is_finished()
     }
 
-    pub fn join(self) -> Result<()> {
+    /// Wait for the task to finish, returning the file ID of the executable.
+    pub fn join(self) -> Result<FileId> {
         let rt = tokio::runtime::Handle::current();
         rt.block_on(self.task).expect("ingester panicked")
     }
 
     fn ingest(
-        path: PathBuf,
+        files: SymbolFiles,
+        target: Option<FileId>,
         ranges_ingested: Arc<AtomicUsize>,
         ranges_extracted: Arc<AtomicUsize>,
-    ) -> Result<()> {
+    ) -> Result<FileId> {
+        let path = &files.executable;
+
         // Calculate file ID.
-        let file_id = FileId::from_path(&path)?;
+        let file_id = match target {
+            Some(file_id) => file_id,
+            None => FileId::from_path(path)?,
+        };
         tracing::info!(
             "File ID {} of dropped executable {}",
             file_id.format_es(),
             path.display()
         );
+        if let Some(ref debug) = files.debug {
+            tracing::info!("Using separate debug file {}", debug.display());
+        }
 
-        // Open executable's DWARF info.
-        let obj = symblib::objfile::File::load(&path)?;
-        let obj = obj.parse()?;
-        let dw = symblib::dwarf::Sections::load(&obj)?;
-
-        // Spawn another task for the conversion to DB format + insert.
-        let (tx, rx) = mpsc::sync_channel(10 * 1024);
-        let insert_task = tokio::task::spawn_blocking(move || -> Result<()> {
-            insert_symbols(
-                file_id,
-                rx.into_iter()
-                    .inspect(|_| {
-                        ranges_ingested.fetch_add(1, Relaxed);
-                    })
-                    .into_fallible()
-                    .map_err(|_| unreachable!()),
-            )?;
-            Ok(())
-        });
+        extract_and_insert_symbols(&files, file_id, &ranges_extracted, ranges_ingested)?;
 
-        // Feed the ingest thread with ranges.
-        let mut multi =
-            symbconv::multi::Extractor::new(&obj).context("failed to create multi extractor")?;
+        let file_name = path
+            .file_name()
+            .expect("could not have opened otherwise")
+            .to_string_lossy()
+            .into_owned();
+        let num_symbols = ranges_extracted.load(Relaxed) as u64;
+        let local_path = target.is_none().then_some(path.as_path());
+        mark_symbolized(file_id, num_symbols, Some(file_name), local_path, path);
 
-        multi.add("dwarf", symbconv::dwarf::Extractor::new(&dw));
-        multi.add("go", symbconv::go::Extractor::new(&obj));
-        multi.add(
-            "dbg-obj-sym",
-            symbconv::obj::Extractor::new(&obj, objfile::SymbolSource::Debug),
-        );
-        multi.add(
-            "dyn-obj-sym",
-            symbconv::obj::Extractor::new(&obj, objfile::SymbolSource::Dynamic),
-        );
+        Ok(file_id)
+    }
+}
 
-        multi.extract(&mut |range| {
-            let _ = tx.send(symbfile::Record::Range(range));
-            ranges_extracted.fetch_add(1, Relaxed);
-            Ok(())
-        })?;
+/// Update or create the executable record after ingesting symbols.
+///
+/// `local_path` is the path of the executable if symbols were extracted from
+/// the executable itself (as opposed to e.g. a different build of it).
+/// `source` is the file that the symbols were taken from.
+fn mark_symbolized(
+    file_id: FileId,
+    num_symbols: u64,
+    file_name: Option<String>,
+    local_path: Option<&Path>,
+    source: &Path,
+) {
+    let local_path = local_path.map(|x| x.to_string_lossy().into_owned());
+    let symb_source = Some(source.to_string_lossy().into_owned());
+    let symb_status = SymbStatus::Complete { num_symbols };
+
+    let exe = match DB.executables.get(file_id) {
+        Some(exe) => {
+            let mut exe = exe.read();
+            exe.symb_status = symb_status;
+            exe.symb_attempts = 0;
+            exe.symb_error = None;
+            exe.symb_source = symb_source;
+            if local_path.is_some() {
+                exe.local_path = local_path;
+            }
+            exe
+        }
+        None => ExecutableMeta {
+            build_id: None,
+            file_name,
+            symb_status,
+            mapping: None,
+            first_seen: None,
+            last_seen: None,
+            symb_attempts: 0,
+            symb_error: None,
+            local_path,
+            symb_source,
+        },
+    };
 
-        // Close channel and wait for insertion task to finish.
-        drop(tx);
-        let rt = tokio::runtime::Handle::current();
-        rt.block_on(insert_task).expect("DB inserter panicked")?;
+    DB.executables.insert(file_id, exe);
+}
 
-        // Update or create executable record.
-        let num_symbols = ranges_extracted.load(Relaxed) as u64;
-        let symb_status = SymbStatus::Complete { num_symbols };
+/// Extract symbols from the given executable and its debug files and insert
+/// them into the database for the given file ID.
+///
+/// Must be called from a blocking task.
+fn extract_and_insert_symbols(
+    files: &SymbolFiles,
+    file_id: FileId,
+    ranges_extracted: &AtomicUsize,
+    ranges_ingested: Arc<AtomicUsize>,
+) -> Result<u64> {
+    let obj = symblib::objfile::File::load(&files.executable)?;
+    let obj = obj.parse()?;
+
+    // Open DWARF info: from the separate debug file if there is one.
+    let dbg_obj = match files.debug {
+        Some(ref path) => Some(
+            symblib::objfile::File::load(path)
+                .with_context(|| format!("failed to load debug file {}", path.display()))?,
+        ),
+        None => None,
+    };
+    let dbg_obj = match dbg_obj {
+        Some(ref x) => Some(x.parse()?),
+        None => None,
+    };
+    let dbg = dbg_obj.as_ref().unwrap_or(&obj);
+
+    let sup_obj = match files.sup {
+        Some(ref path) => Some(
+            symblib::objfile::File::load(path)
+                .with_context(|| format!("failed to load supplementary file {}", path.display()))?,
+        ),
+        None => None,
+    };
+    let sup_obj = match sup_obj {
+        Some(ref x) => Some(x.parse()?),
+        None => None,
+    };
+
+    let mut dw = symblib::dwarf::Sections::load(dbg)?;
+    if let Some(ref sup) = sup_obj {
+        dw.load_sup(sup)
+            .context("failed to load DWARF supplementary file")?;
+    }
 
-        DB.executables.insert(
+    // Spawn another task for the conversion to DB format + insert.
+    let (tx, rx) = mpsc::sync_channel(10 * 1024);
+    let insert_task = tokio::task::spawn_blocking(move || -> Result<()> {
+        insert_symbols(
             file_id,
-            DB.executables.get(file_id).map_or_else(
-                || ExecutableMeta {
-                    build_id: None,
-                    file_name: Some(
-                        path.file_name()
-                            .expect("could not have opened otherwise")
-                            .to_string_lossy()
-                            .into_owned(),
-                    ),
-                    symb_status,
-                },
-                |exe| {
-                    let mut exe = exe.read();
-                    exe.symb_status = symb_status;
-                    exe
-                },
-            ),
-        );
+            rx.into_iter()
+                .inspect(|_| {
+                    ranges_ingested.fetch_add(1, Relaxed);
+                })
+                .into_fallible()
+                .map_err(|_| unreachable!()),
+        )?;
+        Ok(())
+    });
+
+    // Feed the ingest thread with ranges.
+    let mut multi =
+        symbconv::multi::Extractor::new(&obj).context("failed to create multi extractor")?;
 
+    multi.add("dwarf", symbconv::dwarf::Extractor::new(&dw));
+    multi.add("go", symbconv::go::Extractor::new(&obj));
+    multi.add(
+        "dbg-obj-sym",
+        symbconv::obj::Extractor::new(dbg, objfile::SymbolSource::Debug),
+    );
+    multi.add(
+        "dyn-obj-sym",
+        symbconv::obj::Extractor::new(&obj, objfile::SymbolSource::Dynamic),
+    );
+
+    let mut num_ranges = 0;
+    multi.extract(&mut |range| {
+        let _ = tx.send(symbfile::Record::Range(range));
+        ranges_extracted.fetch_add(1, Relaxed);
+        num_ranges += 1;
         Ok(())
+    })?;
+
+    // Close channel and wait for insertion task to finish.
+    drop(tx);
+    let rt = tokio::runtime::Handle::current();
+    rt.block_on(insert_task).expect("DB inserter panicked")?;
+
+    Ok(num_ranges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meta() -> ExecutableMeta {
+        ExecutableMeta {
+            build_id: None,
+            file_name: None,
+            symb_status: SymbStatus::NotAttempted,
+            mapping: None,
+            first_seen: None,
+            last_seen: None,
+            symb_attempts: 0,
+            symb_error: None,
+            local_path: None,
+            symb_source: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn fetch_concurrency() {
+        let (tx, rx) = tokio::sync::mpsc::channel(8);
+        let (done_tx, mut done_rx) = tokio::sync::mpsc::unbounded_channel();
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let fetch = {
+            let (running, peak) = (Arc::clone(&running), Arc::clone(&peak));
+            move |_: FileId, _: ExecutableMeta| {
+                let (running, peak) = (Arc::clone(&running), Arc::clone(&peak));
+                async move {
+                    let n = running.fetch_add(1, Relaxed) + 1;
+                    peak.fetch_max(n, Relaxed);
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    running.fetch_sub(1, Relaxed);
+                    Ok((SymbStatus::NotPresentGlobally, None))
+                }
+            }
+        };
+        let complete = move |file_id: FileId, _: FetchResult| done_tx.send(file_id).unwrap();
+        let controller = tokio::spawn(run_fetch_tasks(rx, 1, fetch, complete));
+
+        let file_ids: Vec<_> = (1..=3).map(|x| FileId::from_parts(x, 0xf00d)).collect();
+        for &file_id in &file_ids {
+            tx.send((file_id, meta())).await.unwrap();
+        }
+
+        // A single slot still fetches everything, one at a time.
+        let mut done = Vec::new();
+        for _ in &file_ids {
+            let file_id = tokio::time::timeout(Duration::from_secs(10), done_rx.recv());
+            done.push(file_id.await.unwrap().unwrap());
+        }
+        done.sort_by_key(|&x| u128::from(x));
+        assert_eq!(done, file_ids);
+        assert_eq!(peak.load(Relaxed), 1);
+        assert!(!fetch_in_progress(file_ids[0]));
+
+        controller.abort();
+    }
+
+    #[test]
+    fn retry_backoff() {
+        assert_eq!(retry_delay(0), SYMB_RETRY_FREQ);
+        assert_eq!(retry_delay(1), SYMB_RETRY_FREQ);
+        assert_eq!(retry_delay(2), SYMB_RETRY_FREQ * 2);
+        assert_eq!(retry_delay(3), SYMB_RETRY_FREQ * 4);
+        assert_eq!(retry_delay(20), SYMB_MAX_RETRY_DELAY);
+        assert_eq!(retry_delay(u32::MAX), SYMB_MAX_RETRY_DELAY);
     }
 }
@@ -0,0 +1,280 @@
+// Copyright Elasticsearch B.V. and/or licensed to Elasticsearch B.V. under one
+// or more contributor license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Serves the local symbol database in the layout of the global symbolization
+//! infrastructure, allowing other devfiler instances to use this one as their
+//! `--symb-endpoint`.
+
+use super::symbfile_io::open_encoded_symbfile;
+use crate::storage::*;
+use anyhow::{Context, Result};
+use hyper::body::Bytes;
+use hyper::header::{self, HeaderMap, HeaderValue};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use std::convert::Infallible;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::net::SocketAddr;
+use std::ops::Range;
+use std::time::UNIX_EPOCH;
+
+/// Size of the chunks that symbfiles are streamed in.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Resource within the directory of a file ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Resource {
+    MetaData,
+    Ranges,
+}
+
+/// Serve the symbol database on the given address.
+pub async fn serve_symbols(addr: SocketAddr) -> Result<()> {
+    let make_svc =
+        make_service_fn(|_conn| async { Ok::<_, Infallible>(service_fn(handle_request)) });
+
+    let server = Server::try_bind(&addr)
+        .with_context(|| format!("failed to bind symbol server to {addr}"))?
+        .serve(make_svc);
+
+    tracing::info!("Serving symbols on http://{addr}");
+    server.await.context("symbol server failed")
+}
+
+async fn handle_request(req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    if req.method() != Method::GET {
+        return Ok(status_response(StatusCode::METHOD_NOT_ALLOWED));
+    }
+
+    let Some((file_id, resource)) = parse_path(req.uri().path()) else {
+        return Ok(status_response(StatusCode::NOT_FOUND));
+    };
+
+    let response = match resource {
+        Resource::MetaData => match DB.symbols.get(file_id) {
+            Ok(Some(_)) => {
+                let meta = serde_json::json!({
+                    "version": 1,
+                    "symbolFileReferences": {
+                        "dwarfFileID": file_id.format_es(),
+                    },
+                });
+                Response::builder()
+                    .header("content-type", "application/json")
+                    .body(Body::from(meta.to_string()))
+                    .expect("valid response")
+            }
+            Ok(None) => status_response(StatusCode::NOT_FOUND),
+            Err(e) => internal_error(e),
+        },
+        Resource::Ranges => {
+            // Encoding larger trees takes a while: don't block the runtime.
+            match tokio::task::spawn_blocking(move || open_encoded_symbfile(file_id)).await {
+                Ok(Ok(Some(file))) => file_response(file, req.headers()),
+                Ok(Ok(None)) => status_response(StatusCode::NOT_FOUND),
+                Ok(Err(e)) => internal_error(e),
+                Err(e) => internal_error(e.into()),
+            }
+        }
+    };
+
+    Ok(response)
+}
+
+/// Stream the given file, honoring range requests.
+fn file_response(file: File, headers: &HeaderMap) -> Response<Body> {
+    let meta = match file.metadata() {
+        Ok(meta) => meta,
+        Err(e) => return internal_error(e.into()),
+    };
+    let size = meta.len();
+    let mtime = meta
+        .modified()
+        .ok()
+        .and_then(|x| x.duration_since(UNIX_EPOCH).ok());
+    let etag = format!("\"{:x}-{size:x}\"", mtime.unwrap_or_default().as_nanos());
+
+    // A range only applies to the version of the file it was requested for.
+    let if_range_ok = headers
+        .get(header::IF_RANGE)
+        .is_none_or(|x| x.as_bytes() == etag.as_bytes());
+    let range = match headers.get(header::RANGE) {
+        Some(value) if if_range_ok => parse_range(value, size),
+        _ => None,
+    };
+
+    let builder = Response::builder()
+        .header(header::CONTENT_TYPE, "application/octet-stream")
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::ETAG, &etag);
+
+    match range {
+        None => stream_range(file, 0..size, builder),
+        Some(Ok(range)) => {
+            let content_range = format!("bytes {}-{}/{size}", range.start, range.end - 1);
+            let builder = builder
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(header::CONTENT_RANGE, content_range);
+            stream_range(file, range, builder)
+        }
+        Some(Err(())) => builder
+            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .header(header::CONTENT_RANGE, format!("bytes */{size}"))
+            .body(Body::empty())
+            .expect("valid response"),
+    }
+}
+
+/// Stream the given byte range of the file from a blocking task.
+fn stream_range(
+    mut file: File,
+    range: Range<u64>,
+    builder: hyper::http::response::Builder,
+) -> Response<Body> {
+    if let Err(e) = file.seek(SeekFrom::Start(range.start)) {
+        return internal_error(e.into());
+    }
+
+    let (mut sender, body) = Body::channel();
+    let rt = tokio::runtime::Handle::current();
+    let mut remaining = range.end - range.start;
+    tokio::task::spawn_blocking(move || {
+        let mut buf = vec![0; CHUNK_SIZE];
+        while remaining > 0 {
+            let len = buf.len().min(remaining as usize);
+            let n = match file.read(&mut buf[..len]) {
+                Ok(0) | Err(_) => {
+                    sender.abort();
+                    return;
+                }
+                Ok(n) => n,
+            };
+            let chunk = Bytes::copy_from_slice(&buf[..n]);
+            if rt.block_on(sender.send_data(chunk)).is_err() {
+                return; // Client went away.
+            }
+            remaining -= n as u64;
+        }
+    });
+
+    builder
+        .header(header::CONTENT_LENGTH, range.end - range.start)
+        .body(body)
+        .expect("valid response")
+}
+
+/// Parse a `Range` header for a file of the given size.
+///
+/// Returns `None` for headers that aren't understood (including multiple
+/// ranges), which are ignored, and `Err` for unsatisfiable ranges.
+fn parse_range(value: &HeaderValue, size: u64) -> Option<Result<Range<u64>, ()>> {
+    let spec = value.to_str().ok()?.trim().strip_prefix("bytes=")?;
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = (start.trim(), end.trim());
+
+    let range = if start.is_empty() {
+        // `bytes=-<suffix length>`
+        let suffix: u64 = end.parse().ok()?;
+        size.saturating_sub(suffix)..size
+    } else {
+        // `bytes=<start>-[<end>]`, with an inclusive end.
+        let start: u64 = start.parse().ok()?;
+        let end = match end {
+            "" => size,
+            end => end.parse::<u64>().ok()?.saturating_add(1).min(size),
+        };
+        start..end
+    };
+
+    Some(if range.start < range.end {
+        Ok(range)
+    } else {
+        Err(())
+    })
+}
+
+/// Parse a request path of the form `[<prefix>/]<aa>/<bb>/<file-id>/<file>`.
+fn parse_path(path: &str) -> Option<(FileId, Resource)> {
+    let mut parts = path.trim_end_matches('/').rsplit('/');
+    let resource = match parts.next()? {
+        "metadata.json" => Resource::MetaData,
+        "ranges" => Resource::Ranges,
+        _ => return None,
+    };
+    let file_id_str = parts.next()?;
+    let b = parts.next()?;
+    let a = parts.next()?;
+
+    if file_id_str.get(0..2)? != a || file_id_str.get(2..4)? != b {
+        return None;
+    }
+
+    Some((FileId::try_parse_es(file_id_str)?, resource))
+}
+
+fn status_response(status: StatusCode) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .body(Body::empty())
+        .expect("valid response")
+}
+
+fn internal_error(e: anyhow::Error) -> Response<Body> {
+    tracing::error!("Failed to serve symbols: {e:?}");
+    status_response(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::build_sym_url;
+    use super::*;
+
+    #[test]
+    fn parse_paths() {
+        let file_id = FileId::from_parts(0x0123456789abcdef, 0xfedcba9876543210);
+
+        let url = build_sym_url("http://localhost:8080/prefix", file_id, "metadata.json");
+        let path = url.strip_prefix("http://localhost:8080").unwrap();
+        assert_eq!(parse_path(path), Some((file_id, Resource::MetaData)));
+
+        let url = build_sym_url("", file_id, "ranges");
+        assert_eq!(parse_path(&url), Some((file_id, Resource::Ranges)));
+
+        let es = file_id.format_es();
+        assert_eq!(parse_path(&format!("/xx/yy/{es}/ranges")), None);
+        assert_eq!(parse_path(&format!("/{}/{es}/ranges", &es[0..2])), None);
+        assert_eq!(parse_path("/metadata.json"), None);
+        let other = format!("/{}/{}/{es}/other", &es[0..2], &es[2..4]);
+        assert_eq!(parse_path(&other), None);
+    }
+
+    #[test]
+    fn ranges() {
+        let range = |x: &'static str| parse_range(&HeaderValue::from_static(x), 100);
+        assert_eq!(range("bytes=10-"), Some(Ok(10..100)));
+        assert_eq!(range("bytes=10-19"), Some(Ok(10..20)));
+        assert_eq!(range("bytes=90-200"), Some(Ok(90..100)));
+        assert_eq!(range("bytes=-30"), Some(Ok(70..100)));
+        assert_eq!(range("bytes=-300"), Some(Ok(0..100)));
+        assert_eq!(range("bytes=100-"), Some(Err(())));
+        assert_eq!(range("bytes=20-10"), Some(Err(())));
+        assert_eq!(range("bytes=0-1,5-6"), None);
+        assert_eq!(range("items=0-1"), None);
+        assert_eq!(range("bytes=x-"), None);
+    }
+}
@@ -0,0 +1,247 @@
+// Copyright Elasticsearch B.V. and/or licensed to Elasticsearch B.V. under one
+// or more contributor license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Symbol endpoints serving the global symbolization HTTP layout.
+
+use anyhow::{bail, Context, Result};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Default timeout for connecting and for waiting on data.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Options of a symbol endpoint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolSourceConfig {
+    /// Base URL of the endpoint.
+    pub url: String,
+
+    /// Additional HTTP headers sent along with every request.
+    pub headers: Vec<(String, String)>,
+
+    /// Token sent as `Authorization: Bearer <token>`.
+    pub bearer_token: Option<String>,
+
+    /// Timeout for connecting and for each read.
+    ///
+    /// Not a limit for the whole request: large downloads may take longer.
+    pub timeout: Duration,
+
+    /// PEM file with an additional CA certificate to trust.
+    pub ca_cert: Option<PathBuf>,
+
+    /// Skip TLS certificate verification.
+    pub insecure: bool,
+}
+
+impl SymbolSourceConfig {
+    pub fn new(url: String) -> Self {
+        SymbolSourceConfig {
+            url: url.trim_end_matches('/').to_owned(),
+            headers: Vec::new(),
+            bearer_token: None,
+            timeout: DEFAULT_TIMEOUT,
+            ca_cert: None,
+            insecure: false,
+        }
+    }
+
+    /// Parse a `--symb-endpoint` value.
+    ///
+    /// The format is `<url>[,<option>...]` with the following options:
+    ///
+    /// - `header=<name>:<value>`: send an additional header (repeatable)
+    /// - `token=<token>`: send a bearer token (visible in the process list,
+    ///   prefer `token-env`)
+    /// - `token-env=<var>`: send the bearer token in the given environment variable
+    /// - `timeout=<seconds>`: connect and read timeout
+    /// - `ca-cert=<path>`: trust the CA certificate in the given PEM file
+    /// - `insecure`: skip TLS certificate verification
+    ///
+    /// Commas and backslashes in option values are escaped with a backslash,
+    /// e.g. `header=Accept:a\,b`.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let (url, options) = spec.split_once(',').unwrap_or((spec, ""));
+        let url = url.trim();
+        if url.is_empty() {
+            bail!("symbol endpoint URL must not be empty");
+        }
+
+        let mut cfg = Self::new(url.to_owned());
+        for option in split_options(options)? {
+            let option = option.as_str();
+            let (key, value) = match option.split_once('=') {
+                Some((key, value)) => (key.trim(), Some(value.trim())),
+                None => (option.trim(), None),
+            };
+
+            match (key, value) {
+                ("header", Some(header)) => {
+                    let (name, value) = header.split_once(':').with_context(|| {
+                        format!("expected `header=<name>:<value>`, got {header:?}")
+                    })?;
+                    cfg.headers
+                        .push((name.trim().to_owned(), value.trim().to_owned()));
+                }
+                ("token", Some(token)) => cfg.bearer_token = Some(token.to_owned()),
+                ("token-env", Some(var)) => {
+                    let token = std::env::var(var)
+                        .with_context(|| format!("environment variable {var} not set"))?;
+                    cfg.bearer_token = Some(token);
+                }
+                ("timeout", Some(secs)) => {
+                    let secs: u64 = secs
+                        .parse()
+                        .with_context(|| format!("invalid timeout {secs:?}"))?;
+                    cfg.timeout = Duration::from_secs(secs);
+                }
+                ("ca-cert", Some(path)) => cfg.ca_cert = Some(path.into()),
+                ("insecure", None) => cfg.insecure = true,
+                _ => bail!("unknown symbol endpoint option {option:?}"),
+            }
+        }
+
+        Ok(cfg)
+    }
+}
+
+/// Split comma separated options, resolving `\,` and `\\` escapes.
+fn split_options(options: &str) -> Result<Vec<String>> {
+    if options.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut parts = vec![String::new()];
+    let mut chars = options.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            ',' => parts.push(String::new()),
+            '\\' => match chars.next() {
+                Some(c @ (',' | '\\')) => parts.last_mut().unwrap().push(c),
+                _ => bail!("invalid escape in symbol endpoint options {options:?}"),
+            },
+            c => parts.last_mut().unwrap().push(c),
+        }
+    }
+
+    Ok(parts)
+}
+
+/// Symbol endpoint along with the HTTP client configured for it.
+#[derive(Debug, Clone)]
+pub struct SymbolSource {
+    pub url: String,
+    pub client: reqwest::Client,
+}
+
+impl SymbolSource {
+    pub fn new(cfg: &SymbolSourceConfig) -> Result<Self> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &cfg.headers {
+            let name = HeaderName::try_from(name.as_str())
+                .with_context(|| format!("invalid header name {name:?}"))?;
+            let value = HeaderValue::try_from(value.as_str())
+                .with_context(|| format!("invalid value for header {name}"))?;
+            headers.append(name, value);
+        }
+        if let Some(ref token) = cfg.bearer_token {
+            let mut value =
+                HeaderValue::try_from(format!("Bearer {token}")).context("invalid bearer token")?;
+            value.set_sensitive(true);
+            headers.insert(AUTHORIZATION, value);
+        }
+
+        let mut builder = reqwest::Client::builder()
+            .user_agent(concat!(
+                env!("CARGO_PKG_NAME"),
+                "/",
+                env!("CARGO_PKG_VERSION")
+            ))
+            .default_headers(headers)
+            .connect_timeout(cfg.timeout)
+            .read_timeout(cfg.timeout)
+            .danger_accept_invalid_certs(cfg.insecure);
+
+        if let Some(ref path) = cfg.ca_cert {
+            let pem = std::fs::read(path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            let cert = reqwest::Certificate::from_pem(&pem)
+                .with_context(|| format!("invalid certificate in {}", path.display()))?;
+            builder = builder.add_root_certificate(cert);
+        }
+
+        Ok(SymbolSource {
+            url: cfg.url.clone(),
+            client: builder.build().context("failed to create HTTP client")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let cfg = SymbolSourceConfig::parse("https://symbols.example.com/").unwrap();
+        assert_eq!(
+            cfg,
+            SymbolSourceConfig::new("https://symbols.example.com".into())
+        );
+
+        let cfg = SymbolSourceConfig::parse(
+            "http://localhost:11001,header=X-Api-Key: abc,token=secret,timeout=5,insecure",
+        )
+        .unwrap();
+        assert_eq!(cfg.url, "http://localhost:11001");
+        assert_eq!(cfg.headers, [("X-Api-Key".to_owned(), "abc".to_owned())]);
+        assert_eq!(cfg.bearer_token.as_deref(), Some("secret"));
+        assert_eq!(cfg.timeout, Duration::from_secs(5));
+        assert!(cfg.insecure);
+
+        let cfg = SymbolSourceConfig::parse(
+            r"http://x,header=Accept: a\, b,header=X-Path: C:\\dir,token=x",
+        )
+        .unwrap();
+        assert_eq!(
+            cfg.headers,
+            [
+                ("Accept".to_owned(), "a, b".to_owned()),
+                ("X-Path".to_owned(), r"C:\dir".to_owned()),
+            ]
+        );
+        assert_eq!(cfg.bearer_token.as_deref(), Some("x"));
+
+        assert!(SymbolSourceConfig::parse("").is_err());
+        assert!(SymbolSourceConfig::parse(r"http://x,header=A:\b").is_err());
+        assert!(SymbolSourceConfig::parse("http://x,timeout=soon").is_err());
+        assert!(SymbolSourceConfig::parse("http://x,header=nocolon").is_err());
+        assert!(SymbolSourceConfig::parse("http://x,bogus").is_err());
+    }
+
+    #[test]
+    fn build_client() {
+        let mut cfg = SymbolSourceConfig::new("http://localhost".into());
+        cfg.bearer_token = Some("secret".into());
+        cfg.headers.push(("X-Tenant".into(), "a".into()));
+        assert!(SymbolSource::new(&cfg).is_ok());
+
+        cfg.headers.push(("Bad Header".into(), "a".into()));
+        assert!(SymbolSource::new(&cfg).is_err());
+    }
+}
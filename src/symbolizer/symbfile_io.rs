@@ -0,0 +1,350 @@
+// Copyright Elasticsearch B.V. and/or licensed to Elasticsearch B.V. under one
+// or more contributor license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Reading and writing symbfiles from and to disk.
+
+use super::{build_sym_tree, mark_symbolized};
+use crate::storage::*;
+use anyhow::{anyhow, Context, Result};
+use fallible_iterator::FallibleIterator;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::Path;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::Arc;
+use symblib::symbfile;
+
+/// Magic bytes at the start of zstd compressed files.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+/// Check whether the file name indicates a (possibly compressed) symbfile.
+pub fn is_symbfile_name(path: &Path) -> bool {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    name.ends_with(".symbfile") || name.ends_with(".symbfile.zst")
+}
+
+/// Parse the file ID from a symbfile name like `<file-id>.symbfile.zst`.
+///
+/// The file ID can be in either hex or ES notation.
+pub fn file_id_from_symbfile_name(path: &Path) -> Option<FileId> {
+    let name = path.file_name()?.to_str()?;
+    let name = name.strip_suffix(".zst").unwrap_or(name);
+    let stem = name.strip_suffix(".symbfile")?;
+    FileId::try_parse_hex(stem).or_else(|| FileId::try_parse_es(stem))
+}
+
+/// Read symbols from a symbfile on disk and insert them for the given file ID.
+///
+/// zstd compressed files are detected automatically.
+pub fn import_symbfile(
+    path: &Path,
+    file_id: FileId,
+    ranges_read: &AtomicUsize,
+    ranges_ingested: &AtomicUsize,
+) -> Result<u64> {
+    let sym_reader =
+        symbfile::Reader::new(open_symbfile(path)?).context("failed to open symbfile")?;
+
+    let mut num_symbols = 0;
+    let tree = build_sym_tree(
+        sym_reader
+            .inspect(|_| {
+                num_symbols += 1;
+                ranges_read.fetch_add(1, Relaxed);
+                ranges_ingested.fetch_add(1, Relaxed);
+                Ok(())
+            })
+            .map_err(|e| e.into()),
+    )?;
+    DB.symbols.insert(file_id, tree)?;
+
+    tracing::info!(
+        "Imported {num_symbols} symbols for file ID {} from {}",
+        file_id.format_hex(),
+        path.display()
+    );
+
+    mark_symbolized(file_id, num_symbols, None, None, path);
+    Ok(num_symbols)
+}
+
+/// Open a symbfile on disk, decompressing it if it is zstd compressed.
+fn open_symbfile(path: &Path) -> Result<Box<dyn Read>> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let mut reader = BufReader::new(file);
+    let compressed = reader.fill_buf()?.starts_with(&ZSTD_MAGIC);
+
+    Ok(if compressed {
+        Box::new(zstd::Decoder::with_buffer(reader).context("failed to init decompressor")?)
+    } else {
+        Box::new(reader)
+    })
+}
+
+/// Write the symbols stored for the given file ID to a symbfile.
+///
+/// The output is zstd compressed if the path ends with `.zst`.
+pub fn export_symbfile(file_id: FileId, path: &Path) -> Result<u64> {
+    let tree = DB
+        .symbols
+        .get(file_id)?
+        .ok_or_else(|| anyhow!("no symbols for file ID {}", file_id.format_hex()))?;
+
+    write_symbfile(&tree, path)
+}
+
+fn write_symbfile(tree: &ArchivedSymTree, path: &Path) -> Result<u64> {
+    // Write into a temporary file that is only moved into place on success,
+    // so failed exports don't leave a truncated file behind.
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let temp = tempfile::NamedTempFile::new_in(dir)
+        .with_context(|| format!("failed to create temp file in {}", dir.display()))?;
+    let file = BufWriter::new(temp);
+
+    let (num_symbols, file) = if path.extension().is_some_and(|x| x == "zst") {
+        let encoder = zstd::Encoder::new(file, 0).context("failed to init compressor")?;
+        let (num_symbols, encoder) = write_records(tree, encoder)?;
+        (num_symbols, encoder.finish()?)
+    } else {
+        write_records(tree, file)?
+    };
+
+    let temp = file.into_inner().map_err(|e| e.into_error())?;
+    temp.persist(path)
+        .with_context(|| format!("failed to create {}", path.display()))?;
+
+    Ok(num_symbols)
+}
+
+/// Open the symbols stored for the given file ID as a zstd compressed
+/// symbfile.
+///
+/// Encoded files are cached on disk (see [`SymDb::encoded_path`]) until the
+/// symbols change. Returns `None` if there are no symbols for the file ID.
+pub fn open_encoded_symbfile(file_id: FileId) -> Result<Option<File>> {
+    let path = DB.symbols.encoded_path(file_id);
+    match File::open(&path) {
+        Ok(file) => return Ok(Some(file)),
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => return Err(e).context("failed to open encoded symbfile"),
+    }
+
+    let Some(tree) = DB.symbols.get(file_id)? else {
+        return Ok(None);
+    };
+
+    // Encode into a temporary file that is atomically moved into place, so
+    // concurrent requests never see a partially written file.
+    let dir = path.parent().expect("encoded symbfile path has a parent");
+    let temp = tempfile::NamedTempFile::new_in(dir).context("failed to create temp file")?;
+    let encoder =
+        zstd::Encoder::new(BufWriter::new(temp), 0).context("failed to init compressor")?;
+    let (_, encoder) = write_records(&tree, encoder)?;
+    let temp = encoder.finish()?.into_inner().map_err(|e| e.into_error())?;
+    let file = temp
+        .persist(&path)
+        .context("failed to move encoded symbfile into place")?;
+
+    // Symbols replaced while encoding: don't keep the outdated file around.
+    let current = DB.symbols.get(file_id)?;
+    if !current.is_some_and(|x| Arc::ptr_eq(&x, &tree)) {
+        let _ = std::fs::remove_file(&path);
+    }
+
+    Ok(Some(file))
+}
+
+fn write_records<W: Write>(tree: &ArchivedSymTree, out: W) -> Result<(u64, W)> {
+    let string = |r: StringRef| tree.strings.get(r.0 as usize).map(|x| x.to_string());
+
+    let mut writer = symbfile::Writer::new(out).context("failed to create symbfile writer")?;
+    let mut num_symbols = 0;
+
+    for element in tree.tree.elements() {
+        let range = &element.value;
+        let length = u32::try_from(element.range.end - element.range.start).map_err(|_| {
+            anyhow!(
+                "symbol range {:#x}..{:#x} is too large for a symbfile",
+                element.range.start,
+                element.range.end
+            )
+        })?;
+        writer.write(symbfile::Record::Range(symbfile::Range {
+            elf_va: element.range.start,
+            length,
+            func: string(range.func).unwrap_or_default(),
+            file: string(range.file),
+            call_file: string(range.call_file),
+            call_line: range.call_line.as_ref().copied(),
+            depth: range.depth as u32,
+            line_table: range
+                .line_table
+                .iter()
+                .map(|x| symbfile::LineTableEntry {
+                    offset: x.offset,
+                    line_number: x.line_number,
+                })
+                .collect(),
+        }))?;
+        num_symbols += 1;
+    }
+
+    Ok((num_symbols, writer.finalize()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symbfile_names() {
+        let file_id = FileId::from_parts(0xdeadbeef, 0x1234);
+        let hex = file_id.format_hex();
+
+        for name in [format!("{hex}.symbfile"), format!("{hex}.symbfile.zst")] {
+            assert!(is_symbfile_name(Path::new(&name)));
+            assert_eq!(file_id_from_symbfile_name(Path::new(&name)), Some(file_id));
+        }
+
+        let es = format!("/tmp/{}.symbfile", file_id.format_es());
+        assert_eq!(file_id_from_symbfile_name(Path::new(&es)), Some(file_id));
+
+        assert!(!is_symbfile_name(Path::new("libc.so.6")));
+        assert_eq!(file_id_from_symbfile_name(Path::new("foo.symbfile")), None);
+    }
+
+    /// Read a symbfile and store its symbols in `db`.
+    fn import(db: &SymDb, file_id: FileId, path: &Path) -> Arc<MappedSymTree> {
+        let reader = symbfile::Reader::new(open_symbfile(path).unwrap()).unwrap();
+        let tree = build_sym_tree(reader.map_err(|e| e.into())).unwrap();
+        db.insert(file_id, tree).unwrap();
+        db.get(file_id).unwrap().unwrap()
+    }
+
+    /// Flatten the ranges of a tree into comparable strings.
+    fn ranges(tree: &ArchivedSymTree) -> Vec<String> {
+        let string = |r: StringRef| tree.strings.get(r.0 as usize).map(|x| x.as_str());
+        tree.tree
+            .elements()
+            .map(|x| {
+                let r = &x.value;
+                let lines: Vec<_> = r
+                    .line_table
+                    .iter()
+                    .map(|x| (x.offset, x.line_number))
+                    .collect();
+                format!(
+                    "{:#x}..{:#x} {:?} {:?} {:?} {:?} {} {lines:?}",
+                    x.range.start,
+                    x.range.end,
+                    string(r.func),
+                    string(r.file),
+                    string(r.call_file),
+                    r.call_line.as_ref(),
+                    r.depth,
+                )
+            })
+            .collect()
+    }
+
+    fn range(elf_va: u64, length: u32, func: &str, depth: u32) -> symbfile::Record {
+        symbfile::Record::Range(symbfile::Range {
+            elf_va,
+            length,
+            func: func.into(),
+            file: Some("main.c".into()),
+            call_file: (depth > 0).then(|| "main.c".into()),
+            call_line: (depth > 0).then_some(12),
+            depth,
+            line_table: vec![
+                symbfile::LineTableEntry {
+                    offset: 0,
+                    line_number: 10,
+                },
+                symbfile::LineTableEntry {
+                    offset: 4,
+                    line_number: 11,
+                },
+            ],
+        })
+    }
+
+    #[test]
+    fn import_export_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = SymDb::open_at(dir.path().join("db")).unwrap();
+        let input = dir.path().join("input.symbfile");
+        let file_id = FileId::from_parts(0x1234, 0x5678);
+
+        let mut writer = symbfile::Writer::new(File::create(&input).unwrap()).unwrap();
+        writer.write(range(0x1000, 0x40, "main", 0)).unwrap();
+        writer.write(range(0x1010, 0x8, "inlined", 1)).unwrap();
+        writer.write(range(0x2000, 0x10, "helper", 0)).unwrap();
+        writer.finalize().unwrap();
+
+        let imported = import(&db, file_id, &input);
+        let expected = ranges(&imported);
+        assert_eq!(expected.len(), 3);
+
+        // Both compressed and uncompressed exports must read back unchanged.
+        for name in ["output.symbfile", "output.symbfile.zst"] {
+            let output = dir.path().join(name);
+            assert_eq!(write_symbfile(&imported, &output).unwrap(), 3);
+
+            let other_id = FileId::from_parts(0xabcd, 0xef01);
+            let reimported = import(&db, other_id, &output);
+            assert_eq!(ranges(&reimported), expected);
+        }
+    }
+
+    #[test]
+    fn oversized_range() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = SymDb::open_at(dir.path().to_owned()).unwrap();
+        let file_id = FileId::from_parts(0x1234, 0x5678);
+
+        let tree = SymTree {
+            strings: vec!["huge".into()],
+            tree: rkyvtree::Tree::from_iter([rkyvtree::Element {
+                range: 0x1000..0x1000 + (1 << 32),
+                value: SymRange {
+                    func: StringRef(0),
+                    file: StringRef::NONE,
+                    call_file: StringRef::NONE,
+                    call_line: None,
+                    depth: 0,
+                    line_table: vec![],
+                },
+            }]),
+        };
+        db.insert(file_id, tree).unwrap();
+
+        let tree = db.get(file_id).unwrap().unwrap();
+        let err = write_records(&tree, Vec::new()).unwrap_err();
+        assert!(err.to_string().contains("too large"), "{err}");
+
+        // Failed exports don't leave a partial file behind.
+        let out_dir = tempfile::tempdir().unwrap();
+        let output = out_dir.path().join("out.symbfile");
+        assert!(write_symbfile(&tree, &output).is_err());
+        assert_eq!(std::fs::read_dir(out_dir.path()).unwrap().count(), 0);
+    }
+}
@@ -0,0 +1,350 @@
+// Copyright Elasticsearch B.V. and/or licensed to Elasticsearch B.V. under one
+// or more contributor license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Symbolization from local directories with executables and debug files.
+//!
+//! The directories are periodically re-scanned and ELF files within them
+//! indexed by file ID and GNU build ID. Executables in the database that
+//! match an indexed file are symbolized from it automatically.
+
+use super::elf::{self, ElfInfo};
+use super::{extract_and_insert_symbols, fetch_in_progress, mark_symbolized, SymbolFiles};
+use crate::storage::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicUsize;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// Frequency at which the directories are re-scanned for changes.
+const SCAN_FREQ: Duration = Duration::from_secs(5);
+
+/// Maximum directory depth to descend into.
+const MAX_DEPTH: usize = 16;
+
+/// Modification time and size of a file at the time it was indexed.
+type Stamp = (SystemTime, u64);
+
+/// Indexed ELF file.
+#[derive(Debug, Clone)]
+struct Entry {
+    file_id: FileId,
+    info: ElfInfo,
+    stamp: Stamp,
+}
+
+/// Index over the ELF files in a set of directories.
+#[derive(Debug, Default)]
+pub struct SymbolDirIndex {
+    files: HashMap<PathBuf, Entry>,
+    /// Files that aren't usable ELF files, so they aren't parsed again until
+    /// they change.
+    skipped: HashMap<PathBuf, Stamp>,
+    by_file_id: HashMap<FileId, PathBuf>,
+    by_build_id: HashMap<String, PathBuf>,
+}
+
+impl SymbolDirIndex {
+    /// Re-scan the given directories, updating the index for new, changed
+    /// and deleted files.
+    pub fn rescan(&mut self, dirs: &[PathBuf]) {
+        let mut found = HashMap::new();
+        for dir in dirs {
+            collect_files(dir, 0, &mut found);
+        }
+
+        self.files
+            .retain(|path, entry| found.get(path) == Some(&entry.stamp));
+        self.skipped
+            .retain(|path, stamp| found.get(path) == Some(stamp));
+
+        for (path, stamp) in found {
+            if self.files.contains_key(&path) || self.skipped.contains_key(&path) {
+                continue;
+            }
+            if !elf::is_elf(&path) {
+                self.skipped.insert(path, stamp);
+                continue;
+            }
+
+            let file_id = match FileId::from_path(&path) {
+                Ok(file_id) => file_id,
+                Err(e) => {
+                    tracing::debug!("Failed to hash {}: {e:?}", path.display());
+                    self.skipped.insert(path, stamp);
+                    continue;
+                }
+            };
+
+            let info = ElfInfo::from_path(&path).unwrap_or_default();
+            self.files.insert(
+                path,
+                Entry {
+                    file_id,
+                    info,
+                    stamp,
+                },
+            );
+        }
+
+        self.by_file_id.clear();
+        self.by_build_id.clear();
+        for (path, entry) in &self.files {
+            self.by_file_id.insert(entry.file_id, path.clone());
+
+            // Prefer files with DWARF for build ID lookups: typically the
+            // separate debug file of a stripped executable.
+            let Some(ref build_id) = entry.info.build_id else {
+                continue;
+            };
+            let replace = match self.by_build_id.get(build_id) {
+                Some(existing) => !self.files[existing].info.has_dwarf && entry.info.has_dwarf,
+                None => true,
+            };
+            if replace {
+                self.by_build_id.insert(build_id.clone(), path.clone());
+            }
+        }
+    }
+
+    /// Find the file to symbolize the given executable with.
+    ///
+    /// Files with an identical file ID are preferred over ones that merely
+    /// share the build ID (e.g. separate debug files).
+    pub fn lookup(&self, file_id: FileId, build_id: Option<&str>) -> Option<&Path> {
+        self.by_file_id
+            .get(&file_id)
+            .or_else(|| self.by_build_id.get(build_id?))
+            .map(PathBuf::as_path)
+    }
+
+    /// Files to extract the symbols for the given executable from.
+    ///
+    /// Stripped executables are paired with a debug file sharing the build
+    /// ID from the index, falling back to the system's debug directories.
+    pub fn symbol_files(&self, file_id: FileId, build_id: Option<&str>) -> Option<SymbolFiles> {
+        let path = self.lookup(file_id, build_id)?;
+        let mut files = SymbolFiles::single(path.to_owned());
+
+        let entry = &self.files[path];
+        if !entry.info.has_dwarf {
+            let debug = entry
+                .info
+                .build_id
+                .as_ref()
+                .and_then(|x| self.by_build_id.get(x))
+                .filter(|x| x.as_path() != path && self.files[*x].info.has_dwarf);
+            files.debug = debug.cloned();
+        }
+
+        files.resolve();
+        Some(files)
+    }
+
+    /// Modification stamps of all indexed files in the given set.
+    fn stamps(&self, files: &SymbolFiles) -> Vec<Option<Stamp>> {
+        [
+            Some(&files.executable),
+            files.debug.as_ref(),
+            files.sup.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|path| self.files.get(path).map(|x| x.stamp))
+        .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+/// Recursively collect all regular files below the given directory.
+fn collect_files(dir: &Path, depth: usize, out: &mut HashMap<PathBuf, Stamp>) {
+    if depth > MAX_DEPTH {
+        return;
+    }
+
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+
+        let path = entry.path();
+        if file_type.is_dir() {
+            // Symlinked directories are not followed to avoid cycles.
+            collect_files(&path, depth + 1, out);
+            continue;
+        }
+
+        // Follows symlinks to files, e.g. in `/usr/lib/debug/.build-id`.
+        let Ok(meta) = std::fs::metadata(&path) else {
+            continue;
+        };
+        if meta.is_file() {
+            let mtime = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            out.insert(path, (mtime, meta.len()));
+        }
+    }
+}
+
+/// Periodically scan the given directories and symbolize all executables
+/// that have matching files in them.
+pub async fn watch_symbol_dirs(dirs: Vec<PathBuf>) {
+    tracing::info!("Watching symbol directories: {dirs:?}");
+
+    let mut index = SymbolDirIndex::default();
+
+    // Files that executables were last symbolized (or failed to be
+    // symbolized) from. Allows re-ingesting when the files change.
+    let mut ingested = HashMap::<FileId, (SymbolFiles, Vec<Option<Stamp>>)>::new();
+
+    loop {
+        index = tokio::task::spawn_blocking({
+            let dirs = dirs.clone();
+            move || {
+                index.rescan(&dirs);
+                index
+            }
+        })
+        .await
+        .expect("directory scan panicked");
+
+        let mut todo = Vec::new();
+        for (file_id, meta_ref) in DB.executables.iter() {
+            let meta = meta_ref.get();
+            if matches!(meta.symb_status, ArchivedSymbStatus::Ignored) {
+                continue;
+            }
+
+            // Wait for running fetches, so their results don't overwrite
+            // ours. The executable is picked up again by the next scan.
+            if fetch_in_progress(file_id) {
+                continue;
+            }
+
+            let build_id = meta.build_id.as_ref().map(|x| x.as_str());
+
+            let Some(files) = index.symbol_files(file_id, build_id) else {
+                continue;
+            };
+            let stamps = index.stamps(&files);
+
+            let needs_ingest = match ingested.get(&file_id) {
+                // Re-ingest whenever the files change.
+                Some((f, s)) => *f != files || *s != stamps,
+                None => !matches!(meta.symb_status, ArchivedSymbStatus::Complete { .. }),
+            };
+            if !needs_ingest {
+                continue;
+            }
+
+            // Only remember the path if it is the executable itself rather
+            // than e.g. a separate debug file sharing the build ID.
+            let exact = index.by_file_id.get(&file_id) == Some(&files.executable);
+            let local_path = exact.then(|| files.executable.clone());
+
+            todo.push((file_id, files, stamps, local_path));
+        }
+
+        for (file_id, files, stamps, local_path) in todo {
+            ingested.insert(file_id, (files.clone(), stamps));
+            let source = files.executable.clone();
+            let result = tokio::task::spawn_blocking(move || {
+                tracing::info!(
+                    "Symbolizing file ID {} from {}",
+                    file_id.format_hex(),
+                    files.executable.display()
+                );
+                let num_extracted = AtomicUsize::new(0);
+                let num_ingested = Arc::new(AtomicUsize::new(0));
+                extract_and_insert_symbols(&files, file_id, &num_extracted, num_ingested)
+            })
+            .await
+            .expect("symbol extraction panicked");
+
+            match result {
+                Ok(num_symbols) => {
+                    mark_symbolized(file_id, num_symbols, None, local_path.as_deref(), &source)
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to extract symbols for {file_id:?}: {e:?}");
+                    if let Some(exe) = DB.executables.get(file_id) {
+                        let mut exe = exe.read();
+                        exe.symb_error = Some(format!("{e:#}"));
+                        DB.executables.insert(file_id, exe);
+                    }
+                }
+            }
+        }
+
+        tokio::time::sleep(SCAN_FREQ).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn index_lookup() {
+        let dir = tempfile::tempdir().unwrap();
+        let sub = dir.path().join("sub");
+        std::fs::create_dir(&sub).unwrap();
+
+        let exe = std::env::current_exe().unwrap();
+        let copy = sub.join("exe");
+        std::fs::copy(&exe, &copy).unwrap();
+        std::fs::write(dir.path().join("README"), "not an ELF").unwrap();
+
+        let mut index = SymbolDirIndex::default();
+        index.rescan(&[dir.path().to_owned()]);
+        assert_eq!(index.len(), 1);
+
+        // Non-ELF files are remembered until they change.
+        let readme = dir.path().join("README");
+        assert!(index.skipped.contains_key(&readme));
+        index.rescan(&[dir.path().to_owned()]);
+        assert_eq!(index.skipped.len(), 1);
+        std::fs::remove_file(&readme).unwrap();
+        index.rescan(&[dir.path().to_owned()]);
+        assert!(index.skipped.is_empty());
+
+        let file_id = FileId::from_path(&exe).unwrap();
+        assert_eq!(index.lookup(file_id, None), Some(copy.as_path()));
+
+        let build_id = ElfInfo::from_path(&exe).unwrap().build_id;
+        if let Some(build_id) = build_id {
+            let other = FileId::from_parts(1, 2);
+            assert_eq!(index.lookup(other, Some(&build_id)), Some(copy.as_path()));
+        }
+
+        std::fs::remove_file(&copy).unwrap();
+        index.rescan(&[dir.path().to_owned()]);
+        assert_eq!(index.len(), 0);
+        assert_eq!(index.lookup(file_id, None), None);
+    }
+}
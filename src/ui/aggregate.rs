@@ -0,0 +1,367 @@
+// Copyright Elasticsearch B.V. and/or licensed to Elasticsearch B.V. under one
+// or more contributor license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Options shared by the views aggregating stack traces.
+
+use crate::storage::*;
+use egui::{Button, Ui};
+use egui_phosphor::regular as icons;
+use regex::Regex;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Level of detail at which frames are merged when aggregating traces.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Granularity {
+    /// Every address (or interpreter line) is a separate frame.
+    Address,
+
+    /// Frames are merged by function and source line.
+    Line,
+
+    /// Frames are merged by function.
+    #[default]
+    Function,
+
+    /// Frames are merged by the executable or library containing them.
+    Executable,
+}
+
+impl Granularity {
+    pub const ALL: [Granularity; 4] = [Self::Address, Self::Line, Self::Function, Self::Executable];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Granularity::Address => "Address",
+            Granularity::Line => "Source line",
+            Granularity::Function => "Function",
+            Granularity::Executable => "Executable",
+        }
+    }
+
+    /// Remove the details of a symbolized frame that are merged away.
+    pub fn strip_details(self, frame: &mut SymbolizedFrame) {
+        match self {
+            Granularity::Address | Granularity::Executable => {}
+            Granularity::Line => frame.column = None,
+            Granularity::Function => {
+                frame.line_no = None;
+                frame.column = None;
+            }
+        }
+    }
+
+    /// Key identifying the frames that are merged at this granularity.
+    ///
+    /// `inline_idx` is the position of the frame within its inline chain.
+    /// Frames without a function name are never merged across addresses.
+    pub fn key(self, frame: &SymbolizedFrame, inline_idx: usize) -> u64 {
+        let raw = frame.raw;
+        match (self, &frame.func) {
+            (Granularity::Executable, _) => executable_key(raw),
+            (Granularity::Address, _) | (_, None) => hash_key((raw.id, inline_idx)),
+            (Granularity::Line, Some(func)) => {
+                hash_key((raw.id.file_id, raw.kind, func, &frame.file, frame.line_no))
+            }
+            (Granularity::Function, Some(func)) => {
+                hash_key((raw.id.file_id, raw.kind, func, &frame.file))
+            }
+        }
+    }
+}
+
+/// Key of the executable containing the frame, see [`executable_name`].
+pub fn executable_key(frame: Frame) -> u64 {
+    match frame.kind {
+        FrameKind::Regular(InterpKind::Native) => hash_key(frame.id.file_id),
+        kind => hash_key(frame_kind_name(kind)),
+    }
+}
+
+/// Name of the executable or library containing the frame.
+///
+/// Interpreted frames don't correspond to an executable and are named after
+/// their interpreter instead.
+pub fn executable_name(frame: Frame) -> String {
+    if frame.kind != FrameKind::Regular(InterpKind::Native) {
+        return format!("<{}>", frame_kind_name(frame.kind));
+    }
+
+    DB.executables
+        .get(frame.id.file_id)
+        .and_then(|x| x.read().file_name)
+        .unwrap_or_else(|| frame.id.file_id.format_hex())
+}
+
+/// Transformation applied to every stack trace before it is aggregated.
+///
+/// Stacks are passed root first. Transformations are applied in order, so
+/// e.g. focusing after dropping frames only considers the remaining frames.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Transform {
+    /// Keep only stacks through the given function, re-rooted at its
+    /// outermost occurrence.
+    Focus(String),
+
+    /// Drop stacks containing a function that contains the given substring.
+    Exclude(String),
+
+    /// Remove all frames with a function name matching the pattern.
+    DropFrames(FramePattern),
+
+    /// Merge directly recursive calls into a single frame.
+    CollapseRecursion,
+
+    /// Merge consecutive frames of the given interpreter into a single frame.
+    Fold(InterpKind),
+}
+
+impl Transform {
+    /// Short description for display in the transformation chain.
+    pub fn label(&self) -> String {
+        match self {
+            Transform::Focus(func) => format!("{} Focus: {func}", icons::CROSSHAIR),
+            Transform::Exclude(pattern) => format!("{} Exclude: {pattern}", icons::PROHIBIT),
+            Transform::DropFrames(re) => format!("{} Drop: /{}/", icons::ERASER, re.as_str()),
+            Transform::CollapseRecursion => format!("{} Collapse recursion", icons::ARROWS_IN),
+            Transform::Fold(kind) => format!("{} Fold: {kind}", icons::STACK),
+        }
+    }
+}
+
+/// Compiled regex, compared and hashed by its source.
+#[derive(Debug, Clone)]
+pub struct FramePattern(Regex);
+
+impl FramePattern {
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        Regex::new(pattern).map(Self)
+    }
+
+    /// Pattern matching exactly the given function name.
+    pub fn exact(func: &str) -> Self {
+        Self::new(&format!("^{}$", regex::escape(func))).expect("escaped regex is valid")
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    pub fn is_match(&self, func: &str) -> bool {
+        self.0.is_match(func)
+    }
+}
+
+impl PartialEq for FramePattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for FramePattern {}
+
+impl Hash for FramePattern {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+/// Frame that [`Transform`]s can be applied to.
+pub trait TransformFrame {
+    /// Function name that patterns are matched against.
+    fn func(&self) -> &str;
+
+    /// Merge key, see [`Granularity::key`].
+    fn key(&self) -> u64;
+
+    /// Frame kind, if this is a frame of a stack trace.
+    fn kind(&self) -> Option<FrameKind>;
+}
+
+/// Apply the transformation chain to a stack, root first.
+///
+/// `folded` returns the frame replacing the frames folded by
+/// [`Transform::Fold`]. Returns `false` if the stack is dropped entirely.
+pub fn apply_transforms<'a, F: TransformFrame>(
+    transforms: &[Transform],
+    stack: &mut Vec<&'a F>,
+    folded: impl Fn(InterpKind) -> &'a F,
+) -> bool {
+    for transform in transforms {
+        match transform {
+            Transform::Focus(func) => {
+                let Some(pos) = stack.iter().position(|x| x.func() == func) else {
+                    return false;
+                };
+                stack.drain(..pos);
+            }
+            Transform::Exclude(pattern) => {
+                if stack.iter().any(|x| x.func().contains(pattern.as_str())) {
+                    return false;
+                }
+            }
+            Transform::DropFrames(re) => stack.retain(|x| !re.is_match(x.func())),
+            Transform::CollapseRecursion => stack.dedup_by_key(|x| x.key()),
+            Transform::Fold(interp) => {
+                let fold = folded(*interp);
+                for frame in stack.iter_mut() {
+                    if frame.kind() == Some(FrameKind::Regular(*interp)) {
+                        *frame = fold;
+                    }
+                }
+                stack.dedup_by(|a, b| a.key() == fold.key() && b.key() == fold.key());
+            }
+        }
+    }
+
+    true
+}
+
+/// Draw the transformation chain as chips that remove themselves when clicked.
+pub fn draw_transform_chips(ui: &mut Ui, transforms: &mut Vec<Transform>) {
+    let mut remove = None;
+    ui.horizontal_wrapped(|ui| {
+        for (i, transform) in transforms.iter().enumerate() {
+            let text = format!("{} {}", transform.label(), icons::X);
+            let chip = ui.add(Button::new(text).small().corner_radius(8.0));
+            if chip.on_hover_text("Remove transformation").clicked() {
+                remove = Some(i);
+            }
+        }
+    });
+
+    if let Some(i) = remove {
+        transforms.remove(i);
+    }
+}
+
+/// Hash a value into a merge key.
+pub fn hash_key(value: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(addr: u64, func: &str, line: u32) -> SymbolizedFrame {
+        SymbolizedFrame {
+            raw: Frame {
+                id: FrameId {
+                    file_id: FileId::from_parts(1, 2),
+                    addr_or_line: addr,
+                },
+                kind: FrameKind::Regular(InterpKind::Python),
+            },
+            func: Some(func.to_owned()),
+            file: Some("main.py".to_owned()),
+            line_no: Some(line),
+            column: Some(4),
+        }
+    }
+
+    #[test]
+    fn keys() {
+        let a = frame(0x10, "main", 1);
+        let b = frame(0x20, "main", 1);
+        let c = frame(0x30, "main", 2);
+        let d = frame(0x40, "other", 3);
+
+        let g = Granularity::Address;
+        assert_ne!(g.key(&a, 0), g.key(&b, 0));
+        assert_ne!(g.key(&a, 0), g.key(&a, 1));
+
+        let g = Granularity::Line;
+        assert_eq!(g.key(&a, 0), g.key(&b, 1));
+        assert_ne!(g.key(&a, 0), g.key(&c, 0));
+
+        let g = Granularity::Function;
+        assert_eq!(g.key(&a, 0), g.key(&c, 0));
+        assert_ne!(g.key(&a, 0), g.key(&d, 0));
+
+        let g = Granularity::Executable;
+        assert_eq!(g.key(&a, 0), g.key(&d, 0));
+
+        let mut e = frame(0x10, "main", 1);
+        Granularity::Function.strip_details(&mut e);
+        assert_eq!((e.line_no, e.column), (None, None));
+    }
+
+    struct TestFrame(&'static str, InterpKind);
+
+    impl TransformFrame for TestFrame {
+        fn func(&self) -> &str {
+            self.0
+        }
+
+        fn key(&self) -> u64 {
+            hash_key(self.0)
+        }
+
+        fn kind(&self) -> Option<FrameKind> {
+            Some(FrameKind::Regular(self.1))
+        }
+    }
+
+    fn transform(transforms: &[Transform], stack: &[TestFrame]) -> Option<Vec<&'static str>> {
+        static FOLDED: TestFrame = TestFrame("<folded>", InterpKind::Native);
+        let mut stack: Vec<_> = stack.iter().collect();
+        apply_transforms(transforms, &mut stack, |_| &FOLDED)
+            .then(|| stack.iter().map(|x| x.0).collect())
+    }
+
+    #[test]
+    fn transforms() {
+        use InterpKind::{Native, Python};
+        let stack = [
+            TestFrame("_start", Native),
+            TestFrame("PyEval", Native),
+            TestFrame("main", Python),
+            TestFrame("fib", Python),
+            TestFrame("fib", Python),
+            TestFrame("malloc", Native),
+        ];
+
+        assert_eq!(
+            transform(&[Transform::Focus("main".into())], &stack),
+            Some(vec!["main", "fib", "fib", "malloc"]),
+        );
+        assert_eq!(transform(&[Transform::Focus("nope".into())], &stack), None);
+        assert_eq!(
+            transform(&[Transform::Exclude("alloc".into())], &stack),
+            None
+        );
+        assert_eq!(
+            transform(
+                &[
+                    Transform::DropFrames(FramePattern::new("^(_start|Py)").unwrap()),
+                    Transform::CollapseRecursion,
+                ],
+                &stack,
+            ),
+            Some(vec!["main", "fib", "malloc"]),
+        );
+        assert_eq!(
+            transform(&[Transform::Fold(Python)], &stack),
+            Some(vec!["_start", "PyEval", "<folded>", "malloc"]),
+        );
+        assert!(FramePattern::exact("a.b").is_match("a.b"));
+        assert!(!FramePattern::exact("a.b").is_match("axb"));
+    }
+}
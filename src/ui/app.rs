@@ -19,19 +19,33 @@ use super::*;
 use crate::collector::Collector;
 use crate::storage::dbtypes::UtcTimestamp;
 use crate::storage::{RawTable, SampleKind, DB};
+use crate::ui::aggregate::Granularity;
 use crate::ui::cached::Cached;
+use crate::ui::editor::{open_in_editor, EditorPreset};
+use crate::ui::pathmap::{PathMap, PathMapRule};
 use crate::ui::tabs::{Tab, TabWidget};
+use crate::ui::util::humanize_count;
 use chrono::Duration;
-use eframe::egui::{Align, Layout};
+use eframe::egui::{Align, Color32, Layout};
 use eframe::{egui, egui::Ui};
 use egui::{Button, Image, Label, Pos2, Rect, RichText, Sense, Vec2, Widget};
 use egui_commonmark::{CommonMarkCache, CommonMarkViewer};
+use egui_phosphor::regular as icons;
 use egui_plot::{Axis, AxisHints, Line, Plot, PlotBounds};
+use std::sync::atomic::Ordering::Relaxed;
 
 #[derive(Debug)]
 pub struct DevfilerConfig {
     pub dev_mode: bool,
     pub collector: Collector,
+    /// Strip template arguments, argument lists and hashes from function names.
+    pub simplify_names: bool,
+    /// Level of detail at which frames are merged in aggregated views.
+    pub granularity: Granularity,
+    /// Rules for locating source files from the symbols on the local system.
+    pub path_map: PathMap,
+    /// Command template for opening source files, see [`EditorPreset`].
+    pub editor_command: String,
 }
 
 pub struct DevfilerUi {
@@ -40,6 +54,7 @@ pub struct DevfilerUi {
     sample_agg_cache: Cached<Vec<[f64; 2]>>,
     cfg: DevfilerConfig,
     show_add_data_window: bool,
+    show_settings_window: bool,
     md_cache: CommonMarkCache,
     auto_scroll_time: Option<Duration>,
     kind: SampleKind,
@@ -53,17 +68,24 @@ impl eframe::App for DevfilerUi {
         if self.show_add_data_window {
             self.draw_add_data_window(ctx);
         }
+
+        if self.show_settings_window {
+            self.draw_settings_window(ctx);
+        }
     }
 }
 
 impl DevfilerUi {
-    pub fn new(collector: Collector) -> Self {
+    pub fn new(collector: Collector, path_map: PathMap, editor: Option<String>) -> Self {
         DevfilerUi {
             active_tab: Tab::FlameGraph,
             tabs: vec![
                 Box::new(tabs::FlameGraphTab::default()),
+                Box::new(tabs::CallTreeTab::default()),
                 Box::new(tabs::FlameScopeTab::default()),
                 Box::new(tabs::TopFuncsTab::default()),
+                Box::new(tabs::SourceTab::default()),
+                Box::new(tabs::DisassemblyTab::default()),
                 Box::new(tabs::ExecutablesTab::default()),
                 Box::new(tabs::LogTab::default()),
                 // Keep dev mode tabs below.
@@ -74,12 +96,18 @@ impl DevfilerUi {
             sample_agg_cache: Cached::default(),
             cfg: DevfilerConfig {
                 collector,
+                simplify_names: false,
+                granularity: Granularity::default(),
+                path_map,
+                editor_command: editor
+                    .unwrap_or_else(|| EditorPreset::default().template().to_owned()),
                 #[cfg(feature = "default-dev-mode")]
                 dev_mode: true,
                 #[cfg(not(feature = "default-dev-mode"))]
                 dev_mode: false,
             },
             show_add_data_window: DB.stack_traces.count_estimate() == 0,
+            show_settings_window: false,
             md_cache: CommonMarkCache::default(),
             auto_scroll_time: Some(Duration::try_minutes(15).unwrap()),
             kind: SampleKind::Mixed,
@@ -109,32 +137,61 @@ impl DevfilerUi {
                 });
                 ui[1].with_layout(Layout::right_to_left(Align::Min), |ui| {
                     self.sample_selector(ui);
-                    self.time_selector(ui)
+                    self.granularity_selector(ui);
+                    self.name_selector(ui);
+                    self.time_selector(ui);
+                    self.forward_status(ui);
                 });
             });
 
             let (data_start, data_end) = self.samples_widget(ui);
 
+            let mut action = None;
             if let Some(active_tab) = self.tabs.iter_mut().find(|t| t.id() == self.active_tab) {
                 ui.push_id(active_tab.id(), |ui| {
-                    let action = active_tab.update(ui, &self.cfg, self.kind, data_start, data_end);
-
-                    // Handle any tab action returned
-                    if let Some(tabs::TabAction::SwitchTabWithTimeRange { tab, start, end }) =
-                        action
-                    {
-                        self.active_tab = tab;
-                        // Disable auto-scroll when switching with a specific time range
-                        self.auto_scroll_time = None;
-                        // Set the requested time range for the next frame
-                        self.requested_time_range = Some((start, end));
-                        ctx.request_repaint();
-                    }
+                    action = active_tab.update(ui, &self.cfg, self.kind, data_start, data_end);
                 });
             }
+
+            // Handle any tab action returned
+            if let Some(action) = action {
+                self.handle_tab_action(ctx, action);
+            }
         });
     }
 
+    fn handle_tab_action(&mut self, ctx: &egui::Context, action: tabs::TabAction) {
+        match action {
+            tabs::TabAction::SwitchTabWithTimeRange { tab, start, end } => {
+                self.active_tab = tab;
+                // Disable auto-scroll when switching with a specific time range
+                self.auto_scroll_time = None;
+                // Set the requested time range for the next frame
+                self.requested_time_range = Some((start, end));
+            }
+            tabs::TabAction::ShowSource { .. }
+            | tabs::TabAction::ShowDisassembly { .. }
+            | tabs::TabAction::ShowSandwich { .. } => {
+                for tab in &mut self.tabs {
+                    tab.handle_action(&action);
+                }
+                self.active_tab = match action {
+                    tabs::TabAction::ShowSource { .. } => Tab::Source,
+                    tabs::TabAction::ShowSandwich { .. } => Tab::FlameGraph,
+                    _ => Tab::Disassembly,
+                };
+            }
+            tabs::TabAction::OpenInEditor { file, line } => {
+                let cfg = &self.cfg;
+                if let Err(e) = open_in_editor(&cfg.editor_command, &cfg.path_map, &file, line) {
+                    tracing::error!("Failed to open {file} in editor: {e:#}");
+                }
+            }
+        }
+
+        ctx.request_repaint();
+    }
+
     fn draw_add_data_window(&mut self, ctx: &egui::Context) {
         const DEFAULT_WIDTH: f32 = 800.0;
         const DEFAULT_HEIGHT: f32 = 600.0;
@@ -168,6 +225,91 @@ impl DevfilerUi {
         });
     }
 
+    fn draw_settings_window(&mut self, ctx: &egui::Context) {
+        let mut still_open = true;
+
+        egui::Window::new("Settings")
+            .collapsible(true)
+            .default_width(600.0)
+            .open(&mut still_open)
+            .show(ctx, |ui| {
+                ui.vertical(|ui| {
+                    self.draw_path_map_settings(ui);
+                    ui.separator();
+                    self.draw_editor_settings(ui);
+                });
+            });
+
+        if !still_open {
+            self.show_settings_window = false;
+        }
+    }
+
+    fn draw_path_map_settings(&mut self, ui: &mut Ui) {
+        ui.strong("Source path remapping");
+        ui.label(
+            "Source file paths recorded at build time that start with a prefix on \
+             the left are looked up below the local directory on the right.",
+        );
+        ui.add_space(5.0);
+
+        let mut remove = None;
+        egui::Grid::new("path_map").num_columns(3).show(ui, |ui| {
+            ui.label("Build path prefix");
+            ui.label("Local directory");
+            ui.end_row();
+
+            for (idx, rule) in self.cfg.path_map.rules.iter_mut().enumerate() {
+                ui.text_edit_singleline(&mut rule.from);
+                ui.text_edit_singleline(&mut rule.to);
+                if ui
+                    .button(icons::TRASH)
+                    .on_hover_text("Remove rule")
+                    .clicked()
+                {
+                    remove = Some(idx);
+                }
+                ui.end_row();
+            }
+        });
+
+        if let Some(idx) = remove {
+            self.cfg.path_map.rules.remove(idx);
+        }
+
+        if ui.button(format!("{} Add rule", icons::PLUS)).clicked() {
+            self.cfg.path_map.rules.push(PathMapRule::default());
+        }
+    }
+
+    fn draw_editor_settings(&mut self, ui: &mut Ui) {
+        ui.strong("External editor");
+        ui.label(
+            "Command used by \"Open in editor\". `{file}` and `{line}` are replaced \
+             with the location, `$EDITOR` with the environment variable.",
+        );
+        ui.add_space(5.0);
+
+        ui.horizontal(|ui| {
+            let preset = EditorPreset::from_template(&self.cfg.editor_command);
+            egui::ComboBox::new("editor_preset", "")
+                .selected_text(preset.map_or("Custom", EditorPreset::name))
+                .show_ui(ui, |ui| {
+                    for preset in EditorPreset::ALL {
+                        if ui.selectable_label(false, preset.name()).clicked() {
+                            self.cfg.editor_command = preset.template().to_owned();
+                        }
+                    }
+                });
+
+            ui.add(
+                egui::TextEdit::singleline(&mut self.cfg.editor_command)
+                    .code_editor()
+                    .desired_width(f32::INFINITY),
+            );
+        });
+    }
+
     fn samples_widget(&mut self, ui: &mut Ui) -> (UtcTimestamp, UtcTimestamp) {
         let plot = Plot::new("trace_counts")
             .custom_x_axes(vec![timeaxis::mk_time_axis(Axis::X)])
@@ -258,6 +400,13 @@ impl DevfilerUi {
             {
                 self.show_add_data_window = !self.show_add_data_window;
             }
+
+            if ui
+                .selectable_label(self.show_settings_window, "Settings")
+                .clicked()
+            {
+                self.show_settings_window = !self.show_settings_window;
+            }
         });
     }
 
@@ -297,4 +446,59 @@ impl DevfilerUi {
             ui.label("Sample kind:");
         });
     }
+
+    fn granularity_selector(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            egui::ComboBox::new("granularity", "")
+                .selected_text(self.cfg.granularity.name())
+                .show_ui(ui, |ui| {
+                    for granularity in Granularity::ALL {
+                        ui.selectable_value(
+                            &mut self.cfg.granularity,
+                            granularity,
+                            granularity.name(),
+                        );
+                    }
+                });
+
+            ui.label("Group frames by:")
+                .on_hover_text("Level of detail at which frames are merged in aggregated views");
+        });
+    }
+
+    fn name_selector(&mut self, ui: &mut Ui) {
+        ui.checkbox(&mut self.cfg.simplify_names, "Simplify names")
+            .on_hover_text(
+                "Strip template arguments, argument lists and hashes from function names",
+            );
+        ui.separator();
+    }
+
+    fn forward_status(&mut self, ui: &mut Ui) {
+        let Some(stats) = self.cfg.collector.forward_stats() else {
+            return;
+        };
+
+        let forwarded = stats.forwarded.load(Relaxed);
+        let failed = stats.failed.load(Relaxed);
+        let dropped = stats.dropped.load(Relaxed);
+
+        let text = RichText::new(format!("Forwarded: {}", humanize_count(forwarded)));
+        let text = if stats.num_lost() > 0 {
+            text.color(Color32::ORANGE)
+        } else {
+            text
+        };
+
+        let mut tooltip = format!(
+            "Forwarding to {}\n\nForwarded: {forwarded}\nFailed: {failed}\nDropped: {dropped}",
+            stats.endpoint,
+        );
+        if let Some((time, ref err)) = *stats.last_error.lock().unwrap() {
+            tooltip += &format!("\n\nLast error ({time}):\n{err}");
+        }
+
+        ui.label(text).on_hover_text(tooltip);
+        ui.separator();
+    }
 }
@@ -43,13 +43,21 @@ struct CachedInner<V> {
 
 /// Cached, background computed value.
 ///
-/// Created via [`Cached::default`].
+/// Created via [`Cached::default`] or [`Cached::with_max_lifetime`].
 pub struct Cached<V: Default>(Arc<CachedInner<V>>);
 
 impl<V: Default> Default for Cached<V> {
     fn default() -> Self {
+        Self::with_max_lifetime(Duration::try_seconds(1).unwrap())
+    }
+}
+
+impl<V: Default> Cached<V> {
+    /// Create a cache that recomputes its value after the given time, even
+    /// if the key didn't change.
+    pub fn with_max_lifetime(max_lifetime: Duration) -> Self {
         Self(Arc::new(CachedInner {
-            max_lifetime: Duration::try_seconds(1).unwrap(),
+            max_lifetime,
             being_constructed: AtomicBool::new(false),
             value: ArcSwap::new(Arc::new(CachedValue {
                 created: DateTime::UNIX_EPOCH,
@@ -58,9 +66,7 @@ impl<V: Default> Default for Cached<V> {
             })),
         }))
     }
-}
 
-impl<V: Default> Cached<V> {
     /// Get the cached value or initiate computing it in the background.
     ///
     /// The value passed as `key` is hashed and used to determine whether the
@@ -122,6 +128,19 @@ impl<V: Default> Cached<V> {
 /// Access to the cached value.
 pub struct CachedValueRef<V>(Arc<CachedValue<V>>);
 
+impl<V> CachedValueRef<V> {
+    /// Whether both refer to the same computed value.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<V> Clone for CachedValueRef<V> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
 impl<V> Deref for CachedValueRef<V> {
     type Target = V;
 
@@ -0,0 +1,159 @@
+// Copyright Elasticsearch B.V. and/or licensed to Elasticsearch B.V. under one
+// or more contributor license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Opening source locations in an external editor or IDE.
+
+use crate::ui::pathmap::PathMap;
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+use std::process::Command;
+
+/// Predefined editor command templates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditorPreset {
+    VsCode,
+    JetBrains,
+    Env,
+}
+
+impl EditorPreset {
+    pub const ALL: [EditorPreset; 3] = [Self::VsCode, Self::JetBrains, Self::Env];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            EditorPreset::VsCode => "VS Code",
+            EditorPreset::JetBrains => "JetBrains IDE",
+            EditorPreset::Env => "$EDITOR",
+        }
+    }
+
+    pub fn template(self) -> &'static str {
+        match self {
+            EditorPreset::VsCode => "code --goto {file}:{line}",
+            EditorPreset::JetBrains => "idea --line {line} {file}",
+            EditorPreset::Env => "$EDITOR +{line} {file}",
+        }
+    }
+
+    /// Preset matching the given template, if any.
+    pub fn from_template(template: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|x| x.template() == template)
+    }
+}
+
+impl Default for EditorPreset {
+    fn default() -> Self {
+        if std::env::var_os("EDITOR").is_some() {
+            EditorPreset::Env
+        } else {
+            EditorPreset::VsCode
+        }
+    }
+}
+
+/// Expand a command template into the program and its arguments.
+///
+/// The template is split on whitespace. `{file}` and `{line}` are substituted
+/// in every word and a leading `$EDITOR` or `$VISUAL` is replaced with the
+/// value of the environment variable.
+fn expand_template(
+    template: &str,
+    file: &Path,
+    line: Option<u32>,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<Vec<String>> {
+    let file = file.to_string_lossy();
+    let line = line.unwrap_or(1).to_string();
+    let mut words = template.split_whitespace();
+
+    let mut args: Vec<String> = match words.next() {
+        Some(var @ ("$EDITOR" | "$VISUAL")) => {
+            let value = env(&var[1..]).unwrap_or_default();
+            let args: Vec<_> = value.split_whitespace().map(str::to_owned).collect();
+            if args.is_empty() {
+                bail!("{var} is not set");
+            }
+            args
+        }
+        Some(program) => vec![program.to_owned()],
+        None => bail!("no editor command configured"),
+    };
+
+    args.extend(words.map(|x| x.replace("{file}", &file).replace("{line}", &line)));
+
+    Ok(args)
+}
+
+/// Open the given source location in the editor described by `template`.
+///
+/// The file is located on the local system via the path remapping rules.
+pub fn open_in_editor(
+    template: &str,
+    path_map: &PathMap,
+    file: &str,
+    line: Option<u32>,
+) -> Result<()> {
+    let Some(local) = path_map.resolve(file) else {
+        bail!("{file} not found locally: configure path remapping in the settings");
+    };
+
+    let args = expand_template(template, &local, line, |x| std::env::var(x).ok())?;
+    tracing::info!("Opening {} with {:?}", local.display(), args);
+
+    let mut child = Command::new(&args[0])
+        .args(&args[1..])
+        .spawn()
+        .with_context(|| format!("failed to run {:?}", args[0]))?;
+
+    // Reap the process once the editor (or its launcher) exits.
+    std::thread::spawn(move || child.wait());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand() {
+        let file = Path::new("/src/main.rs");
+        let env = |var: &str| (var == "EDITOR").then(|| "vim -p".to_owned());
+
+        let cases = [
+            (
+                EditorPreset::VsCode,
+                vec!["code", "--goto", "/src/main.rs:42"],
+            ),
+            (
+                EditorPreset::JetBrains,
+                vec!["idea", "--line", "42", "/src/main.rs"],
+            ),
+            (EditorPreset::Env, vec!["vim", "-p", "+42", "/src/main.rs"]),
+        ];
+        for (preset, expected) in cases {
+            let args = expand_template(preset.template(), file, Some(42), env).unwrap();
+            assert_eq!(args, expected);
+        }
+
+        let args = expand_template("subl {file}:{line}", file, None, env).unwrap();
+        assert_eq!(args, ["subl", "/src/main.rs:1"]);
+
+        assert!(expand_template("", file, None, env).is_err());
+        assert!(expand_template("$VISUAL {file}", file, None, env).is_err());
+    }
+}
@@ -15,15 +15,24 @@
 // specific language governing permissions and limitations
 // under the License.
 
+mod aggregate;
 mod app;
 mod cached;
+mod editor;
+mod pathmap;
 mod tabs;
 mod timeaxis;
 mod util;
 
+pub use pathmap::PathMap;
+
 static ICON_BYTES: &[u8] = include_bytes!("../../assets/icon.png");
 
-pub fn gui_thread(collector: crate::collector::Collector) -> Result<(), eframe::Error> {
+pub fn gui_thread(
+    collector: crate::collector::Collector,
+    path_map: PathMap,
+    editor: Option<String>,
+) -> Result<(), eframe::Error> {
     let icon = eframe::icon_data::from_png_bytes(ICON_BYTES);
     let icon = icon.expect("corrupted icon");
 
@@ -55,7 +64,7 @@ pub fn gui_thread(collector: crate::collector::Collector) -> Result<(), eframe::
             egui_extras::install_image_loaders(&cc.egui_ctx);
             load_phosphor_icons(&cc.egui_ctx);
             tokio::spawn(background_ui_waker(cc.egui_ctx.clone()));
-            Ok(Box::new(app::DevfilerUi::new(collector)))
+            Ok(Box::new(app::DevfilerUi::new(collector, path_map, editor)))
         }),
     )
 }
@@ -0,0 +1,149 @@
+// Copyright Elasticsearch B.V. and/or licensed to Elasticsearch B.V. under one
+// or more contributor license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Remapping of source file paths recorded at build time to local checkouts.
+
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+
+/// Single prefix replacement rule.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct PathMapRule {
+    /// Path prefix as recorded in the symbols, e.g. `/build/src`.
+    pub from: String,
+
+    /// Local directory to substitute for the prefix.
+    pub to: String,
+}
+
+impl PathMapRule {
+    /// Parse a rule in `from=to` notation.
+    pub fn parse(rule: &str) -> Result<Self> {
+        let (from, to) = rule
+            .split_once('=')
+            .with_context(|| format!("expected `from=to` path mapping, got {rule:?}"))?;
+
+        Ok(PathMapRule {
+            from: from.trim().to_owned(),
+            to: to.trim().to_owned(),
+        })
+    }
+
+    /// Apply the rule to the given path, if its prefix matches.
+    fn apply(&self, path: &str) -> Option<PathBuf> {
+        let from = self.from.trim_end_matches('/');
+        if from.is_empty() {
+            return None;
+        }
+
+        let rest = path.strip_prefix(from)?;
+        if !rest.is_empty() && !rest.starts_with('/') {
+            // Only match on whole path components.
+            return None;
+        }
+
+        let to = self.to.trim_end_matches('/');
+        Some(PathBuf::from(format!("{to}{rest}")))
+    }
+}
+
+/// Ordered list of path remapping rules.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct PathMap {
+    pub rules: Vec<PathMapRule>,
+}
+
+impl PathMap {
+    /// Parse the rules from `from=to` command line arguments.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let rules = args
+            .into_iter()
+            .map(|x| PathMapRule::parse(&x))
+            .collect::<Result<_>>()?;
+
+        Ok(PathMap { rules })
+    }
+
+    /// Local paths to look for the given file at, in order of preference.
+    pub fn candidates(&self, path: &str) -> Vec<PathBuf> {
+        let mut candidates: Vec<_> = self.rules.iter().filter_map(|x| x.apply(path)).collect();
+        candidates.push(PathBuf::from(path));
+        candidates
+    }
+
+    /// Find the first candidate that exists on the local system.
+    pub fn resolve(&self, path: &str) -> Option<PathBuf> {
+        self.candidates(path).into_iter().find(|x| x.is_file())
+    }
+
+    /// Locate and read the given source file, returning its local path and
+    /// lines with tabs expanded.
+    pub fn load_source(&self, path: &str) -> Result<(PathBuf, Vec<String>), String> {
+        let Some(local) = self.resolve(path) else {
+            let tried: Vec<_> = self
+                .candidates(path)
+                .iter()
+                .map(|x| x.display().to_string())
+                .collect();
+            return Err(format!(
+                "File not found locally. Tried: {}",
+                tried.join(", ")
+            ));
+        };
+
+        let data = std::fs::read(&local)
+            .map_err(|e| format!("Failed to read {}: {e}", local.display()))?;
+        let text = String::from_utf8_lossy(&data);
+        let lines = text.lines().map(|x| x.replace('\t', "    ")).collect();
+
+        Ok((local, lines))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn candidates() {
+        let map = PathMap::from_args([
+            "/build/src/=/home/me/project".to_owned(),
+            "/rustc/abc = /opt/rust".to_owned(),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            map.candidates("/build/src/main.c"),
+            vec![
+                PathBuf::from("/home/me/project/main.c"),
+                PathBuf::from("/build/src/main.c"),
+            ]
+        );
+        assert_eq!(
+            map.candidates("/rustc/abc/library/core/src/fmt/mod.rs")[0],
+            PathBuf::from("/opt/rust/library/core/src/fmt/mod.rs"),
+        );
+
+        // Prefixes only match whole components.
+        assert_eq!(
+            map.candidates("/build/srcfoo/x.c"),
+            vec![PathBuf::from("/build/srcfoo/x.c")]
+        );
+
+        assert!(PathMap::from_args(["no-separator".to_owned()]).is_err());
+    }
+}
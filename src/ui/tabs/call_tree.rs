@@ -0,0 +1,587 @@
+// Copyright Elasticsearch B.V. and/or licensed to Elasticsearch B.V. under one
+// or more contributor license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Hierarchical table view of the aggregated call stacks.
+
+use super::flamegraph::{build_flame_graph, FlameGraphNode, FlameGraphOptions, GroupBy};
+use super::top_funcs::{draw_count_column, draw_percent_column};
+use super::*;
+use crate::ui::aggregate::hash_key;
+use crate::ui::cached::{Cached, CachedValueRef};
+use crate::ui::util::clearable_line_edit;
+use egui::{Align, Button, Key, Layout, RichText, Sense, Stroke, StrokeKind};
+use egui_extras::{Column, TableBuilder};
+use egui_phosphor::regular as icons;
+use std::collections::HashSet;
+
+/// Indentation per tree level.
+const INDENT: f32 = 14.0;
+
+/// Path of the (invisible) root node.
+const ROOT_PATH: u64 = 0;
+
+pub struct CallTreeTab {
+    cached_root: Cached<FlameGraphNode>,
+    show_inline: bool,
+    inverted: bool,
+    sort_field: SortField,
+    filter: String,
+    /// Paths of the expanded nodes, see [`child_path`].
+    expanded: HashSet<u64>,
+    /// Path of the selected node.
+    selected: Option<u64>,
+    /// Scroll the selected row into view on the next frame.
+    scroll_to_selected: bool,
+    /// Paths of the nodes with a subtree matching the filter.
+    search: Option<SearchResult>,
+    /// Rows shown for the current tree, expansion, filter and sorting.
+    rows: Option<RowCache>,
+}
+
+impl Default for CallTreeTab {
+    fn default() -> Self {
+        Self {
+            cached_root: Default::default(),
+            show_inline: true,
+            inverted: false,
+            sort_field: SortField::default(),
+            filter: String::new(),
+            expanded: HashSet::new(),
+            selected: None,
+            scroll_to_selected: false,
+            search: None,
+            rows: None,
+        }
+    }
+}
+
+impl TabWidget for CallTreeTab {
+    fn id(&self) -> Tab {
+        Tab::CallTree
+    }
+
+    fn update(
+        &mut self,
+        ui: &mut Ui,
+        cfg: &DevfilerConfig,
+        kind: SampleKind,
+        start: UtcTimestamp,
+        end: UtcTimestamp,
+    ) -> Option<TabAction> {
+        let opts = FlameGraphOptions {
+            inline_frames: self.show_inline,
+            simplify_names: cfg.simplify_names,
+            granularity: cfg.granularity,
+            group_by: GroupBy::None,
+            inverted: self.inverted,
+        };
+        let root = self
+            .cached_root
+            .get_or_create((kind, start, end, opts), move || {
+                build_flame_graph(kind, start, end, opts, vec![])
+            });
+
+        let mut expand_hot_path = false;
+        ui.add_space(5.0);
+        ui.columns(2, |ui| {
+            ui[0].with_layout(Layout::left_to_right(Align::Min), |ui| {
+                ui.checkbox(&mut self.show_inline, "Show inline");
+                ui.checkbox(&mut self.inverted, "Inverted")
+                    .on_hover_text("Show leaf functions as roots, with their callers as children");
+                expand_hot_path = ui
+                    .button(format!("{} Expand hot path", icons::FIRE))
+                    .on_hover_text("Expand the heaviest children, starting at the selected row")
+                    .clicked();
+                if ui.button("Collapse all").clicked() {
+                    self.expanded.clear();
+                }
+            });
+            ui[1].with_layout(Layout::right_to_left(Align::Min), |ui| {
+                let hint = format!("{} Search ...", icons::MAGNIFYING_GLASS);
+                clearable_line_edit(ui, &hint, &mut self.filter);
+            });
+        });
+        ui.separator();
+
+        self.update_search(&root);
+        let rows = self.visible_rows(&root);
+
+        // Keyboard navigation, unless e.g. the search field has focus.
+        let selected_idx = self
+            .selected
+            .and_then(|path| rows.iter().position(|x| x.path == path));
+        if ui.memory(|x| x.focused().is_none()) {
+            self.handle_keys(ui, &rows, selected_idx);
+        }
+
+        if expand_hot_path {
+            let start = selected_idx.map_or((&*root, ROOT_PATH), |i| (rows[i].node, rows[i].path));
+            self.expand_hot_path(start.0, start.1);
+        }
+
+        // Expansion may have changed above.
+        let rows = self.visible_rows(&root);
+        self.draw_table(ui, &root, &rows)
+    }
+}
+
+impl CallTreeTab {
+    /// Recompute the nodes matching the filter if it or the tree changed.
+    fn update_search(&mut self, root: &CachedValueRef<FlameGraphNode>) {
+        if self.filter.is_empty() {
+            self.search = None;
+            return;
+        }
+
+        if let Some(ref search) = self.search {
+            if search.filter == self.filter && search.root.ptr_eq(root) {
+                return;
+            }
+        }
+
+        let mut paths = HashSet::new();
+        for child in &root.children {
+            collect_matches(
+                child,
+                child_path(ROOT_PATH, child),
+                &self.filter,
+                &mut paths,
+            );
+        }
+
+        self.search = Some(SearchResult {
+            filter: self.filter.clone(),
+            root: root.clone(),
+            paths,
+        });
+    }
+
+    /// Flatten the expanded part of the tree into table rows.
+    ///
+    /// While searching, only nodes with matches in their subtree are shown
+    /// and all of them are expanded. The row layout is cached until the
+    /// tree, the expanded nodes, the filter or the sorting change.
+    fn visible_rows<'a>(&mut self, root: &'a CachedValueRef<FlameGraphNode>) -> Vec<Row<'a>> {
+        let stale = self.rows.as_ref().is_none_or(|x| {
+            !x.root.ptr_eq(root)
+                || x.expanded != self.expanded
+                || x.filter != self.filter
+                || x.sort_field != self.sort_field
+        });
+        if stale {
+            let matches = self.search.as_ref().map(|x| &x.paths);
+            let mut layout = Vec::new();
+            self.push_children(&mut layout, root, ROOT_PATH, None, 0, matches);
+            self.rows = Some(RowCache {
+                root: root.clone(),
+                expanded: self.expanded.clone(),
+                filter: self.filter.clone(),
+                sort_field: self.sort_field,
+                layout,
+            });
+        }
+
+        // Parents always precede their children, so their nodes are known.
+        let layout = &self.rows.as_ref().unwrap().layout;
+        let mut rows: Vec<Row<'a>> = Vec::with_capacity(layout.len());
+        for row in layout {
+            let parent = row.parent.map_or(&**root, |x| rows[x].node);
+            rows.push(Row {
+                node: &parent.children[row.child],
+                path: row.path,
+                parent: row.parent,
+                depth: row.depth,
+                expanded: row.expanded,
+            });
+        }
+        rows
+    }
+
+    fn push_children(
+        &self,
+        rows: &mut Vec<RowLayout>,
+        node: &FlameGraphNode,
+        path: u64,
+        parent: Option<usize>,
+        depth: usize,
+        matches: Option<&HashSet<u64>>,
+    ) {
+        let mut children: Vec<_> = node
+            .children
+            .iter()
+            .enumerate()
+            .map(|(i, child)| (child, child_path(path, child), i))
+            .filter(|(_, path, _)| matches.is_none_or(|x| x.contains(path)))
+            .collect();
+        self.sort_field.sort(&mut children);
+
+        for (child, child_path, child_idx) in children {
+            let idx = rows.len();
+            rows.push(RowLayout {
+                child: child_idx,
+                path: child_path,
+                parent,
+                depth,
+                expanded: false,
+            });
+
+            if self.expanded.contains(&child_path) || matches.is_some() {
+                self.push_children(rows, child, child_path, Some(idx), depth + 1, matches);
+                rows[idx].expanded = rows.len() > idx + 1;
+            }
+        }
+    }
+
+    /// Expand nodes along the heaviest children and select the last one.
+    fn expand_hot_path(&mut self, mut node: &FlameGraphNode, mut path: u64) {
+        while let Some(child) = node.children.iter().max_by_key(|x| x.weight) {
+            if path != ROOT_PATH {
+                self.expanded.insert(path);
+            }
+            path = child_path(path, child);
+            node = child;
+        }
+
+        if path != ROOT_PATH {
+            self.selected = Some(path);
+            self.scroll_to_selected = true;
+        }
+    }
+
+    /// Move the selection and expand or collapse rows with the arrow keys.
+    fn handle_keys(&mut self, ui: &Ui, rows: &[Row], selected_idx: Option<usize>) {
+        if rows.is_empty() {
+            return;
+        }
+
+        let pressed = |key| ui.input(|x| x.key_pressed(key));
+        let Some(idx) = selected_idx else {
+            if pressed(Key::ArrowDown) || pressed(Key::ArrowUp) {
+                self.select(rows[0].path);
+            }
+            return;
+        };
+
+        let row = &rows[idx];
+        if pressed(Key::ArrowDown) && idx + 1 < rows.len() {
+            self.select(rows[idx + 1].path);
+        } else if pressed(Key::ArrowUp) && idx > 0 {
+            self.select(rows[idx - 1].path);
+        } else if pressed(Key::ArrowRight) {
+            if row.expanded {
+                self.select(rows[idx + 1].path);
+            } else if !row.node.children.is_empty() {
+                self.expanded.insert(row.path);
+            }
+        } else if pressed(Key::ArrowLeft) {
+            if row.expanded && self.filter.is_empty() {
+                self.expanded.remove(&row.path);
+            } else if let Some(parent) = row.parent {
+                self.select(rows[parent].path);
+            }
+        } else if pressed(Key::Enter) {
+            self.toggle(row);
+        }
+    }
+
+    fn select(&mut self, path: u64) {
+        self.selected = Some(path);
+        self.scroll_to_selected = true;
+    }
+
+    fn toggle(&mut self, row: &Row) {
+        if !self.expanded.remove(&row.path) && !row.node.children.is_empty() {
+            self.expanded.insert(row.path);
+        }
+    }
+
+    fn draw_table(
+        &mut self,
+        ui: &mut Ui,
+        root: &FlameGraphNode,
+        rows: &[Row],
+    ) -> Option<TabAction> {
+        let mut action = None;
+        let mut table = TableBuilder::new(ui)
+            .striped(true)
+            .resizable(true)
+            .sense(Sense::click())
+            .cell_layout(Layout::left_to_right(Align::Center))
+            .column(Column::exact(85.0))
+            .column(Column::exact(85.0))
+            .column(Column::exact(85.0))
+            .column(Column::exact(85.0))
+            .column(Column::remainder().clip(true))
+            .max_scroll_height(f32::INFINITY);
+
+        let selected_idx = self
+            .selected
+            .and_then(|path| rows.iter().position(|x| x.path == path));
+        if let Some(idx) = selected_idx.filter(|_| self.scroll_to_selected) {
+            table = table.scroll_to_row(idx, None);
+        }
+        self.scroll_to_selected = false;
+
+        let total = root.weight.max(1) as f32;
+        table
+            .header(20.0, |mut header| {
+                for (field, text) in [
+                    (SortField::Total, "Total"),
+                    (SortField::Zelf, "Self"),
+                    (SortField::Total, "Total"),
+                    (SortField::Zelf, "Self"),
+                    (SortField::Name, "Function"),
+                ] {
+                    header.col(|ui| {
+                        ui.selectable_value(&mut self.sort_field, field, text);
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(20.0, rows.len(), |mut row| {
+                    let item = &rows[row.index()];
+                    let node = item.node;
+                    row.set_selected(selected_idx == Some(row.index()));
+
+                    row.col(|ui| draw_percent_column(ui, node.weight as f32 / total));
+                    row.col(|ui| draw_percent_column(ui, node.weight_self() as f32 / total));
+                    row.col(|ui| draw_count_column(ui, node.weight));
+                    row.col(|ui| draw_count_column(ui, node.weight_self()));
+
+                    let mut toggled = false;
+                    row.col(|ui| {
+                        ui.add_space(3.0 + item.depth as f32 * INDENT);
+                        if node.children.is_empty() {
+                            ui.add_space(INDENT + ui.spacing().item_spacing.x);
+                        } else {
+                            let icon = match item.expanded {
+                                true => icons::CARET_DOWN,
+                                false => icons::CARET_RIGHT,
+                            };
+                            toggled = ui.add(Button::new(icon).small().frame(false)).clicked();
+                        }
+                        draw_node_square(ui, node);
+
+                        let text = RichText::new(&node.text);
+                        let is_match = !self.filter.is_empty() && node.text.contains(&self.filter);
+                        ui.label(if is_match { text.strong() } else { text });
+                    });
+
+                    let response = row.response();
+                    if toggled || response.double_clicked() {
+                        self.toggle(item);
+                    }
+                    if toggled || response.clicked() {
+                        self.selected = Some(item.path);
+                    }
+                    response.context_menu(|ui| {
+                        if let Some(chosen) = draw_context_menu(ui, node) {
+                            action = Some(chosen);
+                            ui.close();
+                        }
+                    });
+                });
+            });
+
+        action
+    }
+}
+
+/// Populates the context menu of a call tree row.
+fn draw_context_menu(ui: &mut Ui, node: &FlameGraphNode) -> Option<TabAction> {
+    let source = node.source.as_ref();
+    let show_source = ui.add_enabled(source.is_some(), Button::new("Show source"));
+    if show_source.clicked() {
+        return Some(TabAction::ShowSource {
+            file: source?.file.clone(),
+            line: source?.line,
+        });
+    }
+
+    let open = ui.add_enabled(source.is_some(), Button::new("Open in editor"));
+    if open.clicked() {
+        return Some(TabAction::OpenInEditor {
+            file: source?.file.clone(),
+            line: source?.line,
+        });
+    }
+
+    None
+}
+
+/// Draw a little square in the color of the node.
+fn draw_node_square(ui: &mut Ui, node: &FlameGraphNode) {
+    let stroke_color = node.bg_color.gamma_multiply(0.5).to_opaque();
+    let (rect, _) = ui.allocate_exact_size([10.0, 10.0].into(), Sense::hover());
+    ui.painter_at(rect).rect(
+        rect,
+        0.0,
+        node.bg_color,
+        Stroke::new(1.0, stroke_color),
+        StrokeKind::Middle,
+    );
+}
+
+/// Identifies a node by the keys of the nodes on its path from the root.
+fn child_path(parent_path: u64, child: &FlameGraphNode) -> u64 {
+    hash_key((parent_path, child.key))
+}
+
+/// Collect the paths of all nodes whose subtree contains a node whose text
+/// contains `filter`. Returns whether the given node's subtree matched.
+fn collect_matches(node: &FlameGraphNode, path: u64, filter: &str, out: &mut HashSet<u64>) -> bool {
+    let mut matched = node.text.contains(filter);
+    for child in &node.children {
+        matched |= collect_matches(child, child_path(path, child), filter, out);
+    }
+    if matched {
+        out.insert(path);
+    }
+    matched
+}
+
+/// Visible row of the call tree.
+struct Row<'a> {
+    node: &'a FlameGraphNode,
+    path: u64,
+    /// Index of the parent's row, if the node isn't a top-level node.
+    parent: Option<usize>,
+    depth: usize,
+    /// Whether the rows of the node's children follow.
+    expanded: bool,
+}
+
+/// Position of a visible row in the tree, see [`Row`].
+struct RowLayout {
+    /// Index of the node in its parent's children.
+    child: usize,
+    path: u64,
+    parent: Option<usize>,
+    depth: usize,
+    expanded: bool,
+}
+
+/// Row layout computed for a particular tree and view state.
+struct RowCache {
+    root: CachedValueRef<FlameGraphNode>,
+    expanded: HashSet<u64>,
+    filter: String,
+    sort_field: SortField,
+    layout: Vec<RowLayout>,
+}
+
+/// Nodes matching the search filter in a particular tree.
+struct SearchResult {
+    filter: String,
+    root: CachedValueRef<FlameGraphNode>,
+    paths: HashSet<u64>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum SortField {
+    #[default]
+    Total,
+    Zelf,
+    Name,
+}
+
+impl SortField {
+    /// Sort sibling nodes, heaviest (or alphabetically first) first.
+    fn sort(self, nodes: &mut [(&FlameGraphNode, u64, usize)]) {
+        match self {
+            SortField::Total => nodes.sort_by_key(|(x, ..)| std::cmp::Reverse(x.weight)),
+            SortField::Zelf => nodes.sort_by_key(|(x, ..)| std::cmp::Reverse(x.weight_self())),
+            SortField::Name => nodes.sort_by(|(a, ..), (b, ..)| a.text.cmp(&b.text)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(key: u64, text: &str, weight: u64, children: Vec<FlameGraphNode>) -> FlameGraphNode {
+        let mut node = FlameGraphNode::new_meta_node(text.into(), key);
+        node.weight = weight;
+        node.children = children;
+        node
+    }
+
+    /// root -> main -> {parse -> read, render}
+    fn tree() -> FlameGraphNode {
+        node(
+            0,
+            "root",
+            10,
+            vec![node(
+                1,
+                "main",
+                10,
+                vec![
+                    node(2, "parse", 3, vec![node(3, "read", 3, vec![])]),
+                    node(4, "render", 6, vec![]),
+                ],
+            )],
+        )
+    }
+
+    /// Path of the node reached by following the given keys from the root.
+    fn path(keys: &[u64]) -> u64 {
+        keys.iter()
+            .fold(ROOT_PATH, |path, &key| hash_key((path, key)))
+    }
+
+    #[test]
+    fn matches() {
+        let root = tree();
+        let mut out = HashSet::new();
+        let main = &root.children[0];
+        assert!(collect_matches(main, path(&[1]), "read", &mut out));
+        assert_eq!(
+            out,
+            HashSet::from([path(&[1]), path(&[1, 2]), path(&[1, 2, 3])])
+        );
+
+        let mut out = HashSet::new();
+        assert!(!collect_matches(main, path(&[1]), "write", &mut out));
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn hot_path() {
+        let root = tree();
+        let mut tab = CallTreeTab::default();
+        tab.expand_hot_path(&root, ROOT_PATH);
+        assert_eq!(tab.expanded, HashSet::from([path(&[1])]));
+        assert_eq!(tab.selected, Some(path(&[1, 4])));
+        assert!(tab.scroll_to_selected);
+
+        // Starting at a row only expands below it.
+        let mut tab = CallTreeTab::default();
+        let parse = &root.children[0].children[0];
+        tab.expand_hot_path(parse, path(&[1, 2]));
+        assert_eq!(tab.expanded, HashSet::from([path(&[1, 2])]));
+        assert_eq!(tab.selected, Some(path(&[1, 2, 3])));
+
+        // Leaves have nothing to expand and stay selected.
+        let mut tab = CallTreeTab::default();
+        let read = &parse.children[0];
+        tab.expand_hot_path(read, path(&[1, 2, 3]));
+        assert!(tab.expanded.is_empty());
+        assert_eq!(tab.selected, Some(path(&[1, 2, 3])));
+    }
+}
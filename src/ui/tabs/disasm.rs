@@ -0,0 +1,453 @@
+// Copyright Elasticsearch B.V. and/or licensed to Elasticsearch B.V. under one
+// or more contributor license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use super::*;
+use crate::storage::*;
+use crate::symbolizer::{disassemble, Instruction};
+use crate::ui::cached::Cached;
+use crate::ui::pathmap::PathMap;
+use crate::ui::util::draw_heat_count;
+use egui::{Align, Color32, Layout, RichText};
+use egui_extras::{Column, TableBuilder};
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+const ROW_HEIGHT: f32 = 16.0;
+const CLR_SOURCE: Color32 = Color32::from_rgb(0x80, 0xA8, 0xD0);
+
+pub struct DisassemblyTab {
+    target: Option<FrameId>,
+    listing: Cached<Option<Listing>>,
+    counts: Cached<InsnCounts>,
+    path_input: String,
+    path_error: Option<String>,
+    scroll_pending: bool,
+
+    /// Bumped whenever the local path of an executable is changed, to rebuild
+    /// the listing.
+    generation: u64,
+}
+
+impl Default for DisassemblyTab {
+    fn default() -> Self {
+        DisassemblyTab {
+            target: None,
+            // Rebuilt when the target or path map changes, and occasionally
+            // to pick up symbols ingested in the meantime.
+            listing: Cached::with_max_lifetime(chrono::Duration::try_seconds(30).unwrap()),
+            counts: Cached::default(),
+            path_input: String::new(),
+            path_error: None,
+            scroll_pending: false,
+            generation: 0,
+        }
+    }
+}
+
+/// Disassembled function, interleaved with source lines.
+struct Listing {
+    target: FrameId,
+    result: Result<ListingData, ListingError>,
+}
+
+struct ListingData {
+    func: Option<String>,
+    range: Range<VirtAddr>,
+    path: PathBuf,
+    insns: Vec<Instruction>,
+    rows: Vec<Row>,
+}
+
+enum ListingError {
+    /// No local copy of the executable is known.
+    NotLocal,
+    Failed(String),
+}
+
+enum Row {
+    Source {
+        file: String,
+        line: u32,
+        text: Option<String>,
+    },
+    Insn(usize),
+}
+
+impl Listing {
+    fn build(target: FrameId, path_map: &PathMap) -> Self {
+        Listing {
+            target,
+            result: build_listing(target, path_map),
+        }
+    }
+}
+
+fn build_listing(target: FrameId, path_map: &PathMap) -> Result<ListingData, ListingError> {
+    let file_id = target.file_id;
+
+    let Some((range, func)) = function_range(file_id, target.addr_or_line) else {
+        return Err(ListingError::Failed(
+            "No symbols for this address: ingest symbols for the executable first.".into(),
+        ));
+    };
+
+    let path = DB
+        .executables
+        .get(file_id)
+        .and_then(|x| x.read().local_path)
+        .map(PathBuf::from)
+        .filter(|x| x.is_file())
+        .ok_or(ListingError::NotLocal)?;
+
+    let insns =
+        disassemble(&path, range.clone()).map_err(|e| ListingError::Failed(format!("{e:#}")))?;
+
+    // Insert a source line whenever the leaf location changes.
+    let mut sources = HashMap::new();
+    let mut rows = Vec::with_capacity(insns.len() * 2);
+    let mut prev = None;
+    for (idx, insn) in insns.iter().enumerate() {
+        let frame = Frame {
+            id: FrameId {
+                file_id,
+                addr_or_line: insn.addr,
+            },
+            kind: FrameKind::Regular(InterpKind::Native),
+        };
+        let loc = symbolize_frame(frame, true)
+            .pop()
+            .and_then(|x| Some((x.file?, x.line_no?)));
+
+        if loc.is_some() && loc != prev {
+            let (file, line) = loc.clone().unwrap();
+            let lines = sources
+                .entry(file.clone())
+                .or_insert_with(|| path_map.load_source(&file).ok().map(|(_, lines)| lines));
+            let text = lines
+                .as_ref()
+                .zip(line.checked_sub(1))
+                .and_then(|(lines, idx)| lines.get(idx as usize).cloned());
+            rows.push(Row::Source { file, line, text });
+        }
+
+        prev = loc;
+        rows.push(Row::Insn(idx));
+    }
+
+    Ok(ListingData {
+        func,
+        range,
+        path,
+        insns,
+        rows,
+    })
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Counts {
+    zelf: u64,
+    total: u64,
+}
+
+/// Sample counts per address within a function.
+#[derive(Debug, Default)]
+struct InsnCounts {
+    file_id: Option<FileId>,
+    total_samples: u64,
+    addrs: HashMap<VirtAddr, Counts>,
+}
+
+impl TabWidget for DisassemblyTab {
+    fn id(&self) -> Tab {
+        Tab::Disassembly
+    }
+
+    fn update(
+        &mut self,
+        ui: &mut Ui,
+        cfg: &DevfilerConfig,
+        kind: SampleKind,
+        start: UtcTimestamp,
+        end: UtcTimestamp,
+    ) -> Option<TabAction> {
+        let Some(target) = self.target else {
+            ui.label("Use \"Show disassembly\" on a native frame in the flamegraph.");
+            return None;
+        };
+
+        // Disassembling and symbolizing every instruction takes a while: build
+        // the listing in the background.
+        let path_map = cfg.path_map.clone();
+        let listing = self
+            .listing
+            .get_or_create((target, &cfg.path_map, self.generation), move || {
+                Some(Listing::build(target, &path_map))
+            });
+        let Some(listing) = listing.as_ref().filter(|x| x.target == target) else {
+            ui.label("Disassembling ...");
+            return None;
+        };
+
+        ui.add_space(5.0);
+        if matches!(listing.result, Err(ListingError::NotLocal)) {
+            self.draw_path_selector(ui, target.file_id);
+            return None;
+        }
+
+        let listing = match listing.result {
+            Ok(ref listing) => listing,
+            Err(ListingError::NotLocal) => return None,
+            Err(ListingError::Failed(ref e)) => {
+                ui.colored_label(Color32::ORANGE, e);
+                return None;
+            }
+        };
+
+        let file_id = target.file_id;
+        let range = listing.range.clone();
+        let counts = self
+            .counts
+            .get_or_create((file_id, range.start, kind, start, end), move || {
+                query_insn_counts(file_id, range, kind, start, end)
+            });
+        let counts_ready = counts.file_id == Some(file_id);
+
+        // Attribute the sampled addresses to the instructions containing them.
+        let mut insn_counts = vec![Counts::default(); listing.insns.len()];
+        if counts_ready {
+            for (&addr, addr_counts) in &counts.addrs {
+                let idx = listing.insns.partition_point(|x| x.addr <= addr);
+                if let Some(idx) = idx.checked_sub(1) {
+                    insn_counts[idx].zelf += addr_counts.zelf;
+                    insn_counts[idx].total += addr_counts.total;
+                }
+            }
+        }
+        let max_total = insn_counts.iter().map(|x| x.total).max().unwrap_or(0);
+        let total_samples = counts.total_samples;
+
+        ui.horizontal(|ui| {
+            ui.strong("Function:");
+            ui.monospace(listing.func.as_deref().unwrap_or("<unknown>"));
+            ui.label(format!(
+                "({:#x}..{:#x}, {} instructions)",
+                listing.range.start,
+                listing.range.end,
+                listing.insns.len()
+            ));
+        });
+        ui.horizontal(|ui| {
+            ui.strong("Executable:");
+            ui.monospace(listing.path.display().to_string());
+        });
+        if !counts_ready {
+            ui.label("Loading sample counts ...");
+        }
+        ui.separator();
+
+        let target_idx = listing
+            .insns
+            .iter()
+            .position(|x| x.range().contains(&target.addr_or_line));
+
+        let mut table = TableBuilder::new(ui)
+            .striped(true)
+            .resizable(true)
+            .cell_layout(Layout::left_to_right(Align::Center))
+            .column(Column::exact(70.0))
+            .column(Column::exact(70.0))
+            .column(Column::initial(140.0))
+            .column(Column::remainder().clip(true))
+            .max_scroll_height(f32::INFINITY);
+
+        if self.scroll_pending {
+            let row = listing
+                .rows
+                .iter()
+                .position(|x| matches!(x, Row::Insn(idx) if Some(*idx) == target_idx));
+            if let Some(row) = row {
+                table = table.scroll_to_row(row, Some(Align::Center));
+            }
+            self.scroll_pending = false;
+        }
+
+        table
+            .header(20.0, |mut header| {
+                for col in ["Self", "Total", "Address", "Instruction"] {
+                    header.col(|ui| {
+                        ui.strong(col);
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(ROW_HEIGHT, listing.rows.len(), |mut row| {
+                    match listing.rows[row.index()] {
+                        Row::Source {
+                            ref file,
+                            line,
+                            ref text,
+                        } => {
+                            row.col(|_| ());
+                            row.col(|_| ());
+                            row.col(|ui| {
+                                ui.label(RichText::new(format!("line {line}")).weak())
+                                    .on_hover_text(format!("{file}:{line}"));
+                            });
+                            row.col(|ui| {
+                                let text = match text {
+                                    Some(text) => text.clone(),
+                                    None => format!("{file}:{line}"),
+                                };
+                                ui.label(RichText::new(text).monospace().color(CLR_SOURCE));
+                            });
+                        }
+                        Row::Insn(idx) => {
+                            let insn = &listing.insns[idx];
+                            let Counts { zelf, total } = insn_counts[idx];
+                            for count in [zelf, total] {
+                                row.col(|ui| draw_heat_count(ui, count, max_total, total_samples));
+                            }
+
+                            let is_target = Some(idx) == target_idx;
+                            row.col(|ui| {
+                                let text = RichText::new(format!("{:#x}", insn.addr)).monospace();
+                                ui.label(if is_target { text.strong() } else { text });
+                            });
+                            row.col(|ui| {
+                                let text = RichText::new(&insn.text).monospace();
+                                ui.label(if is_target { text.strong() } else { text });
+                            });
+                        }
+                    }
+                });
+            });
+
+        None
+    }
+
+    fn handle_action(&mut self, action: &TabAction) {
+        if let TabAction::ShowDisassembly { frame } = action {
+            self.target = Some(*frame);
+            self.path_error = None;
+            self.scroll_pending = true;
+        }
+    }
+
+    fn show_tab_selector(&self, _cfg: &DevfilerConfig) -> bool {
+        self.target.is_some()
+    }
+}
+
+impl DisassemblyTab {
+    /// Let the user pick the local copy of an executable.
+    fn draw_path_selector(&mut self, ui: &mut Ui, file_id: FileId) {
+        let name = DB
+            .executables
+            .get(file_id)
+            .and_then(|x| x.read().file_name)
+            .unwrap_or_else(|| file_id.format_hex());
+
+        ui.label(format!(
+            "No local copy of {name} is known. Enter the path of the executable to disassemble it:"
+        ));
+
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.path_input);
+            if ui.button("Use file").clicked() {
+                match set_local_path(file_id, self.path_input.trim()) {
+                    Ok(()) => {
+                        self.generation += 1;
+                        self.path_error = None;
+                    }
+                    Err(e) => self.path_error = Some(e),
+                }
+            }
+        });
+
+        if let Some(ref e) = self.path_error {
+            ui.colored_label(Color32::ORANGE, e);
+        }
+    }
+}
+
+/// Remember the local copy of an executable after verifying its file ID.
+fn set_local_path(file_id: FileId, path: &str) -> Result<(), String> {
+    let actual = FileId::from_path(Path::new(path)).map_err(|e| format!("{path}: {e:#}"))?;
+    if actual != file_id {
+        return Err(format!(
+            "{path} has file ID {}, expected {}",
+            actual.format_hex(),
+            file_id.format_hex()
+        ));
+    }
+
+    let Some(meta) = DB.executables.get(file_id) else {
+        return Err("executable no longer exists".into());
+    };
+    let mut meta = meta.read();
+    meta.local_path = Some(path.to_owned());
+    DB.executables.insert(file_id, meta);
+
+    Ok(())
+}
+
+/// Count the samples attributed to each address in the given function.
+fn query_insn_counts(
+    file_id: FileId,
+    range: Range<VirtAddr>,
+    kind: SampleKind,
+    start: UtcTimestamp,
+    end: UtcTimestamp,
+) -> InsnCounts {
+    let mut seen_this_trace = HashSet::new();
+    let mut counts = InsnCounts::default();
+
+    for (_, tc) in DB.trace_events.time_range(start, end, kind) {
+        let tc = tc.get();
+        let count = u64::from(tc.count);
+        counts.total_samples += count;
+
+        let Some(trace) = DB.stack_traces.get(tc.trace_hash) else {
+            continue;
+        };
+
+        seen_this_trace.clear();
+        for (frame_idx, frame) in trace.get().iter().enumerate() {
+            let frame: Frame = (*frame).into();
+            if frame.kind != FrameKind::Regular(InterpKind::Native) || frame.id.file_id != file_id {
+                continue;
+            }
+
+            let addr = call_site_frame(frame, frame_idx == 0).id.addr_or_line;
+            if !range.contains(&addr) {
+                continue;
+            }
+
+            let addr_counts = counts.addrs.entry(addr).or_default();
+            if frame_idx == 0 {
+                addr_counts.zelf += count;
+            }
+            if seen_this_trace.insert(addr) {
+                addr_counts.total += count;
+            }
+        }
+    }
+
+    counts.file_id = Some(file_id);
+    counts
+}
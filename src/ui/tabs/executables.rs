@@ -16,8 +16,13 @@
 // under the License.
 
 use super::*;
-use crate::storage::{ArchivedSymbStatus, ExecutableMeta, FileId, SymbStatus, Table, DB};
-use crate::symbolizer::IngestTask;
+use crate::storage::{
+    ArchivedSymbStatus, ExecutableMeta, FileId, MappingRange, SymbStatus, Table, DB,
+};
+use crate::symbolizer::{
+    download_progress, export_symbfile, retry_delay, DownloadProgress, IngestSource, IngestTask,
+};
+use crate::ui::timeaxis::ts2chrono;
 use crate::ui::util::{clearable_line_edit, humanize_count};
 use egui::emath::RectTransform;
 use egui::{
@@ -27,6 +32,7 @@ use egui::{
 use egui_extras::{Column, TableBuilder};
 use egui_phosphor::regular as icons;
 use std::path::PathBuf;
+use std::sync::atomic::Ordering::Relaxed;
 
 const NO_NAME: &str = "<none>";
 
@@ -35,6 +41,7 @@ const CLR_SYMBOLIZED: Color32 = Color32::from_rgb(0x7a, 0xc7, 0x4f);
 const CLR_NO_SYMS: Color32 = Color32::from_rgb(0xff, 0xe7, 0x4c);
 const CLR_PENDING: Color32 = Color32::from_rgb(0x4f, 0xc3, 0xf7);
 const CLR_TEMP_ERR: Color32 = Color32::from_rgb(0xf2, 0x42, 0x36);
+const CLR_IGNORED: Color32 = Color32::from_rgb(0x9e, 0x9e, 0x9e);
 
 #[derive(Debug, Default, Clone, Copy, Hash, PartialEq)]
 enum SortColumn {
@@ -43,11 +50,25 @@ enum SortColumn {
     FileName,
     BuildId,
     FileId,
+    LastSeen,
+}
+
+/// Action chosen from the context menu of an executable.
+#[derive(Debug)]
+enum ExecutableAction {
+    RetryNow,
+    DeleteSymbols,
+    SetIgnored(bool),
+    ReingestFrom(PathBuf),
+    ExportTo(PathBuf),
 }
 
 #[derive(Default)]
 pub struct ExecutablesTab {
-    ingest_queue: Vec<PathBuf>,
+    /// Sources to ingest, optionally for a specific file ID.
+    ingest_queue: Vec<(IngestSource, Option<FileId>)>,
+    reingest_path: String,
+    export_path: String,
     active_ingest_task: Option<IngestTask>,
     filter: String,
     sort_field: SortColumn,
@@ -77,11 +98,21 @@ impl TabWidget for ExecutablesTab {
 
 impl ExecutablesTab {
     fn handle_executable_drops(&mut self, ctx: &egui::Context) {
-        ctx.input(|i| {
-            self.ingest_queue
-                .extend(i.raw.dropped_files.iter().filter_map(|x| x.path.clone()))
+        let dropped: Vec<_> = ctx.input(|i| {
+            i.raw
+                .dropped_files
+                .iter()
+                .filter_map(|x| x.path.clone())
+                .collect()
         });
 
+        // Files dropped together are paired up with their separate debug files.
+        if !dropped.is_empty() {
+            let sources = IngestSource::classify(dropped);
+            self.ingest_queue
+                .extend(sources.into_iter().map(|source| (source, None)));
+        }
+
         if matches!(&self.active_ingest_task, Some(task) if task.done()) {
             if let Err(e) = self.active_ingest_task.take().unwrap().join() {
                 tracing::error!("Executable ingestion failed: {e:?}")
@@ -97,12 +128,15 @@ impl ExecutablesTab {
                 active_task.num_ranges_ingested()
             )
         } else {
-            if let Some(new_task) = self.ingest_queue.pop() {
-                self.active_ingest_task = Some(IngestTask::spawn(new_task));
+            if let Some((source, file_id)) = self.ingest_queue.pop() {
+                self.active_ingest_task = Some(match file_id {
+                    Some(file_id) => IngestTask::spawn_as(source, file_id),
+                    None => IngestTask::spawn(source),
+                });
             }
 
             format!(
-                "{} Drop executables anywhere within this tab to ingest symbols!",
+                "{} Drop executables or symbfiles anywhere within this tab to ingest symbols!",
                 icons::INFO
             )
         };
@@ -162,12 +196,14 @@ impl ExecutablesTab {
         let mut not_present = 0;
         let mut symbolized = 0;
         let mut temp_err = 0;
+        let mut ignored = 0;
 
         for (_, meta) in DB.executables.iter() {
             match meta.get().symb_status {
                 ArchivedSymbStatus::NotAttempted => pending += 1,
                 ArchivedSymbStatus::TempError { .. } => temp_err += 1,
                 ArchivedSymbStatus::NotPresentGlobally => not_present += 1,
+                ArchivedSymbStatus::Ignored => ignored += 1,
                 ArchivedSymbStatus::Complete { .. } => symbolized += 1,
             }
         }
@@ -181,7 +217,7 @@ impl ExecutablesTab {
         );
 
         let style = ui.ctx().style();
-        let total = pending + not_present + symbolized + temp_err;
+        let total = pending + not_present + symbolized + temp_err + ignored;
         let avail_width = response.rect.width();
         let mut offset = 0.0;
         for (name, value, color) in [
@@ -189,6 +225,7 @@ impl ExecutablesTab {
             ("No symbols found", not_present, CLR_NO_SYMS),
             ("Pending", pending, CLR_PENDING),
             ("Temporary error", temp_err, CLR_TEMP_ERR),
+            ("Ignored", ignored, CLR_IGNORED),
         ] {
             let width = avail_width * (value as f32 / total as f32);
             let pos = Pos2::new(offset, 0.0);
@@ -229,27 +266,34 @@ impl ExecutablesTab {
 
     fn draw_executable_table(&mut self, ui: &mut Ui) -> usize {
         let mut exe_count = 0;
+        let mut action = None;
 
         let table = TableBuilder::new(ui)
             .striped(true)
             .resizable(true)
+            .sense(Sense::click())
             .cell_layout(Layout::left_to_right(Align::Center))
             .column(Column::initial(235.0))
             .column(Column::initial(290.0))
             .column(Column::initial(180.0))
+            .column(Column::initial(150.0))
+            .column(Column::initial(250.0))
             .column(Column::remainder().clip(true))
             .max_scroll_height(f32::INFINITY);
 
         table
             .header(20.0, |mut header| {
                 for (text, selected_value) in [
-                    ("File ID", SortColumn::FileId),
-                    ("Build ID", SortColumn::BuildId),
-                    ("Symbols", SortColumn::Symbols),
-                    ("File Name", SortColumn::FileName),
+                    ("File ID", Some(SortColumn::FileId)),
+                    ("Build ID", Some(SortColumn::BuildId)),
+                    ("Symbols", Some(SortColumn::Symbols)),
+                    ("Last Seen", Some(SortColumn::LastSeen)),
+                    ("Mapping", None),
+                    ("File Name", Some(SortColumn::FileName)),
                 ] {
-                    header.col(|ui| {
-                        ui.selectable_value(&mut self.sort_field, selected_value, text);
+                    header.col(|ui| match selected_value {
+                        Some(value) => drop(ui.selectable_value(&mut self.sort_field, value, text)),
+                        None => drop(ui.strong(text)),
                     });
                 }
             })
@@ -268,29 +312,238 @@ impl ExecutablesTab {
                             ui.monospace(meta.build_id.as_deref().unwrap_or("<none>"));
                         });
                         row.col(|ui| {
-                            ui.label(symb_status_text(meta.symb_status));
+                            if let Some(progress) = download_progress(*file_id) {
+                                draw_download_progress(ui, &progress);
+                                return;
+                            }
+                            let label = ui.label(symb_status_text(meta));
+                            if let Some(ref error) = meta.symb_error {
+                                label.on_hover_text(symb_error_text(meta, error));
+                            } else if let Some(ref source) = meta.symb_source {
+                                label.on_hover_text(format!("Source: {source}"));
+                            }
+                        });
+                        row.col(|ui| {
+                            let Some(last_seen) = meta.last_seen else {
+                                ui.label(NO_NAME);
+                                return;
+                            };
+                            let first_seen = meta.first_seen.unwrap_or(last_seen);
+                            ui.label(format_timestamp(last_seen)).on_hover_text(format!(
+                                "First seen: {}",
+                                format_timestamp(first_seen)
+                            ));
+                        });
+                        row.col(|ui| {
+                            ui.monospace(meta.mapping.map_or(NO_NAME.into(), mapping_text));
                         });
                         row.col(|ui| {
                             ui.label(name);
                         });
+
+                        row.response().context_menu(|ui| {
+                            if let Some(chosen) = self.draw_context_menu(ui, meta) {
+                                action = Some((*file_id, chosen));
+                                ui.close();
+                            }
+                        });
                     });
                 }
             });
+
+        if let Some((file_id, action)) = action {
+            self.apply_action(file_id, action);
+        }
+
         exe_count
     }
+
+    fn draw_context_menu(
+        &mut self,
+        ui: &mut Ui,
+        meta: &ExecutableMeta,
+    ) -> Option<ExecutableAction> {
+        let mut action = None;
+
+        if ui
+            .button(format!(
+                "{} Retry symbolization now",
+                icons::ARROW_CLOCKWISE
+            ))
+            .clicked()
+        {
+            action = Some(ExecutableAction::RetryNow);
+        }
+
+        let has_symbols = matches!(meta.symb_status, SymbStatus::Complete { .. });
+        if ui
+            .add_enabled(
+                has_symbols,
+                egui::Button::new(format!("{} Delete symbols", icons::TRASH)),
+            )
+            .on_hover_text(
+                "Removes the symbols and excludes the executable from automatic symbolization",
+            )
+            .clicked()
+        {
+            action = Some(ExecutableAction::DeleteSymbols);
+        }
+
+        let ignored = meta.symb_status == SymbStatus::Ignored;
+        let (icon, text) = match ignored {
+            true => (icons::EYE, "Stop ignoring"),
+            false => (icons::EYE_SLASH, "Ignore for automatic symbolization"),
+        };
+        if ui.button(format!("{icon} {text}")).clicked() {
+            action = Some(ExecutableAction::SetIgnored(!ignored));
+        }
+
+        ui.separator();
+        ui.label("Re-ingest from file:");
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.reingest_path)
+                    .hint_text("/path/to/unstripped/executable"),
+            );
+            let valid = !self.reingest_path.trim().is_empty();
+            if ui
+                .add_enabled(valid, egui::Button::new(icons::UPLOAD_SIMPLE))
+                .clicked()
+            {
+                let path = PathBuf::from(self.reingest_path.trim());
+                action = Some(ExecutableAction::ReingestFrom(path));
+            }
+        });
+
+        ui.add_enabled_ui(has_symbols, |ui| {
+            ui.label("Export symbols to file:");
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.export_path)
+                        .hint_text("/path/to/output.symbfile.zst"),
+                );
+                let valid = !self.export_path.trim().is_empty();
+                if ui
+                    .add_enabled(valid, egui::Button::new(icons::DOWNLOAD_SIMPLE))
+                    .clicked()
+                {
+                    let path = PathBuf::from(self.export_path.trim());
+                    action = Some(ExecutableAction::ExportTo(path));
+                }
+            });
+        });
+
+        action
+    }
+
+    fn apply_action(&mut self, file_id: FileId, action: ExecutableAction) {
+        let Some(meta) = DB.executables.get(file_id) else {
+            return;
+        };
+        let mut meta = meta.read();
+
+        match action {
+            ExecutableAction::RetryNow => {
+                meta.symb_status = SymbStatus::NotAttempted;
+                meta.symb_attempts = 0;
+                meta.symb_error = None;
+            }
+            ExecutableAction::DeleteSymbols => {
+                if let Err(e) = DB.symbols.remove(file_id) {
+                    tracing::error!("Failed to delete symbols: {e:?}");
+                    return;
+                }
+                meta.symb_status = SymbStatus::Ignored;
+                meta.symb_source = None;
+            }
+            ExecutableAction::SetIgnored(true) => {
+                meta.symb_status = SymbStatus::Ignored;
+            }
+            ExecutableAction::SetIgnored(false) => {
+                meta.symb_status = SymbStatus::NotAttempted;
+                meta.symb_attempts = 0;
+                meta.symb_error = None;
+            }
+            ExecutableAction::ReingestFrom(path) => {
+                // The chosen file is paired with its debug file like dropped ones.
+                for source in IngestSource::classify(vec![path]) {
+                    self.ingest_queue.push((source, Some(file_id)));
+                }
+                self.reingest_path.clear();
+                return;
+            }
+            ExecutableAction::ExportTo(path) => {
+                // Encoding large symbol trees takes a while: keep the UI responsive.
+                std::thread::spawn(move || match export_symbfile(file_id, &path) {
+                    Ok(n) => tracing::info!("Exported {n} symbols to {}", path.display()),
+                    Err(e) => tracing::error!("Failed to export symbols: {e:?}"),
+                });
+                self.export_path.clear();
+                return;
+            }
+        }
+
+        DB.executables.insert(file_id, meta);
+    }
 }
 
-fn symb_status_text(status: SymbStatus) -> String {
-    match status {
+fn symb_status_text(meta: &ExecutableMeta) -> String {
+    match meta.symb_status {
         SymbStatus::NotAttempted => "not attempted yet".into(),
+        SymbStatus::TempError { .. } if meta.symb_attempts > 1 => {
+            format!("temporary error ({} attempts)", meta.symb_attempts)
+        }
         SymbStatus::TempError { .. } => "temporary error".into(),
         SymbStatus::NotPresentGlobally => "not present globally".into(),
+        SymbStatus::Ignored => "ignored".into(),
         SymbStatus::Complete { num_symbols, .. } => {
             format!("{} symbols", humanize_count(num_symbols))
         }
     }
 }
 
+fn draw_download_progress(ui: &mut Ui, progress: &DownloadProgress) {
+    let bytes = progress.bytes.load(Relaxed);
+    let symbols = progress.symbols.load(Relaxed);
+    let text = match symbols {
+        0 => format!("downloading: {}B", humanize_count(bytes)),
+        _ => format!("{} symbols", humanize_count(symbols)),
+    };
+
+    let bar = match progress.fraction() {
+        Some(fraction) => egui::ProgressBar::new(fraction),
+        None => egui::ProgressBar::new(0.0).animate(true),
+    };
+    ui.add(bar.text(text).desired_height(14.0));
+
+    // Downloads don't touch the DB until they complete: keep updating.
+    ui.ctx()
+        .request_repaint_after(std::time::Duration::from_millis(200));
+}
+
+fn symb_error_text(meta: &ExecutableMeta, error: &str) -> String {
+    let SymbStatus::TempError { last_attempt } = meta.symb_status else {
+        return format!("Last error: {error}");
+    };
+
+    let next_attempt = last_attempt + retry_delay(meta.symb_attempts).as_secs();
+    format!(
+        "Last error: {error}\nNext attempt: {}",
+        format_timestamp(next_attempt)
+    )
+}
+
+fn mapping_text(range: MappingRange) -> String {
+    format!(
+        "{:#x}-{:#x} @ {:#x}",
+        range.memory_start, range.memory_limit, range.file_offset
+    )
+}
+
+fn format_timestamp(ts: u64) -> String {
+    ts2chrono(ts as i64).format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
 fn query_executables(filter: &String, sort_field: &SortColumn) -> Vec<(FileId, ExecutableMeta)> {
     let mut execs: Vec<_> = DB
         .executables
@@ -298,7 +551,8 @@ fn query_executables(filter: &String, sort_field: &SortColumn) -> Vec<(FileId, E
         .filter_map(|(file_id, value_ref)| {
             let meta = value_ref.read();
             let name = meta.file_name.as_deref().unwrap_or(NO_NAME);
-            if name.contains(filter) {
+            let build_id = meta.build_id.as_deref().unwrap_or_default();
+            if name.contains(filter) || build_id.contains(filter) {
                 return Some((file_id, meta));
             }
             None
@@ -316,6 +570,7 @@ fn query_executables(filter: &String, sort_field: &SortColumn) -> Vec<(FileId, E
             }
             SortColumn::BuildId => lhs_metas.build_id.cmp(&rhs_metas.build_id).reverse(),
             SortColumn::FileId => u128::from(*lhs_file_id).cmp(&u128::from(*rhs_file_id)),
+            SortColumn::LastSeen => lhs_metas.last_seen.cmp(&rhs_metas.last_seen).reverse(),
         },
     );
 
@@ -17,8 +17,14 @@
 
 use super::*;
 use crate::storage::*;
+use crate::ui::aggregate::{
+    apply_transforms, draw_transform_chips, executable_key, executable_name, hash_key,
+    FramePattern, Granularity, Transform, TransformFrame,
+};
 use crate::ui::cached::Cached;
-use crate::ui::util::{clearable_line_edit_with_status, frame_kind_color, humanize_count};
+use crate::ui::util::{
+    clearable_line_edit_with_status, error_frame_text, frame_kind_color, humanize_count,
+};
 use base64::Engine;
 use egui::emath::RectTransform;
 use egui::Stroke;
@@ -27,8 +33,9 @@ use egui::{
     PopupAnchor, Pos2, Rangef, Rect, Response, Sense, Shape, StrokeKind, Tooltip, Vec2,
 };
 use egui_phosphor::regular as icons;
+use std::borrow::Cow;
 use std::collections::HashMap;
-use std::sync::mpsc;
+use std::sync::{mpsc, Arc};
 
 const FLAME_HEIGHT: f32 = 20.0;
 const MIN_WIDTH: f32 = 1.0;
@@ -39,6 +46,16 @@ pub struct FlameGraphTab {
     cached_root: Cached<FlameGraphNode>,
     widget: FlameGraphWidget,
     show_inline: bool,
+    group_by: GroupBy,
+    inverted: bool,
+    /// Transformation chain applied to all stacks, see [`Transform`].
+    transforms: Vec<Transform>,
+    /// Pattern typed into the transformation menu.
+    transform_input: String,
+    /// Function to show the sandwich view for once the graph is available.
+    pending_sandwich: Option<String>,
+    /// Function that a sandwich view was requested for but isn't in the graph.
+    sandwich_not_found: Option<String>,
 }
 
 impl Default for FlameGraphTab {
@@ -47,10 +64,72 @@ impl Default for FlameGraphTab {
             cached_root: Default::default(),
             widget: Default::default(),
             show_inline: true,
+            group_by: GroupBy::Thread,
+            inverted: false,
+            transforms: Vec::new(),
+            transform_input: String::new(),
+            pending_sandwich: None,
+            sandwich_not_found: None,
         }
     }
 }
 
+/// Meta nodes that traces are grouped under below the root node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(super) enum GroupBy {
+    None,
+    Thread,
+    Process,
+    Executable,
+    Container,
+    SampleKind,
+}
+
+impl GroupBy {
+    const ALL: [GroupBy; 6] = [
+        Self::None,
+        Self::Thread,
+        Self::Process,
+        Self::Executable,
+        Self::Container,
+        Self::SampleKind,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            GroupBy::None => "No grouping",
+            GroupBy::Thread => "Thread name",
+            GroupBy::Process => "Process",
+            GroupBy::Executable => "Executable (leaf frame)",
+            GroupBy::Container => "Container",
+            GroupBy::SampleKind => "Sample kind",
+        }
+    }
+
+    fn icon(self) -> &'static str {
+        match self {
+            GroupBy::None => "",
+            GroupBy::Thread => icons::APP_WINDOW,
+            GroupBy::Process => icons::GEAR,
+            GroupBy::Executable => icons::FILE,
+            GroupBy::Container => icons::PACKAGE,
+            GroupBy::SampleKind => icons::TAG,
+        }
+    }
+}
+
+/// Options controlling the construction of the flame graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(super) struct FlameGraphOptions {
+    pub inline_frames: bool,
+    pub simplify_names: bool,
+    pub granularity: Granularity,
+    pub group_by: GroupBy,
+
+    /// Whether leaf frames are the roots (and callers their children).
+    pub inverted: bool,
+}
+
 impl TabWidget for FlameGraphTab {
     fn id(&self) -> Tab {
         Tab::FlameGraph
@@ -64,17 +143,36 @@ impl TabWidget for FlameGraphTab {
         start: UtcTimestamp,
         end: UtcTimestamp,
     ) -> Option<TabAction> {
-        let show_inline = self.show_inline;
+        let opts = FlameGraphOptions {
+            inline_frames: self.show_inline,
+            simplify_names: cfg.simplify_names,
+            granularity: cfg.granularity,
+            group_by: self.group_by,
+            inverted: self.inverted,
+        };
+        let transforms = self.transforms.clone();
         let root = self
             .cached_root
-            .get_or_create((start, end, show_inline), move || {
-                build_flame_graph(kind, start, end, show_inline)
+            .get_or_create((kind, start, end, opts, &self.transforms), move || {
+                build_flame_graph(kind, start, end, opts, transforms)
             });
 
         ui.add_space(5.0);
         ui.columns(2, |ui| {
             ui[0].with_layout(Layout::left_to_right(Align::Min), |ui| {
                 ui.checkbox(&mut self.show_inline, "Show inline");
+                ui.checkbox(&mut self.inverted, "Inverted")
+                    .on_hover_text("Show leaf functions as roots, with their callers below them");
+                egui::ComboBox::from_id_salt("flamegraph-group-by")
+                    .selected_text(self.group_by.name())
+                    .show_ui(ui, |ui| {
+                        for group_by in GroupBy::ALL {
+                            ui.selectable_value(&mut self.group_by, group_by, group_by.name());
+                        }
+                    });
+                ui.menu_button(format!("{} Transform", icons::FUNNEL_SIMPLE), |ui| {
+                    self.draw_transform_menu(ui)
+                });
 
                 // Show sandwich view indicator
                 if self.widget.sandwich_view.is_some() {
@@ -122,10 +220,121 @@ impl TabWidget for FlameGraphTab {
                 }
             });
         });
+        if !self.transforms.is_empty() {
+            draw_transform_chips(ui, &mut self.transforms);
+        }
         ui.add_space(5.0);
 
-        self.widget.draw(ui, cfg, &*root);
-        None
+        self.widget.inverted = opts.inverted;
+        if root.weight > 0 {
+            if let Some(func) = self.pending_sandwich.take() {
+                // Top functions merges functions across executables: so does
+                // the sandwich view opened from there.
+                let keys = function_keys(&root, &func);
+                if keys.is_empty() {
+                    self.sandwich_not_found = Some(func);
+                } else {
+                    self.widget.show_sandwich(&root, &keys);
+                }
+            }
+        }
+        if let Some(ref func) = self.sandwich_not_found {
+            let mut dismissed = false;
+            ui.horizontal(|ui| {
+                ui.colored_label(
+                    Color32::ORANGE,
+                    format!(
+                        "{} {func} isn't part of this flamegraph: it may be removed by the \
+                         transformations or not occur in the selected sample kind.",
+                        icons::WARNING
+                    ),
+                );
+                dismissed = ui.small_button(icons::X).on_hover_text("Dismiss").clicked();
+            });
+            if dismissed {
+                self.sandwich_not_found = None;
+            }
+        }
+
+        let action = self.widget.draw(ui, cfg, &*root);
+        if let Some(transform) = self.widget.new_transform.take() {
+            self.add_transform(transform);
+        }
+        action
+    }
+
+    fn handle_action(&mut self, action: &TabAction) {
+        if let TabAction::ShowSandwich { func } = action {
+            self.pending_sandwich = Some(func.clone());
+            self.sandwich_not_found = None;
+        }
+    }
+}
+
+impl FlameGraphTab {
+    /// Populates the menu for adding pattern-based transformations.
+    fn draw_transform_menu(&mut self, ui: &mut Ui) {
+        ui.label("Function name pattern:");
+        ui.text_edit_singleline(&mut self.transform_input);
+        let input = self.transform_input.trim().to_owned();
+
+        let exclude = ui.add_enabled(
+            !input.is_empty(),
+            egui::Button::new("Exclude stacks containing it"),
+        );
+        if exclude.clicked() {
+            self.add_transform(Transform::Exclude(input.clone()));
+            ui.close();
+        }
+
+        let pattern = FramePattern::new(&input);
+        let drop_frames = ui.add_enabled(
+            !input.is_empty() && pattern.is_ok(),
+            egui::Button::new("Drop frames matching it (regex)"),
+        );
+        if let Err(ref err) = pattern {
+            if !input.is_empty() {
+                ui.colored_label(Color32::from_rgb(200, 100, 100), err.to_string());
+            }
+        }
+        if drop_frames.clicked() {
+            self.add_transform(Transform::DropFrames(pattern.unwrap()));
+            ui.close();
+        }
+
+        ui.separator();
+        if ui.button("Collapse recursion").clicked() {
+            self.add_transform(Transform::CollapseRecursion);
+            ui.close();
+        }
+    }
+
+    /// Append a transformation to the chain, unless it is already part of it.
+    fn add_transform(&mut self, transform: Transform) {
+        if !self.transforms.contains(&transform) {
+            self.transforms.push(transform);
+        }
+        self.widget.sandwich_view = None;
+    }
+}
+
+/// Frame that the cursor or the context menu refers to.
+#[derive(Debug, Clone)]
+struct FrameRef {
+    id: FrameId,
+    source: Option<SourceLine>,
+    func: Arc<str>,
+    kind: Option<FrameKind>,
+}
+
+impl FrameRef {
+    fn new(node: &FlameGraphNode) -> Self {
+        FrameRef {
+            id: node.id,
+            source: node.source.clone(),
+            func: node.func.clone(),
+            kind: node.kind,
+        }
     }
 }
 
@@ -151,12 +360,22 @@ struct FlameGraphWidget {
     match_count: usize,
     cached_filter: String,
     rebuild_matches: bool,
+
+    /// Frame under the cursor.
+    hovered_frame: Option<FrameRef>,
+    /// Frame the context menu was opened on.
+    context_frame: Option<FrameRef>,
+    /// Transformation chosen in the context menu, to be applied by the tab.
+    new_transform: Option<Transform>,
+
+    /// Whether the drawn graph is inverted (see [`FlameGraphOptions`]).
+    inverted: bool,
 }
 
 /// Sandwich view showing callers above and callees below a selected frame
 struct SandwichView {
     #[allow(dead_code)]
-    selected_frame: FrameId,
+    selected_keys: Vec<u64>,
     selected_text: String,
     callers: FlameGraphNode,
     callees: FlameGraphNode,
@@ -174,12 +393,31 @@ impl Default for FlameGraphWidget {
             match_count: 0,
             cached_filter: "".to_string(),
             rebuild_matches: true,
+            hovered_frame: None,
+            context_frame: None,
+            new_transform: None,
+            inverted: false,
         }
     }
 }
 
 impl FlameGraphWidget {
-    pub fn draw(&mut self, ui: &mut Ui, cfg: &DevfilerConfig, root: &FlameGraphNode) {
+    /// Enter the sandwich view for the frames merged under the given keys.
+    pub fn show_sandwich(&mut self, root: &FlameGraphNode, keys: &[u64]) {
+        self.sandwich_view = Some(build_sandwich_view(root, keys, self.inverted));
+        self.origin = Pos2::ZERO;
+        self.x_zoom = 1.0;
+    }
+
+    pub fn draw(
+        &mut self,
+        ui: &mut Ui,
+        cfg: &DevfilerConfig,
+        root: &FlameGraphNode,
+    ) -> Option<TabAction> {
+        let mut action = None;
+        self.hovered_frame = None;
+
         egui::Frame::canvas(ui.style()).show(ui, |ui| {
             let size = ui.available_size_before_wrap();
             let (response, painter) = ui.allocate_painter(size, Sense::click_and_drag());
@@ -235,7 +473,97 @@ impl FlameGraphWidget {
             }
 
             self.rebuild_matches = false;
+
+            if response.secondary_clicked() {
+                self.context_frame = self.hovered_frame.clone();
+            }
+            response.context_menu(|ui| {
+                if let Some(chosen) = self.draw_context_menu(ui) {
+                    action = Some(chosen);
+                    ui.close();
+                }
+                ui.separator();
+                if let Some(transform) = self.draw_transform_context_menu(ui) {
+                    self.new_transform = Some(transform);
+                    ui.close();
+                }
+            });
         });
+
+        action
+    }
+
+    /// Populates the context menu of the frame that was right-clicked.
+    fn draw_context_menu(&self, ui: &mut Ui) -> Option<TabAction> {
+        let frame = self.context_frame.as_ref().map(|x| x.id);
+        let source = self.context_frame.as_ref().and_then(|x| x.source.as_ref());
+        let show_source = ui.add_enabled(source.is_some(), egui::Button::new("Show source"));
+        if show_source.clicked() {
+            let source = source?;
+            return Some(TabAction::ShowSource {
+                file: source.file.clone(),
+                line: source.line,
+            });
+        }
+
+        let open = ui.add_enabled(source.is_some(), egui::Button::new("Open in editor"));
+        if open.clicked() {
+            let source = source?;
+            return Some(TabAction::OpenInEditor {
+                file: source.file.clone(),
+                line: source.line,
+            });
+        }
+
+        // Only native executables known to the DB can be disassembled.
+        let native = frame.filter(|x| DB.executables.get(x.file_id).is_some());
+        let show_disasm = ui.add_enabled(native.is_some(), egui::Button::new("Show disassembly"));
+        if show_disasm.clicked() {
+            return Some(TabAction::ShowDisassembly { frame: native? });
+        }
+
+        None
+    }
+
+    /// Populates the context menu entries adding a transformation for the
+    /// frame that was right-clicked.
+    fn draw_transform_context_menu(&self, ui: &mut Ui) -> Option<Transform> {
+        // Meta nodes don't have a kind and can't be transformed.
+        let frame = self.context_frame.as_ref().filter(|x| x.kind.is_some());
+        let func = frame.map(|x| x.func.to_string());
+
+        let focus = ui.add_enabled(func.is_some(), egui::Button::new("Focus on function"));
+        if focus.clicked() {
+            return Some(Transform::Focus(func?));
+        }
+
+        let exclude = ui.add_enabled(
+            func.is_some(),
+            egui::Button::new("Exclude stacks with function"),
+        );
+        if exclude.clicked() {
+            return Some(Transform::Exclude(func?));
+        }
+
+        let drop_frames = ui.add_enabled(func.is_some(), egui::Button::new("Drop function frames"));
+        if drop_frames.clicked() {
+            return Some(Transform::DropFrames(FramePattern::exact(&func?)));
+        }
+
+        let interp = frame.and_then(|x| match x.kind? {
+            FrameKind::Regular(interp) => Some(interp),
+            _ => None,
+        });
+        let fold_text = match interp {
+            Some(interp) => format!("Fold {interp} frames"),
+            None => "Fold frames".to_owned(),
+        };
+        let fold = ui.add_enabled(interp.is_some(), egui::Button::new(fold_text));
+        if fold.clicked() {
+            return Some(Transform::Fold(interp?));
+        }
+
+        None
     }
 
     /// Draw the sandwich view with callers above and callees below
@@ -282,13 +610,13 @@ impl FlameGraphWidget {
 
         // Helper to draw a single inverted caller flame
         fn draw_inverted_caller_flame(
-            _widget: &mut FlameGraphWidget,
+            widget: &mut FlameGraphWidget,
             _ctx: &egui::Context,
             _cfg: &DevfilerConfig,
             painter: &Painter,
             to_screen: &RectTransform,
             visible_x_range: Rangef,
-            _cursor_hover_pos: Option<Pos2>,
+            cursor_hover_pos: Option<Pos2>,
             x: f32,
             base_y: f32,
             width: f32,
@@ -307,6 +635,10 @@ impl FlameGraphWidget {
             }
 
             let screen_rect = to_screen.transform_rect(rect);
+            if cursor_hover_pos.is_some_and(|x| screen_rect.contains(x)) {
+                widget.hovered_frame = Some(FrameRef::new(node));
+            }
+
             painter.add(Shape::rect_filled(
                 screen_rect,
                 CornerRadius::ZERO,
@@ -333,13 +665,13 @@ impl FlameGraphWidget {
             for child in &node.children {
                 let child_width = width * (child.weight as f32 / node.weight.max(1) as f32);
                 draw_inverted_caller_flame(
-                    _widget,
+                    widget,
                     _ctx,
                     _cfg,
                     painter,
                     to_screen,
                     visible_x_range.clone(),
-                    _cursor_hover_pos,
+                    cursor_hover_pos,
                     child_x,
                     base_y,
                     child_width,
@@ -581,6 +913,8 @@ impl FlameGraphWidget {
 
         if let Some(hover_pos) = cursor_hover_pos {
             if screen_rect.contains(hover_pos) {
+                self.hovered_frame = Some(FrameRef::new(flame));
+
                 let id = Id::new("flamegraph-tooltip");
                 Tooltip::always_open(
                     ctx.clone(),
@@ -593,9 +927,7 @@ impl FlameGraphWidget {
                 if clicked && flame.weight >= 1 {
                     if ctrl_held {
                         // Ctrl+Click: Enter sandwich view mode
-                        self.sandwich_view = Some(build_sandwich_view(root, flame.id));
-                        self.origin = Pos2::ZERO;
-                        self.x_zoom = 1.0;
+                        self.show_sandwich(root, &[flame.key]);
                     } else {
                         // Normal click: Zoom to frame
                         self.x_zoom = root.weight as f32 / flame.weight as f32;
@@ -744,73 +1076,244 @@ static ES_B64_ENGINE: base64::engine::GeneralPurpose = base64::engine::GeneralPu
 );
 
 /// Pull in events and construct a flame graph data structure for them.
-fn build_flame_graph(
+pub(super) fn build_flame_graph(
     kind: SampleKind,
     start: UtcTimestamp,
     end: UtcTimestamp,
-    inline_frames: bool,
+    opts: FlameGraphOptions,
+    transforms: Vec<Transform>,
 ) -> FlameGraphNode {
     // Thread 1: pull events from the table.
     let (event_tx, event_rx) = mpsc::sync_channel(4096);
     let table_task = tokio::task::spawn_blocking(move || {
-        for (_, tc) in DB.trace_events.time_range(start, end, kind) {
+        for (id, tc) in DB.trace_events.time_range(start, end, kind) {
             event_tx
-                .send(tc)
+                .send((id.kind, tc))
                 .expect("should never be closed on RX side (1)");
         }
     });
 
     // Thread 2 (this one): aggregate.
-    let mut comm_nodes = HashMap::new();
-    for tc in event_rx {
+    let mut graph = GroupedGraph::new(opts);
+    let mut exe_names = HashMap::new();
+    let mut labels = HashMap::new();
+    let mut expander = TraceExpander::new(opts, transforms);
+    for (sample_kind, tc) in event_rx {
         let tc = tc.get();
 
         let Some(trace) = DB.stack_traces.get(tc.trace_hash) else {
             continue;
         };
+        let trace = trace.get();
+        let Some(stack) = expander.expand(&trace) else {
+            continue;
+        };
+
+        let group: Cow<'_, str> = match opts.group_by {
+            GroupBy::None => "".into(),
+            GroupBy::Thread => tc.comm.as_str().into(),
+            GroupBy::Process => {
+                process_group(event_labels(&mut labels, tc.labels.as_ref().copied())).into()
+            }
+            GroupBy::Executable => match trace.first() {
+                Some(leaf) => {
+                    let leaf: Frame = (*leaf).into();
+                    let name = exe_names
+                        .entry(leaf.id.file_id)
+                        .or_insert_with(|| executable_name(leaf));
+                    name.as_str().into()
+                }
+                None => "<empty trace>".into(),
+            },
+            GroupBy::Container => {
+                container_group(event_labels(&mut labels, tc.labels.as_ref().copied()))
+            }
+            GroupBy::SampleKind => format!("{sample_kind:?}").into(),
+        };
+
+        graph.insert(&group, &stack, tc.count as u64);
+    }
 
-        let comm_node = if let Some(node) = comm_nodes.get_mut(tc.comm.as_str()) {
+    // Wait for table task to exit.
+    let rt = tokio::runtime::Handle::current();
+    rt.block_on(table_task).expect("table task panicked");
+
+    graph.finish()
+}
+
+/// Resolve the interned labels of an event, caching them in `cache`.
+fn event_labels(
+    cache: &mut HashMap<TraceLabelsId, TraceLabels>,
+    id: Option<TraceLabelsId>,
+) -> &TraceLabels {
+    static NO_LABELS: TraceLabels = TraceLabels {
+        process: None,
+        container_name: None,
+        pod_name: None,
+    };
+
+    let Some(id) = id else {
+        return &NO_LABELS;
+    };
+    cache.entry(id).or_insert_with(|| {
+        DB.trace_labels
+            .get(id)
+            .map(|x| x.read())
+            .unwrap_or_default()
+    })
+}
+
+/// Name of the [`GroupBy::Process`] group that an event belongs to.
+fn process_group(labels: &TraceLabels) -> &str {
+    labels.process.as_deref().unwrap_or("<unknown>")
+}
+
+/// Name of the [`GroupBy::Container`] group that an event belongs to.
+fn container_group(labels: &TraceLabels) -> Cow<'_, str> {
+    let container = labels.container_name.as_deref();
+    match (labels.pod_name.as_deref(), container) {
+        (Some(pod), Some(container)) => format!("{pod}/{container}").into(),
+        (pod, container) => pod.or(container).unwrap_or("<no container>").into(),
+    }
+}
+
+/// Flame graph under construction, with one meta node per group.
+struct GroupedGraph {
+    opts: FlameGraphOptions,
+    root: FlameGraphNode,
+    group_nodes: HashMap<String, FlameGraphNode>,
+}
+
+impl GroupedGraph {
+    fn new(opts: FlameGraphOptions) -> Self {
+        GroupedGraph {
+            opts,
+            root: FlameGraphNode::root(),
+            group_nodes: HashMap::new(),
+        }
+    }
+
+    /// Insert a stack into the given group. The group is ignored with
+    /// [`GroupBy::None`].
+    fn insert(&mut self, group: &str, stack: &[&FlameGraphNode], weight: u64) {
+        if self.opts.group_by == GroupBy::None {
+            self.root.insert_trace(stack, weight, self.opts.inverted);
+            return;
+        }
+
+        let group_node = if let Some(node) = self.group_nodes.get_mut(group) {
             node
         } else {
             // This insert/get chain is dumb, but `try_insert` (which fixes it)
             // is not yet available on stable Rust. `entry` API also isn't any
             // good here because it requires cloning a string in the hot path.
-            comm_nodes.insert(
-                tc.comm.to_owned(),
+            self.group_nodes.insert(
+                group.to_string(),
                 FlameGraphNode::new_meta_node(
-                    format!("{} {}", icons::APP_WINDOW, tc.comm),
-                    comm_nodes.len() as u64,
+                    format!("{} {}", self.opts.group_by.icon(), group),
+                    self.group_nodes.len() as u64 + 1,
                 ),
             );
-            comm_nodes.get_mut(tc.comm.as_str()).unwrap()
+            self.group_nodes.get_mut(group).unwrap()
         };
 
-        comm_node.insert_trace(&trace.get(), tc.count as u64, inline_frames);
+        group_node.insert_trace(stack, weight, self.opts.inverted);
     }
 
-    // Wait for table task to exit.
-    let rt = tokio::runtime::Handle::current();
-    rt.block_on(table_task).expect("table task panicked");
+    fn finish(self) -> FlameGraphNode {
+        let mut root = self.root;
+        if self.opts.group_by != GroupBy::None {
+            root.weight = self.group_nodes.values().map(|x| x.weight).sum();
+            root.children = self.group_nodes.into_values().collect();
+        }
+        root.sort_children();
+        root
+    }
+}
 
-    let mut root = FlameGraphNode::root();
-    root.weight = comm_nodes.values().map(|x| x.weight).sum();
-    root.children = comm_nodes.into_values().collect();
-    root.sort_children();
-    root
+/// Expands traces into the stacks of nodes that are inserted into the graph.
+struct TraceExpander {
+    opts: FlameGraphOptions,
+    transforms: Vec<Transform>,
+    /// Nodes that raw frames expand to, see [`frame_nodes`].
+    cache: HashMap<Frame, Vec<FlameGraphNode>>,
+    /// Nodes replacing the frames folded by [`Transform::Fold`].
+    folded: HashMap<InterpKind, FlameGraphNode>,
+}
+
+impl TraceExpander {
+    fn new(opts: FlameGraphOptions, transforms: Vec<Transform>) -> Self {
+        let folded = transforms
+            .iter()
+            .filter_map(|x| match x {
+                Transform::Fold(interp) => Some((*interp, folded_node(*interp))),
+                _ => None,
+            })
+            .collect();
+
+        TraceExpander {
+            opts,
+            transforms,
+            cache: HashMap::new(),
+            folded,
+        }
+    }
+
+    /// Expand a trace into its transformed stack, root first.
+    ///
+    /// Returns `None` if the trace was dropped by a transformation.
+    fn expand(&mut self, trace: &[ArchivedFrame]) -> Option<Vec<&FlameGraphNode>> {
+        let opts = self.opts;
+        for frame in trace {
+            let frame: Frame = (*frame).into();
+            self.cache
+                .entry(frame)
+                .or_insert_with(|| frame_nodes(frame, opts));
+        }
+
+        let cache = &self.cache;
+        let mut stack: Vec<_> = trace
+            .iter()
+            .rev()
+            .flat_map(|frame| &cache[&Frame::from(*frame)])
+            .collect();
+
+        // Consecutive frames within the same executable form one node.
+        if opts.granularity == Granularity::Executable {
+            stack.dedup_by_key(|x| x.key);
+        }
+
+        let folded = &self.folded;
+        apply_transforms(&self.transforms, &mut stack, |interp| &folded[&interp]).then_some(stack)
+    }
 }
 
 /// Node in the flame graph tree structure.
 #[derive(Debug, Clone)]
-struct FlameGraphNode {
+pub(super) struct FlameGraphNode {
     pub weight: u64,
     pub fg_color: Color32,
     pub bg_color: Color32,
+    /// Frames with equal keys are merged into one node (see [`Granularity`]).
+    pub key: u64,
+    /// First frame that was merged into this node.
     pub id: FrameId,
+    /// Kind of the frame, `None` for meta nodes.
+    pub kind: Option<FrameKind>,
+    /// Function name that transformations are matched against.
+    pub func: Arc<str>,
     pub text: String,
-    pub inline_skip: u16,
+    pub source: Option<SourceLine>,
     pub children: Vec<FlameGraphNode>,
 }
 
+/// Source file and line of a flame graph node.
+#[derive(Debug, Clone)]
+pub(super) struct SourceLine {
+    pub file: String,
+    pub line: Option<u32>,
+}
+
 impl Default for FlameGraphNode {
     fn default() -> Self {
         Self::root()
@@ -824,14 +1327,17 @@ impl FlameGraphNode {
 
     pub fn new_meta_node(text: String, addr_or_line: u64) -> Self {
         FlameGraphNode {
+            func: text.as_str().into(),
             text,
+            source: None,
             weight: 0,
+            key: addr_or_line,
             id: FrameId {
                 file_id: FileId::from(0),
                 addr_or_line: addr_or_line,
             },
+            kind: None,
             fg_color: Color32::WHITE,
-            inline_skip: 0,
             bg_color: Color32::from_rgb(0x39, 0x3D, 0x3F),
             children: Vec::with_capacity(1024),
         }
@@ -847,80 +1353,36 @@ impl FlameGraphNode {
         self.weight - self.weight_children()
     }
 
-    /// Insert a trace into the flame graph.
-    pub fn insert_trace(&mut self, trace: &[ArchivedFrame], weight: u64, inline_frames: bool) {
+    /// Insert a stack expanded by [`TraceExpander`] into the flame graph.
+    ///
+    /// Stacks are inserted root first, or leaf first if `inverted`.
+    pub fn insert_trace(&mut self, stack: &[&FlameGraphNode], weight: u64, inverted: bool) {
         let mut node = self;
         node.weight += weight;
 
-        for frame in trace.iter().rev() {
-            let frame: Frame = (*frame).into();
+        let protos: Box<dyn Iterator<Item = &&FlameGraphNode>> = match inverted {
+            false => Box::new(stack.iter()),
+            true => Box::new(stack.iter().rev()),
+        };
 
+        for proto in protos {
             // WARN: this `find` makes flame graph construction O(n^2) in the
             //       worst case, but I found that in the average case this is
             //       actually quite a bit faster than a hashmap/btreemap based
             //       approach. Most nodes only have one or two nodes.
             // TODO: experiment with a mixed approach that uses linear search for
             //       nodes with <8 nodes and a hashmap for larger ones
-            if let Some(mut child) = node.children.iter_mut().find(|x| x.id == frame.id.into()) {
+            if let Some(child) = node.children.iter_mut().find(|x| x.key == proto.key) {
                 child.weight += weight;
-
-                for _ in 0..child.inline_skip {
-                    child = child.children.first_mut().unwrap();
-                    child.weight += weight;
-                }
-
                 node = unsafe { &mut *(child as *mut _) };
                 continue;
             }
 
-            if let FrameKind::Abort = frame.kind {
-                node.children.push(FlameGraphNode {
-                    weight,
-                    fg_color: Color32::BLACK,
-                    bg_color: frame_kind_color(frame.kind),
-                    id: frame.id,
-                    text: match error_spec_by_id(frame.id.addr_or_line) {
-                        Some(spec) => {
-                            format!("<unwinding aborted: {}>", spec.name)
-                        }
-                        None => {
-                            format!("<unwinding aborted: error code {}>", frame.id.addr_or_line)
-                        }
-                    },
-                    inline_skip: 0,
-                    children: vec![],
-                });
-                node = node.children.last_mut().unwrap();
-                continue;
-            }
-
-            let inline_frames = symbolize_frame(frame, inline_frames);
-            assert!(!inline_frames.is_empty());
-            let mut inline_len = Some((inline_frames.len() - 1) as u16);
-
-            for (i, inline_node) in inline_frames.into_iter().enumerate() {
-                assert!(i == 0 || node.children.is_empty());
-
-                node.children.push(FlameGraphNode {
-                    weight,
-                    fg_color: Color32::BLACK,
-                    bg_color: frame_kind_color(frame.kind),
-                    id: inline_node.raw.id,
-                    text: match frame.kind.interp() {
-                        None => inline_node.to_string(),
-                        Some(interp) => format!(
-                            "{} [{}]{}",
-                            inline_node,
-                            interp,
-                            if i > 0 { " [Inline]" } else { "" },
-                        ),
-                    },
-                    inline_skip: inline_len.take().unwrap_or(0),
-                    children: vec![],
-                });
-
-                node = node.children.last_mut().unwrap();
-            }
+            node.children.push(FlameGraphNode {
+                weight,
+                ..(*proto).clone()
+            });
+            node = node.children.last_mut().unwrap();
         }
     }
 
@@ -936,20 +1398,143 @@ impl FlameGraphNode {
     }
 }
 
-/// Build a sandwich view for a given frame ID
-fn build_sandwich_view(root: &FlameGraphNode, frame_id: FrameId) -> SandwichView {
+/// Childless nodes that a raw frame expands to, outermost inline function first.
+fn frame_nodes(frame: Frame, opts: FlameGraphOptions) -> Vec<FlameGraphNode> {
+    let proto = |key, id, func: &str, text, source| FlameGraphNode {
+        weight: 0,
+        fg_color: Color32::BLACK,
+        bg_color: frame_kind_color(frame.kind),
+        key,
+        id,
+        kind: Some(frame.kind),
+        func: func.into(),
+        text,
+        source,
+        children: vec![],
+    };
+
+    if let Some(text) = error_frame_text(frame) {
+        return vec![proto(
+            hash_key(frame.id),
+            frame.id,
+            &text,
+            text.clone(),
+            None,
+        )];
+    }
+
+    if opts.granularity == Granularity::Executable {
+        let name = executable_name(frame);
+        let text = format!("{} [{}]", name, frame_kind_name(frame.kind));
+        return vec![proto(executable_key(frame), frame.id, &name, text, None)];
+    }
+
+    let inline_frames = symbolize_frame(frame, opts.inline_frames);
+    assert!(!inline_frames.is_empty());
+
+    inline_frames
+        .into_iter()
+        .enumerate()
+        .map(|(i, mut inline_node)| {
+            if opts.simplify_names {
+                inline_node.simplify_name();
+            }
+            opts.granularity.strip_details(&mut inline_node);
+
+            let text = match frame.kind {
+                FrameKind::Abort => inline_node.to_string(),
+                _ => format!(
+                    "{} [{}]{}",
+                    inline_node,
+                    frame_kind_name(frame.kind),
+                    if i > 0 { " [Inline]" } else { "" },
+                ),
+            };
+            let source = inline_node.file.clone().map(|file| SourceLine {
+                file,
+                line: inline_node.line_no,
+            });
+
+            let key = opts.granularity.key(&inline_node, i);
+            let func = inline_node.func.as_deref().unwrap_or(&text);
+            proto(key, inline_node.raw.id, func, text.clone(), source)
+        })
+        .collect()
+}
+
+/// Node replacing all frames of the given interpreter folded by [`Transform::Fold`].
+fn folded_node(interp: InterpKind) -> FlameGraphNode {
+    let kind = FrameKind::Regular(interp);
+    let text = format!("[{interp} frames]");
+    FlameGraphNode {
+        weight: 0,
+        fg_color: Color32::BLACK,
+        bg_color: frame_kind_color(kind),
+        key: hash_key(("folded", interp)),
+        id: FrameId {
+            file_id: FileId::from(0),
+            addr_or_line: 0,
+        },
+        kind: Some(kind),
+        func: text.as_str().into(),
+        text,
+        source: None,
+        children: vec![],
+    }
+}
+
+impl TransformFrame for FlameGraphNode {
+    fn func(&self) -> &str {
+        &self.func
+    }
+
+    fn key(&self) -> u64 {
+        self.key
+    }
+
+    fn kind(&self) -> Option<FrameKind> {
+        self.kind
+    }
+}
+
+/// Keys of all frames in the given function, e.g. in different executables.
+fn function_keys(root: &FlameGraphNode, func: &str) -> Vec<u64> {
+    fn collect(node: &FlameGraphNode, func: &str, keys: &mut Vec<u64>) {
+        if node.kind.is_some() && &*node.func == func && !keys.contains(&node.key) {
+            keys.push(node.key);
+        }
+        for child in &node.children {
+            collect(child, func, keys);
+        }
+    }
+
+    let mut keys = Vec::new();
+    collect(root, func, &mut keys);
+    keys
+}
+
+/// Build a sandwich view for the frames merged under the given keys
+///
+/// For inverted graphs, the paths above the frame are its callees and the
+/// subtree below it its callers.
+fn build_sandwich_view(root: &FlameGraphNode, keys: &[u64], inverted: bool) -> SandwichView {
     let mut callers = FlameGraphNode::new_meta_node("Callers".to_string(), 0);
     let mut callees = FlameGraphNode::new_meta_node("Callees".to_string(), 0);
     let mut selected_info: Option<String> = None;
 
     // Traverse the tree and collect all paths that contain the target frame
+    let (above, below) = match inverted {
+        false => (&mut callers, &mut callees),
+        true => (&mut callees, &mut callers),
+    };
     collect_paths_through_frame(
         root,
-        frame_id,
+        keys,
         &mut Vec::new(),
-        &mut callers,
-        &mut callees,
+        above,
+        below,
         &mut selected_info,
+        inverted,
     );
 
     callers.sort_children();
@@ -958,7 +1543,7 @@ fn build_sandwich_view(root: &FlameGraphNode, frame_id: FrameId) -> SandwichView
     let selected_text = selected_info.unwrap_or_else(|| "Unknown Frame".to_string());
 
     SandwichView {
-        selected_frame: frame_id,
+        selected_keys: keys.to_vec(),
         selected_text,
         callers,
         callees,
@@ -966,16 +1551,17 @@ fn build_sandwich_view(root: &FlameGraphNode, frame_id: FrameId) -> SandwichView
 }
 
 /// Helper function to collect caller and callee paths through a specific frame
-fn collect_paths_through_frame(
-    node: &FlameGraphNode,
-    target_frame: FrameId,
-    path_above: &mut Vec<(FrameId, Color32, Color32, String)>,
+fn collect_paths_through_frame<'a>(
+    node: &'a FlameGraphNode,
+    target_keys: &[u64],
+    path_above: &mut Vec<&'a FlameGraphNode>,
     callers_root: &mut FlameGraphNode,
     callees_root: &mut FlameGraphNode,
     selected_info: &mut Option<String>,
+    inverted: bool,
 ) {
     // Check if this node is the target frame
-    if node.id == target_frame {
+    if target_keys.contains(&node.key) {
         // Found the target frame!
         // Store the frame's display information
         if selected_info.is_none() {
@@ -993,19 +1579,22 @@ fn collect_paths_through_frame(
     }
 
     // Continue searching in children
-    // Skip adding the root frame (100% of all CPU cycles) to the path
-    let is_root_frame = node.id.addr_or_line == 0 && node.id.file_id == FileId::from(0);
+    // Skip adding the root frame (100% of all CPU cycles) to the path. Group
+    // nodes are skipped as well in inverted graphs: they aren't callees.
+    let is_meta = node.kind.is_none();
+    let is_root_frame = is_meta && (inverted || node.key == 0);
     if !is_root_frame {
-        path_above.push((node.id, node.bg_color, node.fg_color, node.text.clone()));
+        path_above.push(node);
     }
     for child in &node.children {
         collect_paths_through_frame(
             child,
-            target_frame,
+            target_keys,
             path_above,
             callers_root,
             callees_root,
             selected_info,
+            inverted,
         );
     }
     if !is_root_frame {
@@ -1015,28 +1604,27 @@ fn collect_paths_through_frame(
 
 /// Insert a caller path (inverted, from target up to root)
 /// Insert a caller path so the flamegraph grows downward from the selected frame
-fn insert_caller_path(
-    root: &mut FlameGraphNode,
-    path: &[(FrameId, Color32, Color32, String)],
-    weight: u64,
-) {
+fn insert_caller_path(root: &mut FlameGraphNode, path: &[&FlameGraphNode], weight: u64) {
     root.weight += weight;
     // Walk the path in reverse (from the immediate caller down to the root)
     // so that level 1 is the direct caller and the root is at the top
     let mut current = root;
-    for (frame_id, bg_color, fg_color, text) in path.iter().rev() {
-        // Find or create child with this frame_id
-        if let Some(child) = current.children.iter_mut().find(|x| x.id == *frame_id) {
+    for node in path.iter().rev() {
+        // Find or create child with this key
+        if let Some(child) = current.children.iter_mut().find(|x| x.key == node.key) {
             child.weight += weight;
             current = unsafe { &mut *(child as *mut _) };
         } else {
             current.children.push(FlameGraphNode {
                 weight,
-                fg_color: *fg_color,
-                bg_color: *bg_color,
-                id: *frame_id,
-                text: text.clone(),
-                inline_skip: 0,
+                fg_color: node.fg_color,
+                bg_color: node.bg_color,
+                key: node.key,
+                id: node.id,
+                kind: node.kind,
+                func: node.func.clone(),
+                text: node.text.clone(),
+                source: node.source.clone(),
                 children: vec![],
             });
             current = current.children.last_mut().unwrap();
@@ -1055,8 +1643,8 @@ fn insert_callee_subtree(root: &mut FlameGraphNode, node: &FlameGraphNode) {
 
 /// Recursively insert a callee node and its descendants
 fn insert_callee_node(parent: &mut FlameGraphNode, node: &FlameGraphNode) {
-    // Find or create child with this frame_id
-    let child = if let Some(existing) = parent.children.iter_mut().find(|x| x.id == node.id) {
+    // Find or create child with this key
+    let child = if let Some(existing) = parent.children.iter_mut().find(|x| x.key == node.key) {
         existing.weight += node.weight;
         existing
     } else {
@@ -1064,9 +1652,12 @@ fn insert_callee_node(parent: &mut FlameGraphNode, node: &FlameGraphNode) {
             weight: node.weight,
             fg_color: node.fg_color,
             bg_color: node.bg_color,
+            key: node.key,
             id: node.id,
+            kind: node.kind,
+            func: node.func.clone(),
             text: node.text.clone(),
-            inline_skip: node.inline_skip,
+            source: node.source.clone(),
             children: vec![],
         });
         parent.children.last_mut().unwrap()
@@ -1077,3 +1668,134 @@ fn insert_callee_node(parent: &mut FlameGraphNode, node: &FlameGraphNode) {
         insert_callee_node(child, grandchild);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(key: u64) -> FlameGraphNode {
+        FlameGraphNode::new_meta_node(format!("f{key}"), key)
+    }
+
+    fn opts(group_by: GroupBy, inverted: bool) -> FlameGraphOptions {
+        FlameGraphOptions {
+            inline_frames: true,
+            simplify_names: false,
+            granularity: Granularity::default(),
+            group_by,
+            inverted,
+        }
+    }
+
+    /// Flattens a graph into `(depth, key, weight)` tuples in pre-order.
+    fn flatten(node: &FlameGraphNode, depth: usize, out: &mut Vec<(usize, u64, u64)>) {
+        out.push((depth, node.key, node.weight));
+        for child in &node.children {
+            flatten(child, depth + 1, out);
+        }
+    }
+
+    #[test]
+    fn insert_trace() {
+        // Stacks are root-first: main -> a -> leaf.
+        let (main, a, b, leaf) = (node(1), node(2), node(3), node(4));
+
+        let mut root = node(0);
+        root.insert_trace(&[&main, &a, &leaf], 3, false);
+        root.insert_trace(&[&main, &b, &leaf], 2, false);
+        root.sort_children();
+        let mut nodes = vec![];
+        flatten(&root, 0, &mut nodes);
+        assert_eq!(
+            nodes,
+            [
+                (0, 0, 5),
+                (1, 1, 5),
+                (2, 2, 3),
+                (3, 4, 3),
+                (2, 3, 2),
+                (3, 4, 2)
+            ]
+        );
+
+        // Inverted: the shared leaf becomes the single top-level node and
+        // its callers are split below it.
+        let mut root = node(0);
+        root.insert_trace(&[&main, &a, &leaf], 3, true);
+        root.insert_trace(&[&main, &b, &leaf], 2, true);
+        root.sort_children();
+        let mut nodes = vec![];
+        flatten(&root, 0, &mut nodes);
+        assert_eq!(
+            nodes,
+            [
+                (0, 0, 5),
+                (1, 4, 5),
+                (2, 2, 3),
+                (3, 1, 3),
+                (2, 3, 2),
+                (3, 1, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn grouping() {
+        let (main, leaf) = (node(10), node(11));
+
+        let mut graph = GroupedGraph::new(opts(GroupBy::Process, true));
+        graph.insert("nginx", &[&main, &leaf], 1);
+        graph.insert("redis", &[&main, &leaf], 4);
+        graph.insert("nginx", &[&main], 2);
+        let root = graph.finish();
+
+        assert_eq!(root.weight, 7);
+        let groups: Vec<_> = root
+            .children
+            .iter()
+            .map(|x| (x.text.as_str(), x.weight))
+            .collect();
+        let icon = GroupBy::Process.icon();
+        assert_eq!(
+            groups,
+            [
+                (&*format!("{icon} redis"), 4),
+                (&*format!("{icon} nginx"), 3)
+            ]
+        );
+
+        // Inverted within the group: leaf first, then main.
+        let nginx = &root.children[1];
+        let mut nodes = vec![];
+        flatten(nginx, 0, &mut nodes);
+        assert_eq!(nodes[1..], [(1, 10, 2), (1, 11, 1), (2, 10, 1)]);
+
+        // Without grouping, the group name is ignored.
+        let mut graph = GroupedGraph::new(opts(GroupBy::None, false));
+        graph.insert("nginx", &[&main, &leaf], 1);
+        graph.insert("redis", &[&main], 2);
+        let root = graph.finish();
+        let mut nodes = vec![];
+        flatten(&root, 0, &mut nodes);
+        assert_eq!(nodes, [(0, 0, 3), (1, 10, 3), (2, 11, 1)]);
+    }
+
+    #[test]
+    fn group_names() {
+        let labels =
+            |process: Option<&str>, pod: Option<&str>, container: Option<&str>| TraceLabels {
+                process: process.map(Into::into),
+                pod_name: pod.map(Into::into),
+                container_name: container.map(Into::into),
+            };
+
+        assert_eq!(process_group(&labels(Some("nginx"), None, None)), "nginx");
+        assert_eq!(process_group(&labels(None, None, None)), "<unknown>");
+
+        let group = |pod, container| container_group(&labels(None, pod, container)).into_owned();
+        assert_eq!(group(Some("web-1"), Some("nginx")), "web-1/nginx");
+        assert_eq!(group(Some("web-1"), None), "web-1");
+        assert_eq!(group(None, Some("nginx")), "nginx");
+        assert_eq!(group(None, None), "<no container>");
+    }
+}
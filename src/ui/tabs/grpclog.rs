@@ -16,9 +16,10 @@
 // under the License.
 
 use super::*;
+use crate::collector::validate::{Report, Severity};
 use crate::collector::{Collector, LoggedRequest};
 use eframe::emath::Align;
-use egui::{CollapsingHeader, Label, Layout, RichText, ScrollArea, Sense};
+use egui::{CollapsingHeader, Color32, Label, Layout, RichText, ScrollArea, Sense};
 use egui_extras::{Column, TableBuilder};
 use egui_phosphor::regular as icons;
 use serde_json::{Map as JsonMap, Value as JsonValue};
@@ -51,7 +52,7 @@ impl TabWidget for GrpcLogTab {
     }
 
     fn show_tab_selector(&self, cfg: &DevfilerConfig) -> bool {
-        cfg.dev_mode
+        cfg.dev_mode || cfg.collector.validation_enabled()
     }
 }
 
@@ -64,12 +65,13 @@ impl GrpcLogTab {
             .striped(true)
             .cell_layout(Layout::left_to_right(Align::Center))
             .column(Column::auto())
+            .column(Column::auto())
             .column(Column::remainder().clip(true))
             .max_scroll_height(f32::INFINITY);
 
         table
             .header(20.0, |mut header| {
-                for column in ["Time", "Kind"] {
+                for column in ["Time", "Validation", "Kind"] {
                     header.col(|ui| drop(ui.strong(column)));
                 }
             })
@@ -85,6 +87,11 @@ impl GrpcLogTab {
                                 self.selected_request = Some(Arc::clone(&logged_msg));
                             }
                         });
+                        row.col(|ui| {
+                            if let Some(report) = &logged_msg.report {
+                                ui.label(report_summary(report));
+                            }
+                        });
                         row.col(|ui| drop(ui.label(logged_msg.kind)));
                     });
                 }
@@ -107,6 +114,15 @@ impl GrpcLogTab {
             Self::draw_meta_table(ui, selected);
         });
 
+        if let Some(report) = &selected.report {
+            ui.push_id("grpc-req-validation", |ui| {
+                ui.add_space(20.0);
+                ui.heading(format!("{} Validation report", icons::SEAL_CHECK));
+                ui.separator();
+                Self::draw_report_table(ui, report);
+            });
+        }
+
         ui.push_id("grpc-req-payload", |ui| {
             ui.add_space(20.0);
             ui.heading(format!("{} gRPC request payload", icons::TREE_STRUCTURE));
@@ -151,6 +167,46 @@ impl GrpcLogTab {
             });
     }
 
+    fn draw_report_table(ui: &mut Ui, report: &Report) {
+        if report.findings.is_empty() {
+            ui.label("No problems found.");
+            return;
+        }
+
+        let report_table = TableBuilder::new(ui)
+            .striped(true)
+            .cell_layout(Layout::left_to_right(Align::Center))
+            .column(Column::auto())
+            .column(Column::auto().clip(true))
+            .column(Column::remainder().clip(true))
+            .max_scroll_height(200.0);
+
+        report_table
+            .header(20.0, |mut header| {
+                for column in ["Severity", "Path", "Message"] {
+                    header.col(|ui| drop(ui.strong(column)));
+                }
+            })
+            .body(|mut body| {
+                let mut findings = report.findings.iter().collect::<Vec<_>>();
+                findings.sort_by_key(|x| std::cmp::Reverse(x.severity));
+
+                for finding in findings {
+                    body.row(20.0, |mut row| {
+                        row.col(|ui| drop(ui.label(severity_text(finding.severity))));
+                        row.col(|ui| drop(ui.monospace(&finding.path)));
+                        row.col(|ui| {
+                            ui.label(&finding.message).on_hover_text(&finding.message);
+                        });
+                    });
+                }
+            });
+
+        if report.omitted > 0 {
+            ui.label(format!("... and {} more", report.omitted));
+        }
+    }
+
     fn recurse_msg_contents(ui: &mut Ui, default_open: bool, key: &str, value: Categorized<'_>) {
         let node_text = format!("{} {}", value.icon(), key);
         let node_text = RichText::new(node_text).monospace();
@@ -196,6 +252,41 @@ impl GrpcLogTab {
     }
 }
 
+fn severity_color(severity: Severity) -> Color32 {
+    match severity {
+        Severity::Info => Color32::GRAY,
+        Severity::Warning => Color32::ORANGE,
+        Severity::Error => Color32::RED,
+    }
+}
+
+fn severity_text(severity: Severity) -> RichText {
+    let icon = match severity {
+        Severity::Info => icons::INFO,
+        Severity::Warning => icons::WARNING,
+        Severity::Error => icons::X_CIRCLE,
+    };
+    RichText::new(format!("{icon} {severity}")).color(severity_color(severity))
+}
+
+fn report_summary(report: &Report) -> RichText {
+    let Some(severity) = report.max_severity() else {
+        return RichText::new(format!("{} ok", icons::CHECK_CIRCLE)).color(Color32::GREEN);
+    };
+
+    let text = match severity {
+        Severity::Error => format!(
+            "{} errors, {} warnings",
+            report.count(Severity::Error),
+            report.count(Severity::Warning)
+        ),
+        Severity::Warning => format!("{} warnings", report.count(Severity::Warning)),
+        Severity::Info => format!("{} notes", report.count(Severity::Info)),
+    };
+
+    RichText::new(text).color(severity_color(severity))
+}
+
 enum Categorized<'v> {
     Scalar(String),
     Array(&'v Vec<JsonValue>),
@@ -15,7 +15,7 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use crate::storage::{SampleKind, UtcTimestamp};
+use crate::storage::{FrameId, SampleKind, UtcTimestamp};
 use crate::ui::app::DevfilerConfig;
 use eframe::egui::Ui;
 use std::fmt;
@@ -23,8 +23,11 @@ use std::fmt;
 #[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
 pub enum Tab {
     FlameGraph,
+    CallTree,
     FlameScope,
     TopFunctions,
+    Source,
+    Disassembly,
     Executables,
     Log,
 
@@ -43,14 +46,29 @@ pub enum TabAction {
         start: UtcTimestamp,
         end: UtcTimestamp,
     },
+
+    /// Show the given source file, scrolled to the line if known
+    ShowSource { file: String, line: Option<u32> },
+
+    /// Show the disassembly of the native function containing the frame
+    ShowDisassembly { frame: FrameId },
+
+    /// Open a source location in the configured external editor
+    OpenInEditor { file: String, line: Option<u32> },
+
+    /// Show the flamegraph sandwich view of the given function
+    ShowSandwich { func: String },
 }
 
 impl fmt::Display for Tab {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.write_str(match self {
             Tab::FlameGraph => "Flamegraph",
+            Tab::CallTree => "Call tree",
             Tab::FlameScope => "FlameScope",
             Tab::TopFunctions => "Top functions",
+            Tab::Source => "Source",
+            Tab::Disassembly => "Disassembly",
             Tab::Executables => "Executables",
             Tab::Log => "Log",
             Tab::TraceFreq => "Trace frequency",
@@ -77,6 +95,11 @@ pub trait TabWidget {
         end: UtcTimestamp,
     ) -> Option<TabAction>;
 
+    /// Handle an action returned by another tab.
+    ///
+    /// Invoked on all tabs, regardless of whether they are active.
+    fn handle_action(&mut self, _action: &TabAction) {}
+
     /// Whether the main view should show the button that enables this tab.
     fn show_tab_selector(&self, _cfg: &DevfilerConfig) -> bool {
         true
@@ -89,12 +112,21 @@ pub use executables::*;
 mod top_funcs;
 pub use top_funcs::*;
 
+mod source;
+pub use source::*;
+
+mod disasm;
+pub use disasm::*;
+
 mod trace_freq;
 pub use trace_freq::*;
 
 mod flamegraph;
 pub use flamegraph::*;
 
+mod call_tree;
+pub use call_tree::*;
+
 mod flamescope;
 pub use flamescope::*;
 
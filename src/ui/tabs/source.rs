@@ -0,0 +1,319 @@
+// Copyright Elasticsearch B.V. and/or licensed to Elasticsearch B.V. under one
+// or more contributor license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use super::*;
+use crate::storage::{call_site_frame, symbolize_frame, Table, DB};
+use crate::ui::cached::Cached;
+use crate::ui::pathmap::PathMap;
+use crate::ui::util::{draw_heat_count, humanize_count};
+use egui::{Align, Color32, Layout, RichText};
+use egui_extras::{Column, TableBuilder};
+use egui_phosphor::regular as icons;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+const ROW_HEIGHT: f32 = 16.0;
+
+#[derive(Default)]
+pub struct SourceTab {
+    target: Option<SourceTarget>,
+    loaded: Option<LoadedFile>,
+    counts: Cached<LineCounts>,
+    scroll_pending: bool,
+}
+
+/// File (and optionally line) to show.
+#[derive(Debug, Clone)]
+struct SourceTarget {
+    file: String,
+    line: Option<u32>,
+}
+
+/// Source file loaded from disk.
+struct LoadedFile {
+    file: String,
+    path_map: PathMap,
+    result: Result<(PathBuf, Vec<String>), String>,
+}
+
+impl LoadedFile {
+    fn load(file: &str, path_map: &PathMap) -> Self {
+        LoadedFile {
+            file: file.to_owned(),
+            path_map: path_map.clone(),
+            result: path_map.load_source(file),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Counts {
+    zelf: u64,
+    total: u64,
+}
+
+/// Per-line sample counts for one source file.
+#[derive(Debug, Default)]
+struct LineCounts {
+    file: String,
+    total_samples: u64,
+    lines: HashMap<u32, Counts>,
+}
+
+impl LineCounts {
+    fn max_total(&self) -> u64 {
+        self.lines.values().map(|x| x.total).max().unwrap_or(0)
+    }
+
+    fn hottest_line(&self) -> Option<u32> {
+        self.lines
+            .iter()
+            .max_by_key(|(line, counts)| (counts.total, std::cmp::Reverse(**line)))
+            .map(|(line, _)| *line)
+    }
+}
+
+impl TabWidget for SourceTab {
+    fn id(&self) -> Tab {
+        Tab::Source
+    }
+
+    fn update(
+        &mut self,
+        ui: &mut Ui,
+        cfg: &DevfilerConfig,
+        kind: SampleKind,
+        start: UtcTimestamp,
+        end: UtcTimestamp,
+    ) -> Option<TabAction> {
+        let Some(target) = self.target.clone() else {
+            ui.label("Use \"Show source\" on a frame in the flamegraph or top functions.");
+            return None;
+        };
+
+        let stale = self
+            .loaded
+            .as_ref()
+            .is_none_or(|x| x.file != target.file || x.path_map != cfg.path_map);
+        if stale {
+            self.loaded = Some(LoadedFile::load(&target.file, &cfg.path_map));
+        }
+
+        let file = target.file.clone();
+        let counts = self
+            .counts
+            .get_or_create((&target.file, kind, start, end), move || {
+                query_line_counts(file, kind, start, end)
+            });
+        let counts_ready = counts.file == target.file;
+
+        let mut reload = false;
+        let mut action = None;
+        ui.add_space(5.0);
+        ui.horizontal(|ui| {
+            ui.strong("Source:");
+            ui.monospace(&target.file);
+            if ui
+                .button(format!("{} Reload", icons::ARROW_CLOCKWISE))
+                .clicked()
+            {
+                reload = true;
+            }
+            if ui
+                .button(format!("{} Open in editor", icons::PENCIL_SIMPLE))
+                .clicked()
+            {
+                action = Some(TabAction::OpenInEditor {
+                    file: target.file.clone(),
+                    line: target.line.or_else(|| counts.hottest_line()),
+                });
+            }
+        });
+
+        let loaded = self.loaded.as_ref().unwrap();
+        let lines = match loaded.result {
+            Ok((ref path, ref lines)) => {
+                ui.label(format!("Loaded from {}", path.display()));
+                Some(lines)
+            }
+            Err(ref e) => {
+                ui.colored_label(Color32::ORANGE, e);
+                ui.label("Configure path remapping in the settings to locate the file.");
+                None
+            }
+        };
+
+        if counts_ready {
+            let total: u64 = counts.lines.values().map(|x| x.zelf).sum();
+            ui.label(format!(
+                "{} samples with self time in this file ({:.02}% of all samples)",
+                humanize_count(total),
+                total as f64 / counts.total_samples.max(1) as f64 * 100.0,
+            ));
+        } else {
+            ui.label("Loading sample counts ...");
+        }
+        ui.separator();
+
+        // Without the file, still list the lines that have samples.
+        let rows: Vec<u32> = match lines {
+            Some(lines) => (1..=lines.len() as u32).collect(),
+            None if counts_ready => {
+                let mut rows: Vec<_> = counts.lines.keys().copied().collect();
+                rows.sort_unstable();
+                rows
+            }
+            None => vec![],
+        };
+
+        let mut table = TableBuilder::new(ui)
+            .striped(true)
+            .resizable(true)
+            .cell_layout(Layout::left_to_right(Align::Center))
+            .column(Column::exact(70.0))
+            .column(Column::exact(70.0))
+            .column(Column::exact(50.0))
+            .column(Column::remainder().clip(true))
+            .max_scroll_height(f32::INFINITY);
+
+        if self.scroll_pending && (target.line.is_some() || counts_ready) {
+            let line = target.line.or_else(|| counts.hottest_line());
+            if let Some(row) = line.and_then(|x| rows.iter().position(|&y| y == x)) {
+                table = table.scroll_to_row(row, Some(Align::Center));
+            }
+            self.scroll_pending = false;
+        }
+
+        let max_total = counts.max_total().max(1);
+        let total_samples = counts.total_samples.max(1);
+
+        table
+            .header(20.0, |mut header| {
+                for col in ["Self", "Total", "Line", "Source"] {
+                    header.col(|ui| {
+                        ui.strong(col);
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(ROW_HEIGHT, rows.len(), |mut row| {
+                    let line_no = rows[row.index()];
+                    let line_counts = counts_ready
+                        .then(|| counts.lines.get(&line_no).copied())
+                        .flatten()
+                        .unwrap_or_default();
+
+                    for count in [line_counts.zelf, line_counts.total] {
+                        row.col(|ui| draw_heat_count(ui, count, max_total, total_samples));
+                    }
+
+                    row.col(|ui| {
+                        let text = RichText::new(line_no.to_string()).monospace();
+                        let text = if Some(line_no) == target.line {
+                            text.strong()
+                        } else {
+                            text.weak()
+                        };
+                        ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                            ui.label(text);
+                        });
+                    });
+
+                    row.col(|ui| {
+                        let text = lines
+                            .and_then(|x| x.get(line_no as usize - 1))
+                            .map_or("", String::as_str);
+                        ui.label(RichText::new(text).monospace());
+                    });
+                });
+            });
+
+        if reload {
+            self.loaded = None;
+        }
+
+        action
+    }
+
+    fn handle_action(&mut self, action: &TabAction) {
+        if let TabAction::ShowSource { file, line } = action {
+            self.target = Some(SourceTarget {
+                file: file.clone(),
+                line: *line,
+            });
+            self.scroll_pending = true;
+        }
+    }
+
+    fn show_tab_selector(&self, _cfg: &DevfilerConfig) -> bool {
+        self.target.is_some()
+    }
+}
+
+/// Count the samples attributed to each line of the given source file.
+///
+/// A sample counts towards the self count of a line if the line is the leaf
+/// (including inline frames) and towards the total count if the line occurs
+/// anywhere in the trace. Non-leaf frames are attributed to their call site,
+/// see [`call_site_frame`].
+fn query_line_counts(
+    file: String,
+    kind: SampleKind,
+    start: UtcTimestamp,
+    end: UtcTimestamp,
+) -> LineCounts {
+    let mut cache = lru::LruCache::new((16 * 1024).try_into().unwrap());
+    let mut seen_this_trace = HashSet::new();
+    let mut counts = LineCounts::default();
+
+    for (_, tc) in DB.trace_events.time_range(start, end, kind) {
+        let tc = tc.get();
+        let count = u64::from(tc.count);
+        counts.total_samples += count;
+
+        let Some(trace) = DB.stack_traces.get(tc.trace_hash) else {
+            continue;
+        };
+
+        seen_this_trace.clear();
+        for (frame_idx, frame) in trace.get().iter().enumerate() {
+            let frame = call_site_frame((*frame).into(), frame_idx == 0);
+            let symbolized = cache.get_or_insert(frame, || symbolize_frame(frame, true));
+
+            // Inline frames are ordered outermost first: the leaf is last.
+            for (inline_idx, inline) in symbolized.iter().rev().enumerate() {
+                if inline.file.as_deref() != Some(file.as_str()) {
+                    continue;
+                }
+                let Some(line) = inline.line_no else {
+                    continue;
+                };
+
+                let line_counts = counts.lines.entry(line).or_default();
+                if frame_idx == 0 && inline_idx == 0 {
+                    line_counts.zelf += count;
+                }
+                if seen_this_trace.insert(line) {
+                    line_counts.total += count;
+                }
+            }
+        }
+    }
+
+    counts.file = file;
+    counts
+}
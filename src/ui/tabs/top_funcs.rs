@@ -16,18 +16,21 @@
 // under the License.
 
 use super::*;
-use crate::storage::{symbolize_frame, FrameKind, Table, DB};
+use crate::storage::{simplify_name, symbolize_frame, Frame, FrameKind, Table, TraceHash, DB};
+use crate::ui::aggregate::{executable_name, Granularity};
 use crate::ui::cached::Cached;
+use crate::ui::timeaxis;
 use crate::ui::util::{
     clearable_line_edit, draw_heat_map, frame_kind_color, humanize_count, plot_color,
 };
-use egui::{Align, Color32, Layout, Sense, Stroke, StrokeKind};
+use egui::{Align, Button, Color32, Layout, RichText, ScrollArea, Sense, Stroke, StrokeKind};
 use egui_extras::{Column, TableBuilder};
 use egui_phosphor::regular as icons;
+use egui_plot::{Axis, AxisHints, HPlacement, Legend, Line, LineStyle, Plot};
 use nohash_hasher::IntSet;
 use std::cmp::min;
 use std::collections::hash_map::DefaultHasher;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher};
 use std::iter;
 use std::iter::FusedIterator;
@@ -36,11 +39,54 @@ use std::sync::mpsc;
 /// Maximum length of the top function table.
 const MAX_LOCATIONS: usize = 500;
 
-#[derive(Default)]
+/// Maximum number of callers and callees listed for the selected function.
+const MAX_NEIGHBORS: usize = 100;
+
+/// Maximum number of functions in the share over time chart.
+const MAX_CHARTED: usize = 8;
+
+/// Number of time buckets in the share over time chart.
+const SHARE_BUCKETS: u64 = 120;
+
+/// Name of the global sample rate series in the share over time chart.
+const ALL_SAMPLES: &str = "All samples";
+
 pub struct TopFuncsTab {
     sort_field: SortField,
     cache: Cached<TopFuncs>,
     filter: String,
+    /// Function whose callers and callees are shown.
+    selected: Option<Location>,
+    /// Previously selected functions, for navigating back.
+    history: Vec<Location>,
+    neighbors: Cached<Neighbors>,
+    /// Functions shown in the share over time chart.
+    charted: Vec<Location>,
+    /// Whether the chart shows the self and/or total share.
+    chart_self: bool,
+    chart_total: bool,
+    shares: Cached<FunctionShares>,
+    /// Granularity and name simplification that the selected and charted
+    /// locations were created with.
+    location_opts: (Granularity, bool),
+}
+
+impl Default for TopFuncsTab {
+    fn default() -> Self {
+        Self {
+            sort_field: SortField::default(),
+            cache: Default::default(),
+            filter: String::new(),
+            selected: None,
+            history: Vec::new(),
+            neighbors: Default::default(),
+            charted: Vec::new(),
+            chart_self: true,
+            chart_total: true,
+            shares: Default::default(),
+            location_opts: (Granularity::default(), false),
+        }
+    }
 }
 
 impl TabWidget for TopFuncsTab {
@@ -51,23 +97,57 @@ impl TabWidget for TopFuncsTab {
     fn update(
         &mut self,
         ui: &mut Ui,
-        _cfg: &DevfilerConfig,
+        cfg: &DevfilerConfig,
         _kind: SampleKind,
         start: UtcTimestamp,
         end: UtcTimestamp,
     ) -> Option<TabAction> {
         let sort_field = self.sort_field;
         let filter = self.filter.clone();
+        let simplify_names = cfg.simplify_names;
+        let granularity = cfg.granularity;
+
+        // Locations of other granularities no longer match any row.
+        if self.location_opts != (granularity, simplify_names) {
+            self.location_opts = (granularity, simplify_names);
+            self.selected = None;
+            self.history.clear();
+            self.charted.clear();
+        }
 
         let TopFuncs {
             total_funcs,
             total_samples,
             ref top,
-        } = *self
-            .cache
-            .get_or_create((start, end, sort_field, &self.filter), move || {
-                query_top_funcs(start, end, sort_field, filter)
-            });
+        } = *self.cache.get_or_create(
+            (
+                start,
+                end,
+                sort_field,
+                &self.filter,
+                simplify_names,
+                granularity,
+            ),
+            move || query_top_funcs(start, end, sort_field, filter, simplify_names, granularity),
+        );
+
+        let mut action = None;
+        if self.selected.is_some() {
+            egui::SidePanel::right("top-funcs-neighbors")
+                .resizable(true)
+                .default_width(450.0)
+                .show_inside(ui, |ui| {
+                    action = self.draw_neighbors_panel(ui, start, end, simplify_names, granularity);
+                });
+        }
+        if !self.charted.is_empty() {
+            egui::TopBottomPanel::bottom("top-funcs-share-chart")
+                .resizable(true)
+                .default_height(250.0)
+                .show_inside(ui, |ui| {
+                    self.draw_share_chart(ui, start, end, simplify_names, granularity);
+                });
+        }
 
         ui.add_space(5.0);
         ui.columns(2, |ui| {
@@ -92,6 +172,7 @@ impl TabWidget for TopFuncsTab {
         let table = TableBuilder::new(ui)
             .striped(true)
             .resizable(true)
+            .sense(Sense::click())
             .cell_layout(Layout::left_to_right(Align::Center))
             .column(Column::exact(85.0))
             .column(Column::exact(85.0))
@@ -137,6 +218,8 @@ impl TabWidget for TopFuncsTab {
                     }
 
                     body.row(20.0, |mut row| {
+                        row.set_selected(self.selected.as_ref() == Some(location));
+
                         // Self (%)
                         row.col(|ui| {
                             let ratio = counts.zelf as f32 / total_samples.max(1) as f32;
@@ -159,34 +242,305 @@ impl TabWidget for TopFuncsTab {
                         row.col(|ui| {
                             ui.add_space(3.0);
                             draw_frame_type_square(ui, location.kind);
-                            ui.label(&location.func);
+                            ui.label(location.display_name());
                         });
                         // File name
                         row.col(|ui| {
                             if let Some(ref file) = location.file {
-                                ui.label(file);
+                                match location.line {
+                                    Some(line) => ui.label(format!("{file}:{line}")),
+                                    None => ui.label(file),
+                                };
                             }
                         });
                         // Heat map
                         row.col(|ui| {
                             draw_func_heatmap(ui, &counts);
                         });
+
+                        let response = row.response();
+                        if response.clicked() && self.selected.as_ref() != Some(location) {
+                            self.select(location.clone());
+                        }
+                        response.context_menu(|ui| {
+                            if let Some(chosen) = draw_context_menu(ui, location) {
+                                action = Some(chosen);
+                                ui.close();
+                            }
+                            ui.separator();
+                            if self.draw_chart_button(ui, location) {
+                                ui.close();
+                            }
+                        });
                     });
                 }
             });
-        None
+
+        action
+    }
+}
+
+impl TopFuncsTab {
+    /// Select a function, remembering the previous one for navigating back.
+    fn select(&mut self, location: Location) {
+        if let Some(prev) = self.selected.replace(location) {
+            self.history.push(prev);
+        }
+    }
+
+    /// Draw the panel listing the callers and callees of the selected function.
+    fn draw_neighbors_panel(
+        &mut self,
+        ui: &mut Ui,
+        start: UtcTimestamp,
+        end: UtcTimestamp,
+        simplify_names: bool,
+        granularity: Granularity,
+    ) -> Option<TabAction> {
+        let selected = self.selected.clone()?;
+        let mut action = None;
+        let mut navigate = None;
+
+        ui.add_space(5.0);
+        ui.horizontal(|ui| {
+            let back = ui.add_enabled(!self.history.is_empty(), Button::new(icons::ARROW_LEFT));
+            if back.on_hover_text("Previous function").clicked() {
+                self.selected = self.history.pop();
+            }
+            if ui.button(icons::X).on_hover_text("Close").clicked() {
+                self.selected = None;
+                self.history.clear();
+            }
+            draw_frame_type_square(ui, selected.kind);
+            ui.strong(selected.display_name());
+        });
+
+        let key = (start, end, &selected, simplify_names, granularity);
+        let target = selected.clone();
+        let neighbors = self.neighbors.get_or_create(key, move || {
+            query_neighbors(start, end, target, simplify_names, granularity)
+        });
+
+        if neighbors.target.as_ref() != Some(&selected) {
+            ui.label("Loading ...");
+            return None;
+        }
+
+        ui.label(format!(
+            "{} samples total, {} with the function as the leaf.",
+            humanize_count(neighbors.total),
+            humanize_count(neighbors.zelf),
+        ));
+        ui.horizontal(|ui| {
+            if ui.button("Show sandwich view in flamegraph").clicked() {
+                action = Some(TabAction::ShowSandwich {
+                    func: selected.func.clone(),
+                });
+            }
+            self.draw_chart_button(ui, &selected);
+        });
+        ui.separator();
+
+        let height = (ui.available_height() - 40.0) / 2.0;
+        for (title, list) in [
+            ("Callers", &neighbors.callers),
+            ("Callees", &neighbors.callees),
+        ] {
+            ui.strong(title);
+            ScrollArea::vertical()
+                .id_salt(title)
+                .max_height(height)
+                .auto_shrink([false, true])
+                .show(ui, |ui| {
+                    if list.is_empty() {
+                        ui.weak("None");
+                    }
+                    for (location, count) in list {
+                        ui.horizontal(|ui| {
+                            let ratio = *count as f32 / neighbors.total.max(1) as f32;
+                            ui.add_sized(
+                                [60.0, 18.0],
+                                egui::Label::new(format!("{:.02}%", ratio * 100.0)),
+                            );
+                            ui.add_sized(
+                                [60.0, 18.0],
+                                egui::Label::new(humanize_count(*count).to_string()),
+                            );
+                            draw_frame_type_square(ui, location.kind);
+                            let link = ui.link(location.display_name());
+                            if link.on_hover_text("Show callers and callees").clicked() {
+                                navigate = Some(location.clone());
+                            }
+                        });
+                    }
+                });
+        }
+
+        if let Some(location) = navigate {
+            self.select(location);
+        }
+
+        action
+    }
+
+    /// Draw a button adding the function to or removing it from the chart.
+    ///
+    /// Returns whether the button was clicked.
+    fn draw_chart_button(&mut self, ui: &mut Ui, location: &Location) -> bool {
+        let pos = self.charted.iter().position(|x| x == location);
+        let (text, enabled) = match pos {
+            Some(_) => ("Remove from chart", true),
+            None => ("Add to chart", self.charted.len() < MAX_CHARTED),
+        };
+
+        let button = ui.add_enabled(
+            enabled,
+            Button::new(format!("{} {text}", icons::CHART_LINE)),
+        );
+        if !button.clicked() {
+            return false;
+        }
+
+        match pos {
+            Some(pos) => {
+                self.charted.remove(pos);
+            }
+            None => self.charted.push(location.clone()),
+        }
+        true
+    }
+
+    /// Draw the share of samples of the charted functions over time.
+    fn draw_share_chart(
+        &mut self,
+        ui: &mut Ui,
+        start: UtcTimestamp,
+        end: UtcTimestamp,
+        simplify_names: bool,
+        granularity: Granularity,
+    ) {
+        ui.add_space(5.0);
+        ui.horizontal_wrapped(|ui| {
+            ui.strong("Share of samples over time");
+            ui.checkbox(&mut self.chart_total, "Total");
+            ui.checkbox(&mut self.chart_self, "Self");
+
+            let mut remove = None;
+            for (i, location) in self.charted.iter().enumerate() {
+                let text = format!("{} {}", location.display_name(), icons::X);
+                let chip = Button::new(RichText::new(text).color(plot_color(i))).small();
+                if ui.add(chip).on_hover_text("Remove from chart").clicked() {
+                    remove = Some(i);
+                }
+            }
+            if let Some(i) = remove {
+                self.charted.remove(i);
+            }
+        });
+
+        let key = (start, end, &self.charted, simplify_names, granularity);
+        let targets = self.charted.clone();
+        let shares = self.shares.get_or_create(key, move || {
+            query_function_shares(start, end, targets, simplify_names, granularity)
+        });
+
+        if shares.targets != self.charted {
+            ui.label("Loading ...");
+            return;
+        }
+
+        // The sample rate is scaled to the share axis, with its own axis on the right.
+        let max_samples = shares.samples.iter().copied().max().unwrap_or(0).max(1) as f64;
+        let plot = Plot::new("top-funcs-share-chart")
+            .custom_x_axes(vec![timeaxis::mk_time_axis(Axis::X)])
+            .custom_y_axes(vec![
+                AxisHints::new_y().label("Share (%)"),
+                AxisHints::new_y()
+                    .label("Samples")
+                    .placement(HPlacement::Right)
+                    .formatter(move |mark, _| format!("{:.0}", mark.value / 100.0 * max_samples)),
+            ])
+            .x_grid_spacer(timeaxis::mk_time_grid)
+            .include_y(0.0)
+            .include_y(100.0)
+            .legend(Legend::default())
+            .label_formatter(move |name, val| {
+                let time = timeaxis::ts2chrono(val.x as i64);
+                match name {
+                    ALL_SAMPLES => format!(
+                        "{name}\nTime: {time}\nSamples: {:.0}",
+                        val.y / 100.0 * max_samples
+                    ),
+                    _ => format!("{name}\nTime: {time}\nShare: {:.02}%", val.y),
+                }
+            });
+
+        plot.show(ui, |pui| {
+            let times = || shares.times.iter().map(|&x| x as f64);
+            let rate: Vec<_> = times()
+                .zip(&shares.samples)
+                .map(|(t, &n)| [t, n as f64 / max_samples * 100.0])
+                .collect();
+            pui.line(
+                Line::new(ALL_SAMPLES, rate)
+                    .color(Color32::GRAY)
+                    .fill(0.0_f32),
+            );
+
+            for (i, (location, counts)) in shares.targets.iter().zip(&shares.counts).enumerate() {
+                // Buckets without samples don't have a share.
+                let share = |count: fn(&(u64, u64)) -> u64| -> Vec<[f64; 2]> {
+                    times()
+                        .zip(counts.iter().zip(&shares.samples))
+                        .filter(|(_, (_, n))| **n > 0)
+                        .map(|(t, (x, &n))| [t, count(x) as f64 / n as f64 * 100.0])
+                        .collect()
+                };
+
+                let name = location.display_name();
+                if self.chart_total {
+                    let line = Line::new(format!("{name} (total)"), share(|x| x.1));
+                    pui.line(line.color(plot_color(i)));
+                }
+                if self.chart_self {
+                    let line = Line::new(format!("{name} (self)"), share(|x| x.0));
+                    pui.line(line.color(plot_color(i)).style(LineStyle::dashed_loose()));
+                }
+            }
+        });
     }
 }
 
+/// Populates the context menu of a function row.
+fn draw_context_menu(ui: &mut Ui, location: &Location) -> Option<TabAction> {
+    let show_source = ui.add_enabled(location.file.is_some(), egui::Button::new("Show source"));
+    if show_source.clicked() {
+        return Some(TabAction::ShowSource {
+            file: location.file.clone()?,
+            line: location.line,
+        });
+    }
+
+    let open = ui.add_enabled(location.file.is_some(), egui::Button::new("Open in editor"));
+    if open.clicked() {
+        return Some(TabAction::OpenInEditor {
+            file: location.file.clone()?,
+            line: location.line,
+        });
+    }
+
+    None
+}
+
 /// Draws an humanized count column.
-fn draw_count_column(ui: &mut Ui, count: u64) {
+pub(super) fn draw_count_column(ui: &mut Ui, count: u64) {
     let layout = Layout::right_to_left(Align::Center);
     let text = humanize_count(count).to_string();
     ui.with_layout(layout, |ui| ui.label(text));
 }
 
 /// Draws a percentage column field, with a "progress bar" in the background.
-fn draw_percent_column(ui: &mut Ui, perc: f32) {
+pub(super) fn draw_percent_column(ui: &mut Ui, perc: f32) {
     if perc > 0.00_1 {
         let mut rect = ui.available_rect_before_wrap();
         rect = rect.shrink(3.0);
@@ -253,14 +607,26 @@ struct TopFuncs {
     pub top: Vec<(Location, Counts)>,
 }
 
-#[derive(Debug, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 struct Location {
     pub kind: FrameKind,
     pub func: String,
     pub file: Option<String>,
+    /// Only set if the granularity distinguishes source lines.
+    pub line: Option<u32>,
+    /// Only set if the granularity distinguishes addresses.
+    pub addr: Option<u64>,
 }
 
 impl Location {
+    /// Function name, along with the address if the granularity distinguishes them.
+    fn display_name(&self) -> String {
+        match self.addr {
+            Some(addr) => format!("{} @ 0x{addr:x}", self.func),
+            None => self.func.clone(),
+        }
+    }
+
     fn matches_filter(&self, filter: &str) -> bool {
         if self.func.contains(filter) {
             return true;
@@ -318,6 +684,8 @@ fn query_top_funcs(
     end: UtcTimestamp,
     sort_field: SortField,
     filter: String,
+    simplify_names: bool,
+    granularity: Granularity,
 ) -> TopFuncs {
     let Some(duration) = end.checked_sub(start) else {
         return TopFuncs::default();
@@ -357,7 +725,7 @@ fn query_top_funcs(
     // Thread 3: pull in symbols.
     let (frame_tx, frame_rx) = mpsc::sync_channel(4096);
     let symb_task = tokio::task::spawn_blocking(move || {
-        let mut cache = lru::LruCache::new((16 * 1024).try_into().unwrap());
+        let mut mapper = LocationMapper::new(simplify_names, granularity);
         let mut new_trace = false;
 
         for (ts, frame, frame_idx, count) in raw_frame_rx {
@@ -365,28 +733,13 @@ fn query_top_funcs(
                 new_trace = true;
             }
 
-            for inline in cache
-                .get_or_insert(frame, || symbolize_frame(frame.into(), true))
-                .iter()
-                .rev()
-            {
-                let Some(ref func) = inline.func else {
-                    // Can't reasonably represent frames without function in this view.
-                    continue;
-                };
-
-                let location = Location {
-                    kind: frame.kind,
-                    func: func.to_owned(),
-                    file: inline.file.clone(),
-                };
-
+            for location in mapper.locations(frame.into()) {
                 if !filter.is_empty() && !location.matches_filter(&filter) {
                     continue;
                 }
 
                 frame_tx
-                    .send((ts, location, std::mem::take(&mut new_trace), count))
+                    .send((ts, location.clone(), std::mem::take(&mut new_trace), count))
                     .expect("should never be closed on RX side (3)");
             }
         }
@@ -445,6 +798,8 @@ fn query_top_funcs(
             .reverse()
             .then_with(|| lhs_loc.func.cmp(&rhs_loc.func))
             .then_with(|| lhs_loc.file.cmp(&rhs_loc.file))
+            .then_with(|| lhs_loc.line.cmp(&rhs_loc.line))
+            .then_with(|| lhs_loc.addr.cmp(&rhs_loc.addr))
     });
 
     // Truncate to reduce memory use after construction.
@@ -459,6 +814,269 @@ fn query_top_funcs(
     }
 }
 
+/// Maps frames to the locations that their samples are accounted to.
+struct LocationMapper {
+    simplify_names: bool,
+    granularity: Granularity,
+    placeholders: bool,
+    cache: lru::LruCache<Frame, Vec<Location>>,
+}
+
+impl LocationMapper {
+    fn new(simplify_names: bool, granularity: Granularity) -> Self {
+        LocationMapper {
+            simplify_names,
+            granularity,
+            placeholders: false,
+            cache: lru::LruCache::new((16 * 1024).try_into().unwrap()),
+        }
+    }
+
+    /// Map frames without a function name to a placeholder location instead
+    /// of skipping them.
+    fn with_placeholders(mut self) -> Self {
+        self.placeholders = true;
+        self
+    }
+
+    /// Locations of the given frame, innermost inline function first.
+    fn locations(&mut self, frame: Frame) -> &[Location] {
+        let (simplify_names, granularity) = (self.simplify_names, self.granularity);
+        let placeholders = self.placeholders;
+        self.cache.get_or_insert(frame, || {
+            let mut locations = frame_locations(frame, simplify_names, granularity);
+            if locations.is_empty() && placeholders {
+                locations.push(placeholder_location(frame, granularity));
+            }
+            locations
+        })
+    }
+}
+
+/// Location standing in for a frame without a function name.
+fn placeholder_location(frame: Frame, granularity: Granularity) -> Location {
+    Location {
+        kind: frame.kind,
+        func: format!("<unsymbolized> {}", executable_name(frame)),
+        file: None,
+        line: None,
+        addr: match granularity {
+            Granularity::Address => Some(frame.id.addr_or_line),
+            _ => None,
+        },
+    }
+}
+
+fn frame_locations(frame: Frame, simplify_names: bool, granularity: Granularity) -> Vec<Location> {
+    if granularity == Granularity::Executable {
+        return vec![Location {
+            kind: frame.kind,
+            func: executable_name(frame),
+            file: None,
+            line: None,
+            addr: None,
+        }];
+    }
+
+    symbolize_frame(frame, true)
+        .into_iter()
+        .rev()
+        .filter_map(|mut inline| {
+            granularity.strip_details(&mut inline);
+
+            // Can't reasonably represent frames without function in this view.
+            let func = inline.func?;
+
+            // Simplified names merge e.g. all instantiations of a template.
+            let func = if simplify_names {
+                simplify_name(&func)
+            } else {
+                func
+            };
+
+            Some(Location {
+                kind: frame.kind,
+                func,
+                file: inline.file,
+                line: inline.line_no,
+                addr: match granularity {
+                    Granularity::Address => Some(frame.id.addr_or_line),
+                    _ => None,
+                },
+            })
+        })
+        .collect()
+}
+
+/// Direct callers and callees of a function.
+#[derive(Debug, Default)]
+struct Neighbors {
+    /// Function the neighbors were queried for.
+    pub target: Option<Location>,
+    /// Number of samples with the function anywhere in the trace.
+    pub total: u64,
+    /// Number of samples with the function as the leaf.
+    pub zelf: u64,
+    pub callers: Vec<(Location, u64)>,
+    pub callees: Vec<(Location, u64)>,
+}
+
+/// Count the direct callers and callees of the given function.
+///
+/// Like in the main table, a sample is accounted to each caller or callee
+/// at most once, even if the function occurs multiple times in the trace.
+/// Unsymbolized frames are kept as placeholders, so that the function calling
+/// through one isn't reported as a direct caller.
+fn query_neighbors(
+    start: UtcTimestamp,
+    end: UtcTimestamp,
+    target: Location,
+    simplify_names: bool,
+    granularity: Granularity,
+) -> Neighbors {
+    let mut mapper = LocationMapper::new(simplify_names, granularity).with_placeholders();
+    let mut callers = HashMap::<Location, u64>::new();
+    let mut callees = HashMap::<Location, u64>::new();
+    let mut neighbors = Neighbors::default();
+    let mut stack = Vec::new();
+
+    for sampled in DB
+        .trace_events
+        .sample_events(SampleKind::Mixed, start, end)
+        .into_values()
+    {
+        // Leaf (innermost inline function) first.
+        stack.clear();
+        for frame in sampled.trace {
+            stack.extend_from_slice(mapper.locations(frame));
+        }
+
+        if !stack.contains(&target) {
+            continue;
+        }
+
+        neighbors.total += sampled.count;
+        if stack[0] == target {
+            neighbors.zelf += sampled.count;
+        }
+
+        let mut seen = HashSet::new();
+        for (i, location) in stack.iter().enumerate() {
+            if *location != target {
+                continue;
+            }
+
+            if let Some(caller) = stack.get(i + 1) {
+                if seen.insert((true, caller)) {
+                    *callers.entry(caller.clone()).or_default() += sampled.count;
+                }
+            }
+            if let Some(callee) = i.checked_sub(1).map(|x| &stack[x]) {
+                if seen.insert((false, callee)) {
+                    *callees.entry(callee.clone()).or_default() += sampled.count;
+                }
+            }
+        }
+    }
+
+    let sorted = |map: HashMap<Location, u64>| {
+        let mut list: Vec<_> = map.into_iter().collect();
+        list.sort_unstable_by(|(lhs_loc, lhs), (rhs_loc, rhs)| {
+            rhs.cmp(lhs).then_with(|| lhs_loc.func.cmp(&rhs_loc.func))
+        });
+        list.truncate(MAX_NEIGHBORS);
+        list
+    };
+
+    neighbors.callers = sorted(callers);
+    neighbors.callees = sorted(callees);
+    neighbors.target = Some(target);
+    neighbors
+}
+
+/// Per time bucket sample counts of the charted functions.
+#[derive(Debug, Default)]
+struct FunctionShares {
+    /// Functions the counts were queried for.
+    pub targets: Vec<Location>,
+    /// Start of each bucket.
+    pub times: Vec<UtcTimestamp>,
+    /// Number of samples in each bucket.
+    pub samples: Vec<u64>,
+    /// Per target and bucket: samples with the function as the leaf and
+    /// samples with the function anywhere in the trace.
+    pub counts: Vec<Vec<(u64, u64)>>,
+}
+
+/// Count the samples of each target function per time bucket.
+fn query_function_shares(
+    start: UtcTimestamp,
+    end: UtcTimestamp,
+    targets: Vec<Location>,
+    simplify_names: bool,
+    granularity: Granularity,
+) -> FunctionShares {
+    let duration = end.saturating_sub(start).max(1);
+    let step = (duration / SHARE_BUCKETS).max(1);
+    let num_buckets = (duration / step + 1) as usize;
+
+    let mut shares = FunctionShares {
+        targets: Vec::new(),
+        times: (0..num_buckets as u64).map(|i| start + i * step).collect(),
+        samples: vec![0; num_buckets],
+        counts: vec![vec![(0, 0); num_buckets]; targets.len()],
+    };
+
+    let mut mapper = LocationMapper::new(simplify_names, granularity);
+    let mut matches = HashMap::new();
+    for (id, tc) in DB.trace_events.time_range(start, end, SampleKind::Mixed) {
+        let tc = tc.get();
+        let count = u64::from(tc.count);
+        let bucket = ((id.timestamp.saturating_sub(start) / step) as usize).min(num_buckets - 1);
+        shares.samples[bucket] += count;
+
+        let trace_matches = matches
+            .entry(tc.trace_hash)
+            .or_insert_with(|| trace_matches(&mut mapper, tc.trace_hash, &targets));
+        for &(i, is_leaf) in trace_matches.iter() {
+            let counts = &mut shares.counts[i][bucket];
+            if is_leaf {
+                counts.0 += count;
+            }
+            counts.1 += count;
+        }
+    }
+
+    shares.targets = targets;
+    shares
+}
+
+/// Indices of the targets occurring in the trace and whether they are its leaf.
+fn trace_matches(
+    mapper: &mut LocationMapper,
+    trace_hash: TraceHash,
+    targets: &[Location],
+) -> Vec<(usize, bool)> {
+    let Some(trace) = DB.stack_traces.get(trace_hash) else {
+        return vec![];
+    };
+
+    let mut found: Vec<(usize, bool)> = Vec::new();
+    let mut is_leaf = true;
+    for frame in trace.get().iter() {
+        for location in mapper.locations((*frame).into()) {
+            for (i, target) in targets.iter().enumerate() {
+                if location == target && !found.iter().any(|x| x.0 == i) {
+                    found.push((i, is_leaf));
+                }
+            }
+            is_leaf = false;
+        }
+    }
+
+    found
+}
+
 fn hash(location: impl Hash) -> u64 {
     let mut hasher = DefaultHasher::new();
     location.hash(&mut hasher);
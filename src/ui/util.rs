@@ -15,10 +15,10 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use crate::storage::{FrameKind, InterpKind};
+use crate::storage::{error_spec_by_id, frame_kind_name, Frame, FrameKind, InterpKind};
 use eframe::emath::{GuiRounding as _, Pos2, Rect, Vec2};
 use eframe::epaint::{Color32, Stroke, StrokeKind};
-use egui::{Align2, Button, FontId, TextEdit, Ui};
+use egui::{Align, Align2, Button, FontId, Layout, TextEdit, Ui};
 use egui_phosphor::regular as icons;
 use std::fmt;
 
@@ -84,11 +84,11 @@ pub fn clearable_line_edit_with_status(
 pub fn frame_kind_color(kind: FrameKind) -> Color32 {
     let interp = match kind {
         FrameKind::Regular(x) => x,
-        FrameKind::Error(_) => return Color32::from_rgb(0xfd, 0x84, 0x84),
+        FrameKind::Error(_) | FrameKind::UnknownError(_) => {
+            return Color32::from_rgb(0xfd, 0x84, 0x84)
+        }
         FrameKind::Abort => return Color32::from_rgb(0xfc, 0x4f, 0x4f),
-
-        // Intentionally horrible color to make it obvious that something is wrong:
-        FrameKind::Unknown(_) | FrameKind::UnknownError(_) => return Color32::RED,
+        FrameKind::Unknown(_) => return Color32::from_rgb(0xc8, 0xc8, 0xc8),
     };
 
     match interp {
@@ -109,6 +109,23 @@ pub fn frame_kind_color(kind: FrameKind) -> Color32 {
     }
 }
 
+/// Text for frames that carry an error code instead of a code location.
+pub fn error_frame_text(frame: Frame) -> Option<String> {
+    let what = match frame.kind {
+        FrameKind::Abort => "unwinding aborted".to_owned(),
+        FrameKind::Error(_) | FrameKind::UnknownError(_) => {
+            format!("{} error", frame_kind_name(frame.kind))
+        }
+        FrameKind::Regular(_) | FrameKind::Unknown(_) => return None,
+    };
+
+    let code = frame.id.addr_or_line;
+    Some(match error_spec_by_id(code) {
+        Some(spec) => format!("<{what}: {}>", spec.name),
+        None => format!("<{what}: error code {code}>"),
+    })
+}
+
 /// Format a count to a nice representation optimized for human readability.
 pub fn humanize_count(x: u64) -> HumanCount {
     if x > 10u64.pow(9) {
@@ -132,6 +149,27 @@ impl fmt::Display for HumanCount {
     }
 }
 
+/// Draws a sample count with a background color indicating its share of `max`.
+///
+/// Used for the gutters of the source and disassembly views.
+pub fn draw_heat_count(ui: &mut Ui, count: u64, max: u64, total_samples: u64) {
+    const CLR_HOT: Color32 = Color32::from_rgb(0xE0, 0x40, 0x20);
+
+    if count == 0 {
+        return;
+    }
+
+    let heat = count as f32 / max.max(1) as f32;
+    let color = CLR_HOT.gamma_multiply(heat.max(0.1));
+    ui.painter().rect_filled(ui.max_rect(), 0.0, color);
+
+    let perc = count as f64 / total_samples.max(1) as f64 * 100.0;
+    ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+        ui.label(humanize_count(count).to_string())
+            .on_hover_text(format!("{count} samples ({perc:.02}%)"));
+    });
+}
+
 /// Generate a nice color the same way as [`egui_plot`] does it.
 #[inline(always)] // should be `const`, but currently can't do float arith in const fn
 pub fn plot_color(idx: usize) -> Color32 {